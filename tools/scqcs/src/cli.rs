@@ -1,10 +1,12 @@
 // cli.rs — Command-line interface definitions (clap derive)
 //
 // Defines the top-level `scqcs` command and the `vbw` subcommand tree:
-//   scqcs vbw keygen   — generate Ed25519 keypair
+//   scqcs vbw keygen   — generate a signing keypair (Ed25519 or ECDSA P-256)
 //   scqcs vbw build    — run build + generate witness bundle
 //   scqcs vbw verify   — verify a witness bundle
+//   scqcs vbw verify-chain — check a release history for gaps, forks, broken links
 //   scqcs vbw attest   — add a co-signature to an existing bundle
+//   scqcs vbw transcript — replay a bundle's recorded build output
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
@@ -27,13 +29,35 @@ pub enum Commands {
     },
 }
 
+// Build carries most of the flags; the enum is parsed once, so its size
+// does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum VbwAction {
-    /// Generate an Ed25519 keypair for build signing
+    /// Generate a keypair for build signing
     Keygen {
         /// Output directory for key files (default: current directory)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Key algorithm: "ed25519" or "ecdsa-p256"
+        #[arg(long, default_value = "ed25519")]
+        key_type: String,
+
+        /// Also generate an ML-DSA-65 (post-quantum) key; bundles built with
+        /// the resulting hybrid key carry both signatures
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Encrypt the secret key file with a passphrase
+        /// (scrypt + XChaCha20-Poly1305)
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Read the passphrase from this file descriptor instead of
+        /// prompting (or SCQCS_VBW_KEY_PASSPHRASE)
+        #[arg(long, requires = "encrypt")]
+        passphrase_fd: Option<i32>,
     },
 
     /// Run a build command and generate a witness bundle
@@ -46,10 +70,20 @@ pub enum VbwAction {
         #[arg(long, default_value = "dist")]
         output_dir: String,
 
-        /// Path to Ed25519 secret key file
+        /// Path to secret key file
         #[arg(long)]
         keyfile: Option<PathBuf>,
 
+        /// External signer URI instead of a key file, e.g.
+        /// "pkcs11:token=ci;object=vbw-builder?module-path=/usr/lib/softhsm/libsofthsm2.so"
+        #[arg(long, conflicts_with = "keyfile")]
+        signer: Option<String>,
+
+        /// Read the key passphrase from this file descriptor instead of
+        /// prompting (or SCQCS_VBW_KEY_PASSPHRASE)
+        #[arg(long)]
+        passphrase_fd: Option<i32>,
+
         /// Key identifier string
         #[arg(long)]
         key_id: Option<String>,
@@ -58,6 +92,95 @@ pub enum VbwAction {
         #[arg(long)]
         policy: Option<String>,
 
+        /// RFC 3161 Time-Stamping Authority URL; the builder signature is
+        /// timestamped and the token stored in the bundle (overrides the
+        /// policy's signing.timestamp.tsa_url)
+        #[arg(long)]
+        tsa_url: Option<String>,
+
+        /// Previous release's bundle directory; links this build to it via
+        /// previous_manifest_hash and the next sequence number
+        #[arg(long)]
+        previous: Option<PathBuf>,
+
+        /// Run as a pipeline step: write a signed sub-witness to
+        /// vbw/steps/<STEP>/ instead of a bundle. A later build without
+        /// --step composes all steps into one bundle
+        #[arg(long, conflicts_with_all = ["previous", "tsa_url"])]
+        step: Option<String>,
+
+        /// Consume an earlier step's outputs: "<step>" for all of them, or
+        /// "<step>:<path>" for one (repeatable)
+        #[arg(long = "input", value_name = "STEP[:PATH]")]
+        inputs: Vec<String>,
+
+        /// A file taken from another project's VBW bundle, recorded as a
+        /// vbw-artifact material; it must match an artifact in the bundle's
+        /// outputs.json (repeatable)
+        #[arg(long, value_name = "BUNDLE:FILE", conflicts_with = "step")]
+        upstream: Vec<String>,
+
+        /// Trace the connections and DNS queries of the build's process
+        /// tree into network-access.json (Linux only; slows the build)
+        #[arg(long, conflicts_with = "step")]
+        trace_network: bool,
+
+        /// Trace the programs the build's process tree executes, recording
+        /// each one's path, SHA-256 and exec count among environment.json's
+        /// tools (Linux only; slows the build)
+        #[arg(long)]
+        trace_exec: bool,
+
+        /// Trace the files the build's process tree reads into inputs.json,
+        /// flagging files in the repository that git does not track
+        /// (Linux only; slows the build)
+        #[arg(long, conflicts_with = "step")]
+        trace_inputs: bool,
+
+        /// OCI image index, manifest or config of the build container
+        /// (e.g. from `skopeo inspect --raw`); its SHA-256 is recorded as
+        /// the image digest in environment.json
+        #[arg(long, value_name = "FILE")]
+        container_image: Option<PathBuf>,
+
+        /// Bind the builder key to a CI identity with an OIDC ID token
+        /// whose audience is derived from the public key: "github" requests
+        /// one from GitHub Actions (needs `permissions: id-token: write`),
+        /// anything else is a file holding a token minted for that audience
+        #[arg(long, value_name = "github|FILE", conflicts_with = "step")]
+        oidc: Option<String>,
+
+        /// Sign with a fresh Ed25519 key generated for this build and then
+        /// discarded; the OIDC token is the identity behind it
+        #[arg(long, requires = "oidc", conflicts_with_all = ["keyfile", "signer"])]
+        ephemeral_key: bool,
+
+        /// Verify the bundle after writing it, as `vbw verify` would, and
+        /// fail the build if it is UNVERIFIED
+        #[arg(long, conflicts_with = "step")]
+        verify: bool,
+
+        /// With --verify: wait this many seconds, then hash the output
+        /// directory again and fail if any output changed, appeared or
+        /// disappeared
+        #[arg(long, value_name = "SECONDS", requires = "verify")]
+        rehash_outputs_after: Option<u64>,
+
+        /// With --verify: the OIDC issuer's JWKS, to check the signature
+        /// of the build's own OIDC token as `vbw verify --oidc-jwks` would
+        #[arg(long, value_name = "FILE", requires = "verify")]
+        oidc_jwks: Option<PathBuf>,
+
+        /// Replace an existing bundle in vbw/; its co-signatures and
+        /// timestamp token are discarded
+        #[arg(long, conflicts_with = "step")]
+        force: bool,
+
+        /// If the build command fails, still write a signed bundle with
+        /// its transcript and exit status, then fail
+        #[arg(long, conflicts_with = "step")]
+        record_failure: bool,
+
         /// Build command (everything after --)
         #[arg(last = true, required = true)]
         cmd: Vec<String>,
@@ -68,6 +191,25 @@ pub enum VbwAction {
         /// Path to the VBW bundle directory
         #[arg(long, default_value = "vbw")]
         bundle: PathBuf,
+
+        /// Upstream bundle to check vbw-artifact materials against
+        /// (repeatable)
+        #[arg(long)]
+        upstream: Vec<PathBuf>,
+
+        /// JWKS of the OIDC issuer (e.g. a saved copy of
+        /// https://token.actions.githubusercontent.com/.well-known/jwks)
+        /// to check a bundle's OIDC token signature against
+        #[arg(long, value_name = "FILE")]
+        oidc_jwks: Option<PathBuf>,
+    },
+
+    /// Check a release history of chained bundles for gaps, forks and
+    /// broken links
+    VerifyChain {
+        /// Bundle directories, in any order
+        #[arg(required = true)]
+        bundles: Vec<PathBuf>,
     },
 
     /// Add a maintainer co-signature to a bundle
@@ -76,12 +218,43 @@ pub enum VbwAction {
         #[arg(long, default_value = "vbw")]
         bundle: PathBuf,
 
-        /// Path to Ed25519 secret key file
+        /// Path to secret key file
         #[arg(long)]
         keyfile: Option<PathBuf>,
 
+        /// External signer URI instead of a key file, e.g.
+        /// "pkcs11:token=ci;object=vbw-builder?module-path=/usr/lib/softhsm/libsofthsm2.so"
+        #[arg(long, conflicts_with = "keyfile")]
+        signer: Option<String>,
+
+        /// Read the key passphrase from this file descriptor instead of
+        /// prompting (or SCQCS_VBW_KEY_PASSPHRASE)
+        #[arg(long)]
+        passphrase_fd: Option<i32>,
+
         /// Key identifier for the attestor
         #[arg(long)]
         key_id: Option<String>,
+
+        /// What the attestor vouches for: "reviewed", "approved-for-release",
+        /// "deployed-to-prod", or a custom lowercase type
+        #[arg(long, default_value = "reviewed")]
+        statement: String,
+
+        /// Optional free-text comment recorded in the signed statement
+        #[arg(long)]
+        comment: Option<String>,
+    },
+
+    /// Write the build output recorded in a bundle's transcript to stdout,
+    /// byte for byte
+    Transcript {
+        /// Path to the VBW bundle directory (or a step in it)
+        #[arg(long, default_value = "vbw")]
+        bundle: PathBuf,
+
+        /// Only this stream: "stdout", "stderr", or "vbw" for vbw's notes
+        #[arg(long)]
+        stream: Option<String>,
     },
 }
@@ -91,6 +91,22 @@ pub fn source_worktree_hash() -> Result<String> {
     Ok(crate::hash::hex_encode(&result))
 }
 
+/// Absolute path of the repository's top-level directory.
+pub fn repo_root() -> Result<std::path::PathBuf> {
+    let output = run_git(&["rev-parse", "--show-toplevel"]).context("finding repo root")?;
+    Ok(std::path::PathBuf::from(output.trim()))
+}
+
+/// Paths of all tracked files, relative to the repository root.
+pub fn tracked_files() -> Result<std::collections::BTreeSet<String>> {
+    let output = run_git(&["ls-files", "-z", "--full-name", "--", ":/"]).context("git ls-files")?;
+    Ok(output
+        .split('\0')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
 /// Run a git command and return stdout as a String.
 fn run_git(args: &[&str]) -> Result<String> {
     let output = Command::new("git")
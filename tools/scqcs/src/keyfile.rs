@@ -0,0 +1,394 @@
+// keyfile.rs — Passphrase-encrypted secret key files
+//
+// `keygen --encrypt` wraps the secret key string (any format accepted by
+// `sign::keyset_from_secret`, including hybrid keys) in a JSON envelope:
+//
+//   {
+//     "scqcs_encrypted_key": "1.0",
+//     "kdf": "scrypt",
+//     "kdf_params": { "log_n": 17, "r": 8, "p": 1, "salt": "<b64, 16 bytes>" },
+//     "cipher": "xchacha20-poly1305",
+//     "nonce": "<b64, 24 bytes>",
+//     "ciphertext": "<b64>"
+//   }
+//
+// The passphrase is stretched with scrypt into a 256-bit key, which seals
+// the secret with XChaCha20-Poly1305. The KDF parameters and algorithm
+// names are bound as associated data, so tampering with the header fails
+// decryption instead of silently weakening it.
+//
+// Passphrase sources, checked in order:
+//   1. --passphrase-fd <N>        (read one line from an inherited descriptor)
+//   2. SCQCS_VBW_KEY_PASSPHRASE   (environment variable)
+//   3. Interactive prompt on the controlling terminal
+//
+// Every intermediate (passphrase, derived key, plaintext) is held in a
+// `Zeroizing` buffer and wiped on drop, on both success and error paths.
+//
+// REAL: Uses the RustCrypto scrypt and chacha20poly1305 implementations
+// with OS randomness for salt and nonce.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const ENVELOPE_VERSION: &str = "1.0";
+const KDF_SCRYPT: &str = "scrypt";
+const CIPHER_XCHACHA: &str = "xchacha20-poly1305";
+
+/// Environment variable holding the key passphrase (for CI).
+pub const PASSPHRASE_ENV: &str = "SCQCS_VBW_KEY_PASSPHRASE";
+
+/// Strongest scrypt work factor accepted on decryption (log2 N).
+const MAX_LOG_N: u8 = 20;
+
+/// Largest scrypt block size (r) and parallelism (p) accepted on decryption.
+const MAX_R: u32 = 32;
+const MAX_P: u32 = 16;
+
+/// Most memory an envelope may make scrypt allocate (128 * r * N bytes),
+/// so an attacker-supplied envelope can't exhaust memory: 1 GiB, which
+/// log_n=20 with the recommended r=8 reaches.
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedKey {
+    scqcs_encrypted_key: String,
+    kdf: String,
+    kdf_params: ScryptParams,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    #[serde(with = "b64_salt")]
+    pub salt: [u8; 16],
+}
+
+impl ScryptParams {
+    /// Default work factor for new key files (scrypt's recommended
+    /// log_n=17, r=8, p=1: ~128 MiB and a fraction of a second).
+    fn recommended() -> Self {
+        let mut salt = [0u8; 16];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        ScryptParams {
+            log_n: scrypt::Params::RECOMMENDED_LOG_N,
+            r: scrypt::Params::RECOMMENDED_R,
+            p: scrypt::Params::RECOMMENDED_P,
+            salt,
+        }
+    }
+}
+
+/// Where to read a passphrase from when one is needed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PassphraseSource {
+    /// Inherited file descriptor to read the passphrase from.
+    pub fd: Option<i32>,
+}
+
+impl PassphraseSource {
+    /// Read the passphrase. `confirm` asks twice when prompting (keygen).
+    pub fn read(&self, purpose: &str, confirm: bool) -> Result<Zeroizing<String>> {
+        if let Some(fd) = self.fd {
+            // The build command must not inherit the descriptor
+            #[cfg(unix)]
+            set_cloexec(fd).with_context(|| format!("closing file descriptor {} on exec", fd))?;
+            // /dev/fd/N avoids taking ownership of a raw descriptor
+            let path = format!("/dev/fd/{}", fd);
+            let contents = Zeroizing::new(
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("reading passphrase from file descriptor {}", fd))?,
+            );
+            let line = contents.lines().next().unwrap_or("");
+            return non_empty(Zeroizing::new(line.to_string()));
+        }
+
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+            return non_empty(Zeroizing::new(passphrase));
+        }
+
+        let passphrase = Zeroizing::new(
+            rpassword::prompt_password(format!("Passphrase for {}: ", purpose)).context(
+                "no passphrase available: use a terminal, --passphrase-fd, or SCQCS_VBW_KEY_PASSPHRASE",
+            )?,
+        );
+        if confirm {
+            let again = Zeroizing::new(rpassword::prompt_password("Confirm passphrase: ")?);
+            if *again != *passphrase {
+                bail!("passphrases do not match");
+            }
+        }
+        non_empty(passphrase)
+    }
+}
+
+/// Mark `fd` close-on-exec, so no child process inherits it.
+#[cfg(unix)]
+fn set_cloexec(fd: i32) -> std::io::Result<()> {
+    // SAFETY: fcntl on a caller-supplied descriptor only changes its flags
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags == -1 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn non_empty(passphrase: Zeroizing<String>) -> Result<Zeroizing<String>> {
+    if passphrase.is_empty() {
+        bail!("empty passphrase");
+    }
+    Ok(passphrase)
+}
+
+/// True if `contents` is an encrypted key envelope rather than a plain key.
+/// Plain keys are base64 or "<tag>:<base64>", which never start with '{'.
+pub fn is_encrypted(contents: &str) -> bool {
+    contents.trim_start().starts_with('{')
+}
+
+/// Encrypt a secret key string with the recommended scrypt work factor.
+/// Returns the pretty-printed JSON envelope.
+pub fn encrypt(secret_key: &str, passphrase: &str) -> Result<String> {
+    encrypt_with_params(secret_key, passphrase, ScryptParams::recommended())
+}
+
+fn encrypt_with_params(secret_key: &str, passphrase: &str, params: ScryptParams) -> Result<String> {
+    let key = derive_key(passphrase, &params)?;
+    let mut nonce = [0u8; 24];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let cipher = XChaCha20Poly1305::new(&(*key).into());
+    let aad = associated_data(&params);
+    let ciphertext = cipher
+        .encrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: secret_key.as_bytes(),
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("encrypting secret key failed"))?;
+
+    let envelope = EncryptedKey {
+        scqcs_encrypted_key: ENVELOPE_VERSION.to_string(),
+        kdf: KDF_SCRYPT.to_string(),
+        kdf_params: params,
+        cipher: CIPHER_XCHACHA.to_string(),
+        nonce: B64.encode(nonce),
+        ciphertext: B64.encode(ciphertext),
+    };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// Decrypt an envelope produced by `encrypt`. A wrong passphrase and a
+/// tampered file are indistinguishable and both fail.
+pub fn decrypt(contents: &str, passphrase: &str) -> Result<Zeroizing<String>> {
+    let envelope: EncryptedKey =
+        serde_json::from_str(contents).context("parsing encrypted key file")?;
+    if envelope.scqcs_encrypted_key != ENVELOPE_VERSION {
+        bail!(
+            "unsupported encrypted key version '{}'",
+            envelope.scqcs_encrypted_key
+        );
+    }
+    if envelope.kdf != KDF_SCRYPT || envelope.cipher != CIPHER_XCHACHA {
+        bail!(
+            "unsupported encrypted key format ({} / {})",
+            envelope.kdf,
+            envelope.cipher
+        );
+    }
+    if envelope.kdf_params.log_n > MAX_LOG_N {
+        bail!(
+            "scrypt log_n {} exceeds the supported maximum of {}",
+            envelope.kdf_params.log_n,
+            MAX_LOG_N
+        );
+    }
+    let ScryptParams { log_n, r, p, .. } = envelope.kdf_params;
+    let memory = (128 * u64::from(r)) << log_n;
+    if r > MAX_R || p > MAX_P {
+        bail!(
+            "scrypt r={} p={} exceeds the supported maximum of r={} p={}",
+            r,
+            p,
+            MAX_R,
+            MAX_P
+        );
+    }
+    if memory > MAX_SCRYPT_MEMORY {
+        bail!(
+            "scrypt log_n={} r={} needs {} MiB, which exceeds the supported maximum of {} MiB",
+            log_n,
+            r,
+            memory >> 20,
+            MAX_SCRYPT_MEMORY >> 20
+        );
+    }
+
+    let nonce: [u8; 24] = B64
+        .decode(&envelope.nonce)
+        .context("decoding nonce")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("nonce must be 24 bytes"))?;
+    let ciphertext = B64
+        .decode(&envelope.ciphertext)
+        .context("decoding ciphertext")?;
+
+    let key = derive_key(passphrase, &envelope.kdf_params)?;
+    let cipher = XChaCha20Poly1305::new(&(*key).into());
+    let aad = associated_data(&envelope.kdf_params);
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                &XNonce::from(nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted key file"))?,
+    );
+
+    let secret = std::str::from_utf8(&plaintext).context("decrypted key is not valid UTF-8")?;
+    Ok(Zeroizing::new(secret.to_string()))
+}
+
+/// Stretch the passphrase into a 256-bit key.
+fn derive_key(passphrase: &str, params: &ScryptParams) -> Result<Zeroizing<[u8; 32]>> {
+    let scrypt_params = scrypt::Params::new(params.log_n, params.r, params.p)
+        .map_err(|_| anyhow::anyhow!("invalid scrypt parameters"))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(
+        passphrase.as_bytes(),
+        &params.salt,
+        &scrypt_params,
+        key.as_mut(),
+    )
+    .map_err(|_| anyhow::anyhow!("scrypt key derivation failed"))?;
+    Ok(key)
+}
+
+/// Header fields bound to the ciphertext.
+fn associated_data(params: &ScryptParams) -> Vec<u8> {
+    format!(
+        "scqcs_encrypted_key={};kdf={};log_n={};r={};p={};cipher={}",
+        ENVELOPE_VERSION, KDF_SCRYPT, params.log_n, params.r, params.p, CIPHER_XCHACHA
+    )
+    .into_bytes()
+}
+
+mod b64_salt {
+    use super::B64;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(salt: &[u8; 16], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&B64.encode(salt))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 16], D::Error> {
+        let encoded = String::deserialize(d)?;
+        B64.decode(encoded)
+            .map_err(serde::de::Error::custom)?
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("salt must be 16 bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap work factor so tests don't spend 128 MiB per derivation.
+    fn test_params() -> ScryptParams {
+        ScryptParams {
+            log_n: 10,
+            r: 8,
+            p: 1,
+            salt: [9u8; 16],
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn passphrase_fd_is_not_inherited() {
+        use std::io::Write;
+        use std::os::unix::io::AsRawFd;
+
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"hunter2\n").unwrap();
+        let fd = file.as_raw_fd();
+        // Make it inheritable, as a shell redirection would
+        unsafe { libc::fcntl(fd, libc::F_SETFD, 0) };
+        // Read through /dev/fd/N, which reopens from the start
+        let passphrase = PassphraseSource { fd: Some(fd) }
+            .read("test", false)
+            .unwrap();
+        assert_eq!(passphrase.as_str(), "hunter2");
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        assert_ne!(flags & libc::FD_CLOEXEC, 0);
+    }
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let (sk, _) = crate::sign::keygen();
+        let envelope = encrypt_with_params(&sk, "correct horse", test_params()).unwrap();
+        assert!(is_encrypted(&envelope));
+        assert!(!is_encrypted(&sk));
+        assert!(!envelope.contains(&sk));
+
+        assert_eq!(*decrypt(&envelope, "correct horse").unwrap(), sk);
+        assert!(decrypt(&envelope, "wrong horse").is_err());
+    }
+
+    #[test]
+    fn tampered_kdf_params_fail() {
+        let envelope = encrypt_with_params("secret", "pw", test_params()).unwrap();
+        // Lowering the work factor changes the derived key and the AAD
+        let weakened = envelope.replace("\"log_n\": 10", "\"log_n\": 9");
+        assert_ne!(weakened, envelope);
+        assert!(decrypt(&weakened, "pw").is_err());
+    }
+
+    #[test]
+    fn excessive_work_factor_rejected() {
+        let envelope = encrypt_with_params("secret", "pw", test_params()).unwrap();
+        let hostile = envelope.replace("\"log_n\": 10", "\"log_n\": 30");
+        let err = decrypt(&hostile, "pw").unwrap_err();
+        assert!(err.to_string().contains("exceeds"));
+    }
+
+    #[test]
+    fn excessive_block_size_or_parallelism_rejected() {
+        let envelope = encrypt_with_params("secret", "pw", test_params()).unwrap();
+        for replacements in [
+            &[("\"r\": 8", "\"r\": 4096")][..],
+            &[("\"p\": 1", "\"p\": 1000000")],
+            // Each within bounds, but together 128 * 32 * 2^20 bytes = 4 GiB
+            &[
+                ("\"r\": 8", "\"r\": 32"),
+                ("\"log_n\": 10", "\"log_n\": 20"),
+            ],
+        ] {
+            let mut hostile = envelope.clone();
+            for (from, to) in replacements {
+                hostile = hostile.replace(from, to);
+            }
+            assert_ne!(hostile, envelope);
+            let err = decrypt(&hostile, "pw").unwrap_err();
+            assert!(err.to_string().contains("exceeds"), "{:#}", err);
+        }
+    }
+}
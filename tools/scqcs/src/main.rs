@@ -6,10 +6,16 @@
 mod cli;
 mod git;
 mod hash;
+mod keyfile;
+mod oidc;
+mod pkcs11;
 mod sign;
+#[cfg(unix)]
+mod ssh_agent;
+mod tsa;
 mod vbw;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -21,48 +27,151 @@ fn main() -> Result<()> {
 
     match cli.command {
         Commands::Vbw { action } => match action {
-            VbwAction::Keygen { output } => cmd_keygen(output),
+            VbwAction::Keygen {
+                output,
+                key_type,
+                hybrid,
+                encrypt,
+                passphrase_fd,
+            } => cmd_keygen(
+                output,
+                &key_type,
+                hybrid,
+                encrypt.then_some(keyfile::PassphraseSource { fd: passphrase_fd }),
+            ),
             VbwAction::Build {
                 project,
                 output_dir,
                 keyfile,
+                signer,
+                passphrase_fd,
                 key_id,
                 policy,
+                tsa_url,
+                previous,
+                step,
+                inputs,
+                upstream,
+                trace_network,
+                trace_exec,
+                trace_inputs,
+                container_image,
+                oidc,
+                ephemeral_key,
+                verify,
+                rehash_outputs_after,
+                oidc_jwks,
+                force,
+                record_failure,
                 cmd,
-            } => vbw::build::run_build(
-                &cmd,
-                project.as_deref(),
-                Some(&output_dir),
-                keyfile.as_deref(),
-                key_id.as_deref(),
-                policy.as_deref(),
-            ),
-            VbwAction::Verify { bundle } => {
-                let verdict = vbw::verify::run_verify(&bundle)?;
+            } => vbw::build::run_build(&vbw::build::BuildOptions {
+                build_cmd: &cmd,
+                project_name: project.as_deref(),
+                output_dir: Some(&output_dir),
+                key_source: sign::KeySource {
+                    keyfile: keyfile.as_deref(),
+                    signer: signer.as_deref(),
+                    passphrase: keyfile::PassphraseSource { fd: passphrase_fd },
+                },
+                key_id: key_id.as_deref(),
+                policy_path: policy.as_deref(),
+                tsa_url: tsa_url.as_deref(),
+                previous_bundle: previous.as_deref(),
+                step: step.as_deref(),
+                inputs: &inputs,
+                upstream: &upstream,
+                trace_network,
+                trace_exec,
+                trace_inputs,
+                container_image: container_image.as_deref(),
+                oidc: oidc.as_deref(),
+                ephemeral_key,
+                verify,
+                rehash_outputs_after: rehash_outputs_after.map(std::time::Duration::from_secs),
+                oidc_jwks: oidc_jwks.as_deref(),
+                force,
+                record_failure,
+            }),
+            VbwAction::Verify {
+                bundle,
+                upstream,
+                oidc_jwks,
+            } => {
+                let verdict = vbw::verify::run_verify(
+                    &bundle,
+                    &vbw::verify::VerifyOptions {
+                        upstream_dirs: &upstream,
+                        oidc_jwks: oidc_jwks.as_deref(),
+                    },
+                )?;
                 match verdict {
                     vbw::verify::Verdict::Verified => std::process::exit(0),
                     vbw::verify::Verdict::VerifiedWithVariance(_) => std::process::exit(0),
                     vbw::verify::Verdict::Unverified(_) => std::process::exit(1),
                 }
             }
+            VbwAction::VerifyChain { bundles } => match vbw::chain::run_verify_chain(&bundles)? {
+                vbw::verify::Verdict::Unverified(_) => std::process::exit(1),
+                _ => std::process::exit(0),
+            },
             VbwAction::Attest {
                 bundle,
                 keyfile,
+                signer,
+                passphrase_fd,
                 key_id,
-            } => cmd_attest(&bundle, keyfile.as_deref(), key_id.as_deref()),
+                statement,
+                comment,
+            } => cmd_attest(
+                &bundle,
+                &sign::KeySource {
+                    keyfile: keyfile.as_deref(),
+                    signer: signer.as_deref(),
+                    passphrase: keyfile::PassphraseSource { fd: passphrase_fd },
+                },
+                key_id.as_deref(),
+                &statement,
+                comment.as_deref(),
+            ),
+            VbwAction::Transcript { bundle, stream } => {
+                vbw::transcript::replay(&bundle, stream.as_deref(), std::io::stdout().lock())
+            }
         },
     }
 }
 
-fn cmd_keygen(output: Option<PathBuf>) -> Result<()> {
-    let (sk, pk) = sign::keygen();
+fn cmd_keygen(
+    output: Option<PathBuf>,
+    key_type: &str,
+    hybrid: bool,
+    encrypt: Option<keyfile::PassphraseSource>,
+) -> Result<()> {
+    let algorithm = sign::Algorithm::parse(key_type)?;
+    if algorithm.is_post_quantum() {
+        bail!(
+            "{} is only available as part of a hybrid key; use --hybrid",
+            algorithm.as_str()
+        );
+    }
+    let (sk, pk, pq_pk) = if hybrid {
+        let (sk, pk, pq_pk) = sign::keygen_hybrid(algorithm);
+        (sk, pk, Some(pq_pk))
+    } else {
+        let (sk, pk) = sign::keygen_with(algorithm);
+        (sk, pk, None)
+    };
     let dir = output.unwrap_or_else(|| PathBuf::from("."));
     fs::create_dir_all(&dir)?;
 
     let sk_path = dir.join("vbw-builder.sk");
     let pk_path = dir.join("vbw-builder.pk");
 
-    fs::write(&sk_path, &sk)?;
+    if let Some(passphrase) = encrypt {
+        let pass = passphrase.read("new secret key", true)?;
+        fs::write(&sk_path, keyfile::encrypt(&sk, &pass)?)?;
+    } else {
+        fs::write(&sk_path, &sk)?;
+    }
     // Restrict secret key file permissions to owner-only (0600) on Unix.
     // Prevents other users on the system from reading the signing key.
     #[cfg(unix)]
@@ -72,26 +181,76 @@ fn cmd_keygen(output: Option<PathBuf>) -> Result<()> {
     }
     fs::write(&pk_path, &pk)?;
 
-    eprintln!("Ed25519 keypair generated:");
-    eprintln!("  Secret key: {}", sk_path.display());
-    eprintln!("  Public key: {}", pk_path.display());
+    if let Some(pq_pk) = &pq_pk {
+        let pq_pk_path = dir.join("vbw-builder.ml-dsa-65.pk");
+        fs::write(&pq_pk_path, pq_pk)?;
+        eprintln!(
+            "hybrid {} + ml-dsa-65 keypair generated:",
+            algorithm.as_str()
+        );
+        eprintln!("  Secret key: {} (both halves)", sk_path.display());
+        eprintln!("  Public key: {}", pk_path.display());
+        eprintln!("  Public key (ml-dsa-65): {}", pq_pk_path.display());
+    } else {
+        eprintln!("{} keypair generated:", algorithm.as_str());
+        eprintln!("  Secret key: {}", sk_path.display());
+        eprintln!("  Public key: {}", pk_path.display());
+    }
     eprintln!();
     eprintln!("Public key (base64): {}", pk);
     eprintln!();
-    eprintln!("SECURITY: Copy the secret key value to a secure location (e.g. CI secret),");
-    eprintln!("then verify the .sk file permissions are restricted.");
-    eprintln!("  SCQCS_VBW_ED25519_SK_B64=<contents of {}>", sk_path.display());
+    if encrypt.is_some() {
+        eprintln!("The secret key file is passphrase-encrypted. build and attest will prompt");
+        eprintln!(
+            "for the passphrase (or read --passphrase-fd / {}).",
+            keyfile::PASSPHRASE_ENV
+        );
+    } else {
+        eprintln!("SECURITY: Copy the secret key value to a secure location (e.g. CI secret),");
+        eprintln!("then verify the .sk file permissions are restricted.");
+        eprintln!(
+            "  SCQCS_VBW_ED25519_SK_B64=<contents of {}>",
+            sk_path.display()
+        );
+    }
 
     Ok(())
 }
 
 fn cmd_attest(
     bundle: &Path,
-    keyfile: Option<&std::path::Path>,
+    key_source: &sign::KeySource,
     key_id: Option<&str>,
+    statement: &str,
+    comment: Option<&str>,
 ) -> Result<()> {
-    let secret_key = sign::load_secret_key(keyfile)?;
-    let public_key = sign::public_key_from_secret(&secret_key)?;
+    // Statement types become part of policy rules, so keep them to a
+    // predictable charset: lowercase alphanumerics and hyphens.
+    if statement.is_empty()
+        || statement.len() > 64
+        || !statement
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        bail!(
+            "invalid statement type '{}': use lowercase letters, digits and hyphens (max 64)",
+            statement
+        );
+    }
+    if !vbw::model::KNOWN_STATEMENTS.contains(&statement) {
+        eprintln!(
+            "[vbw] Note: '{}' is not a standard statement type ({})",
+            statement,
+            vbw::model::KNOWN_STATEMENTS.join(", ")
+        );
+    }
+
+    let keys = sign::load_keyset(key_source)?;
+    if keys.post_quantum.is_some() {
+        bail!("hybrid keys are only supported for builder signatures; attest with a classical key");
+    }
+    let signer = keys.classical;
+    let public_key = signer.public_key()?;
     let resolved_key_id = key_id.unwrap_or("maintainer@local");
 
     // Read manifest, parse, and sign canonical bytes (consistent with build + verify)
@@ -100,16 +259,63 @@ fn cmd_attest(
     let manifest: vbw::model::Manifest =
         serde_json::from_str(&manifest_json).context("parsing manifest.json")?;
     let canonical_bytes = vbw::canonical::canonical_manifest_bytes(&manifest);
-    let signature = sign::sign(&secret_key, &canonical_bytes)?;
 
-    // Write co-signature
+    let (public_key_ed25519, public_key_other) =
+        vbw::model::public_key_fields(signer.algorithm(), public_key.clone());
+    let mut cosignature = vbw::model::Cosignature {
+        cosignature_version: "1.0".to_string(),
+        key_id: resolved_key_id.to_string(),
+        key_type: Some(signer.algorithm()),
+        public_key_ed25519,
+        public_key: public_key_other,
+        signed_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        statement: statement.to_string(),
+        comment: comment.map(|c| c.to_string()),
+        manifest_hash: hash::sha256_hex(&canonical_bytes),
+        signature: String::new(),
+    };
+    let payload = vbw::canonical::cosignature_signing_bytes(&canonical_bytes, &cosignature);
+    cosignature.signature = signer.sign(&payload)?;
+
+    // Write co-signature statement
     let sig_dir = bundle.join("signatures");
     fs::create_dir_all(&sig_dir)?;
 
-    // Sanitize key_id for use as a filename: whitelist alphanumeric, hyphen,
-    // underscore, and dot. Replace all other characters (including path separators,
-    // shell metacharacters, and control characters) with underscore.
-    let sanitized_id: String = resolved_key_id
+    let sig_path = sig_dir.join(cosignature_filename(resolved_key_id, statement));
+    if sig_path.exists() {
+        eprintln!(
+            "[vbw] Replacing this key's earlier '{}' statement",
+            statement
+        );
+    }
+    fs::write(&sig_path, serde_json::to_string_pretty(&cosignature)?)?;
+
+    eprintln!("[vbw] Attestation added:");
+    eprintln!("  Key ID: {}", resolved_key_id);
+    eprintln!("  Public key: {}", public_key);
+    eprintln!("  Statement: {}", statement);
+    if let Some(comment) = comment {
+        eprintln!("  Comment: {}", comment);
+    }
+    eprintln!(
+        "  Signature (over canonical manifest bytes + statement): {}",
+        sig_path.display()
+    );
+
+    Ok(())
+}
+
+/// File name of a co-signature statement. It includes the statement type,
+/// so one key can attest several statements without overwriting them.
+fn cosignature_filename(key_id: &str, statement: &str) -> String {
+    format!("{}.{}.cosig.json", sanitize_key_id(key_id), statement)
+}
+
+/// Sanitize a key_id for use as a filename: whitelist alphanumeric, hyphen,
+/// underscore, and dot. Replace all other characters (including path
+/// separators, shell metacharacters, and control characters) with underscore.
+fn sanitize_key_id(key_id: &str) -> String {
+    let sanitized_id: String = key_id
         .chars()
         .map(|c| {
             if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
@@ -121,7 +327,7 @@ fn cmd_attest(
         .collect();
 
     // Reject empty or dot-only filenames that could cause filesystem issues
-    let sanitized_id = if sanitized_id.is_empty()
+    if sanitized_id.is_empty()
         || sanitized_id == "."
         || sanitized_id == ".."
         || sanitized_id.starts_with('.')
@@ -129,19 +335,22 @@ fn cmd_attest(
         format!("key_{}", sanitized_id)
     } else {
         sanitized_id
-    };
-
-    let sig_filename = format!("{}.ed25519.sig", sanitized_id);
-    let sig_path = sig_dir.join(&sig_filename);
-    fs::write(&sig_path, &signature)?;
+    }
+}
 
-    eprintln!("[vbw] Attestation added:");
-    eprintln!("  Key ID: {}", resolved_key_id);
-    eprintln!("  Public key: {}", public_key);
-    eprintln!(
-        "  Signature (over canonical manifest bytes): {}",
-        sig_path.display()
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    #[test]
+    fn cosignature_filenames_differ_per_statement() {
+        assert_eq!(
+            cosignature_filename("maintainer@org", "reviewed"),
+            "maintainer_org.reviewed.cosig.json"
+        );
+        assert_ne!(
+            cosignature_filename("maintainer@org", "reviewed"),
+            cosignature_filename("maintainer@org", "approved-for-release")
+        );
+    }
 }
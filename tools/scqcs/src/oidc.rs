@@ -0,0 +1,398 @@
+// oidc.rs — OpenID Connect ID tokens binding a builder key to a CI identity
+//
+// A CI system can mint a short-lived ID token (a JWT signed by the
+// provider) naming the repository, ref, workflow and commit a job runs
+// for. Build requests one whose audience is derived from the builder
+// public key (`key_audience`), so the token vouches for that key and no
+// other, and stores it in the bundle as signatures/builder.oidc.jwt.
+//
+// Verify checks the token's signature against a JWKS file supplied by the
+// verifier, e.g. a saved copy of
+// https://token.actions.githubusercontent.com/.well-known/jwks, so it
+// works offline. Whichever keys the file holds are trusted; there is no
+// discovery or key rotation.
+//
+// GitHub Actions tokens are requested from the job's runtime
+// (ACTIONS_ID_TOKEN_REQUEST_URL and ACTIONS_ID_TOKEN_REQUEST_TOKEN, set
+// when the workflow has `permissions: id-token: write`). Other providers'
+// tokens are read from a file, and must already carry the key's audience.
+//
+// Supported signature algorithms: RS256 (GitHub, GitLab and most
+// providers) and ES256.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD as B64, URL_SAFE_NO_PAD as B64URL};
+use base64::Engine;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::io::Read;
+use std::time::Duration;
+
+const MAX_RESPONSE_LEN: u64 = 64 * 1024;
+
+/// The claims of an ID token that VBW records. Provider-specific claims
+/// absent from a token are None.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    pub iss: String,
+    pub sub: String,
+    aud: Audience,
+    pub exp: i64,
+    pub iat: i64,
+    pub nbf: Option<i64>,
+    pub repository: Option<String>,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub workflow: Option<String>,
+    pub sha: Option<String>,
+}
+
+/// `aud` is a single string or a list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Claims {
+    /// Whether `audience` is one of the token's audiences.
+    pub fn has_audience(&self, audience: &str) -> bool {
+        match &self.aud {
+            Audience::One(aud) => aud == audience,
+            Audience::Many(auds) => auds.iter().any(|a| a == audience),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+/// A decoded ID token. Its signature is only checked by `verify`.
+#[derive(Debug)]
+pub struct IdToken {
+    header: Header,
+    pub claims: Claims,
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    // RSA
+    n: Option<String>,
+    e: Option<String>,
+    // EC
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+/// The audience that binds a token to a builder public key (base64, as in
+/// the manifest): "vbw:sha256:<hex of the decoded key>".
+pub fn key_audience(public_key_b64: &str) -> Result<String> {
+    let key = B64
+        .decode(public_key_b64)
+        .context("decoding builder public key")?;
+    Ok(format!("vbw:sha256:{}", crate::hash::sha256_hex(&key)))
+}
+
+/// Request an ID token for `audience` from the GitHub Actions runtime.
+pub fn request_github_token(audience: &str) -> Result<String> {
+    let (Ok(url), Ok(bearer)) = (
+        std::env::var("ACTIONS_ID_TOKEN_REQUEST_URL"),
+        std::env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN"),
+    ) else {
+        bail!(
+            "no GitHub Actions ID token available: ACTIONS_ID_TOKEN_REQUEST_URL is not set \
+             (outside GitHub Actions, or the workflow lacks `permissions: id-token: write`)"
+        );
+    };
+
+    #[derive(Deserialize)]
+    struct Reply {
+        value: String,
+    }
+    let response = ureq::get(&url)
+        .query("audience", audience)
+        .set("Authorization", &format!("Bearer {}", bearer))
+        .timeout(Duration::from_secs(30))
+        .call()
+        .context("requesting an ID token from GitHub Actions")?;
+    let reply: Reply = serde_json::from_reader(response.into_reader().take(MAX_RESPONSE_LEN))
+        .context("parsing the GitHub Actions ID token response")?;
+    Ok(reply.value)
+}
+
+/// Decode a compact JWS ID token without checking its signature.
+pub fn decode(token: &str) -> Result<IdToken> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    let [header_b64, claims_b64, signature_b64] = parts.as_slice() else {
+        bail!("ID token is not a compact JWT (expected 3 dot-separated parts)");
+    };
+    let header: Header = serde_json::from_slice(&b64url(header_b64, "header")?)
+        .context("parsing ID token header")?;
+    if header.alg != "RS256" && header.alg != "ES256" {
+        bail!(
+            "unsupported ID token algorithm '{}' (expected RS256 or ES256)",
+            header.alg
+        );
+    }
+    let claims: Claims = serde_json::from_slice(&b64url(claims_b64, "claims")?)
+        .context("parsing ID token claims")?;
+    Ok(IdToken {
+        header,
+        claims,
+        signing_input: format!("{}.{}", header_b64, claims_b64),
+        signature: b64url(signature_b64, "signature")?,
+    })
+}
+
+fn b64url(part: &str, what: &str) -> Result<Vec<u8>> {
+    B64URL
+        .decode(part)
+        .with_context(|| format!("decoding ID token {} (base64url)", what))
+}
+
+/// Verify the token's signature with a key from `jwks_json`, a JSON Web
+/// Key Set. A token with a `kid` is only checked against the key with
+/// that id.
+pub fn verify(token: &IdToken, jwks_json: &str) -> Result<()> {
+    let jwks: Jwks = serde_json::from_str(jwks_json).context("parsing JWKS")?;
+    let candidates: Vec<&Jwk> = jwks
+        .keys
+        .iter()
+        .filter(|key| match (&token.header.kid, &key.kid) {
+            (Some(want), Some(have)) => want == have,
+            _ => true,
+        })
+        .collect();
+    if candidates.is_empty() {
+        bail!(
+            "no key in the JWKS has the token's kid '{}'",
+            token.header.kid.as_deref().unwrap_or_default()
+        );
+    }
+    for key in candidates {
+        if verify_with_key(token, key)? {
+            return Ok(());
+        }
+    }
+    bail!("ID token signature INVALID");
+}
+
+/// Check the signature with one JWK. Keys of another type than the
+/// token's algorithm are skipped (false).
+fn verify_with_key(token: &IdToken, key: &Jwk) -> Result<bool> {
+    let input = token.signing_input.as_bytes();
+    match (token.header.alg.as_str(), key.kty.as_str()) {
+        ("RS256", "RSA") => {
+            use rsa::signature::Verifier as _;
+            let (Some(n), Some(e)) = (&key.n, &key.e) else {
+                bail!("RSA key in JWKS lacks n or e");
+            };
+            let key = rsa::RsaPublicKey::new(
+                rsa::BigUint::from_bytes_be(&b64url(n, "JWK n")?),
+                rsa::BigUint::from_bytes_be(&b64url(e, "JWK e")?),
+            )
+            .context("invalid RSA key in JWKS")?;
+            let Ok(signature) = rsa::pkcs1v15::Signature::try_from(token.signature.as_slice())
+            else {
+                return Ok(false);
+            };
+            Ok(rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key)
+                .verify(input, &signature)
+                .is_ok())
+        }
+        ("ES256", "EC") if key.crv.as_deref() == Some("P-256") => {
+            use p256::ecdsa::signature::Verifier as _;
+            let (Some(x), Some(y)) = (&key.x, &key.y) else {
+                bail!("EC key in JWKS lacks x or y");
+            };
+            let (x, y) = (b64url(x, "JWK x")?, b64url(y, "JWK y")?);
+            if x.len() != 32 || y.len() != 32 {
+                bail!("P-256 key in JWKS has malformed coordinates");
+            }
+            let point = p256::EncodedPoint::from_affine_coordinates(
+                p256::FieldBytes::from_slice(&x),
+                p256::FieldBytes::from_slice(&y),
+                false,
+            );
+            let key = p256::ecdsa::VerifyingKey::from_encoded_point(&point)
+                .map_err(|_| anyhow::anyhow!("invalid P-256 key in JWKS"))?;
+            let Ok(signature) = p256::ecdsa::Signature::from_slice(&token.signature) else {
+                return Ok(false);
+            };
+            Ok(key.verify(input, &signature).is_ok())
+        }
+        _ => Ok(false),
+    }
+}
+
+/// A minimal in-process OIDC issuer (ES256 or RS256) for tests.
+#[cfg(test)]
+pub mod stand_in {
+    use super::*;
+
+    enum Key {
+        Es256(p256::ecdsa::SigningKey),
+        Rs256(Box<rsa::RsaPrivateKey>),
+    }
+
+    pub struct StandInIssuer {
+        key: Key,
+        kid: String,
+    }
+
+    impl StandInIssuer {
+        pub fn es256() -> Self {
+            StandInIssuer {
+                key: Key::Es256(p256::ecdsa::SigningKey::random(&mut rand::rngs::OsRng)),
+                kid: "stand-in-es256".to_string(),
+            }
+        }
+
+        pub fn rs256() -> Self {
+            // Small key: generating one is slow in debug builds
+            let key = rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, 1024).unwrap();
+            StandInIssuer {
+                key: Key::Rs256(Box::new(key)),
+                kid: "stand-in-rs256".to_string(),
+            }
+        }
+
+        /// The issuer's public key as a JWKS document.
+        pub fn jwks(&self) -> String {
+            let jwk = match &self.key {
+                Key::Es256(key) => {
+                    let point = key.verifying_key().to_encoded_point(false);
+                    serde_json::json!({
+                        "kty": "EC",
+                        "crv": "P-256",
+                        "kid": self.kid,
+                        "x": B64URL.encode(point.x().unwrap()),
+                        "y": B64URL.encode(point.y().unwrap()),
+                    })
+                }
+                Key::Rs256(key) => {
+                    use rsa::traits::PublicKeyParts;
+                    serde_json::json!({
+                        "kty": "RSA",
+                        "kid": self.kid,
+                        "n": B64URL.encode(key.n().to_bytes_be()),
+                        "e": B64URL.encode(key.e().to_bytes_be()),
+                    })
+                }
+            };
+            serde_json::json!({ "keys": [jwk] }).to_string()
+        }
+
+        /// Sign `claims` into a compact JWT.
+        pub fn mint(&self, claims: &serde_json::Value) -> String {
+            let alg = match self.key {
+                Key::Es256(_) => "ES256",
+                Key::Rs256(_) => "RS256",
+            };
+            let header = serde_json::json!({ "alg": alg, "typ": "JWT", "kid": self.kid });
+            let input = format!(
+                "{}.{}",
+                B64URL.encode(header.to_string()),
+                B64URL.encode(claims.to_string())
+            );
+            let signature = match &self.key {
+                Key::Es256(key) => {
+                    use p256::ecdsa::signature::Signer as _;
+                    let signature: p256::ecdsa::Signature = key.sign(input.as_bytes());
+                    signature.to_bytes().to_vec()
+                }
+                Key::Rs256(key) => {
+                    use rsa::signature::{SignatureEncoding as _, Signer as _};
+                    rsa::pkcs1v15::SigningKey::<Sha256>::new(key.as_ref().clone())
+                        .sign(input.as_bytes())
+                        .to_vec()
+                }
+            };
+            format!("{}.{}", input, B64URL.encode(signature))
+        }
+    }
+
+    /// Issuer of GitHub Actions ID tokens.
+    pub const GITHUB_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+    /// GitHub-style claims for `audience` at `commit` on main.
+    pub fn github_claims(audience: &str, commit: &str) -> serde_json::Value {
+        let now = chrono::Utc::now().timestamp();
+        serde_json::json!({
+            "iss": GITHUB_ISSUER,
+            "sub": "repo:acme/widget:ref:refs/heads/main",
+            "aud": audience,
+            "iat": now,
+            "nbf": now,
+            "exp": now + 300,
+            "repository": "acme/widget",
+            "ref": "refs/heads/main",
+            "workflow": "release",
+            "sha": commit,
+            "event_name": "push",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stand_in::*;
+    use super::*;
+
+    #[test]
+    fn verifies_tokens_against_jwks() {
+        for issuer in [StandInIssuer::es256(), StandInIssuer::rs256()] {
+            let token = issuer.mint(&github_claims("vbw:sha256:00", "abc123"));
+            let decoded = decode(&token).unwrap();
+            assert_eq!(decoded.claims.iss, GITHUB_ISSUER);
+            assert_eq!(decoded.claims.git_ref.as_deref(), Some("refs/heads/main"));
+            assert!(decoded.claims.has_audience("vbw:sha256:00"));
+            verify(&decoded, &issuer.jwks()).unwrap();
+
+            // Another issuer's keys do not verify it
+            let other = StandInIssuer::es256();
+            assert!(verify(
+                &decoded,
+                &other.jwks().replace("stand-in-es256", "stand-in-rs256")
+            )
+            .is_err());
+
+            // Tampered claims break the signature
+            let mut parts: Vec<String> = token.split('.').map(String::from).collect();
+            let mut claims = github_claims("vbw:sha256:00", "abc123");
+            claims["ref"] = "refs/heads/evil".into();
+            parts[1] = B64URL.encode(claims.to_string());
+            let tampered = decode(&parts.join(".")).unwrap();
+            assert!(verify(&tampered, &issuer.jwks()).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unsigned_tokens_and_binds_audience_to_key() {
+        let header = B64URL.encode(r#"{"alg":"none"}"#);
+        let claims = B64URL.encode(github_claims("x", "abc").to_string());
+        let err = decode(&format!("{}.{}.", header, claims)).unwrap_err();
+        assert!(err.to_string().contains("unsupported ID token algorithm"));
+
+        let (_, pk) = crate::sign::keygen();
+        let (_, other) = crate::sign::keygen();
+        let audience = key_audience(&pk).unwrap();
+        assert!(audience.starts_with("vbw:sha256:"));
+        assert_eq!(audience, key_audience(&pk).unwrap());
+        assert_ne!(audience, key_audience(&other).unwrap());
+    }
+}
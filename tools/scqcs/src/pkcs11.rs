@@ -0,0 +1,467 @@
+// pkcs11.rs — PKCS#11 (HSM / hardware token) signing backend
+//
+// Implements `sign::Signer` on top of a PKCS#11 module so the builder key
+// never leaves the token: scqcs only ever sees the public key and the
+// signatures the token returns.
+//
+// Keys are selected with an RFC 7512 PKCS#11 URI, e.g.
+//
+//   pkcs11:token=ci-signing;object=vbw-builder?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-source=file:/run/secrets/pin
+//
+// Supported path attributes:  token, serial, slot-id, object, id
+// Supported query attributes: module-path, pin-source, pin-value
+//
+// The module path falls back to SCQCS_PKCS11_MODULE and the user PIN to
+// SCQCS_PKCS11_PIN. `pin-value` works but leaks the PIN into process
+// listings; prefer `pin-source` or the environment variable.
+//
+// Supported key types:
+//   - Ed25519 (CKK_EC_EDWARDS), signed with CKM_EDDSA
+//   - ECDSA P-256 (CKK_EC, prime256v1), signed with raw CKM_ECDSA over a
+//     SHA-256 digest computed here. Raw CKM_ECDSA is the most widely
+//     supported mechanism across tokens, and its r||s output is already the
+//     fixed-width format `sign::EcdsaP256Verifier` expects.
+//
+// REAL: Talks to a real PKCS#11 module. Tested locally against SoftHSM2;
+// see docs/VBW.md ("Hardware Keys (PKCS#11)") for the test setup.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::mechanism::eddsa::{EddsaParams, EddsaSignatureScheme};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::sign::{Algorithm, Signer};
+
+/// DER encoding of the prime256v1 (P-256) named-curve OID.
+const P256_EC_PARAMS: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+/// DER encoding of the Ed25519 OID (RFC 8410).
+const ED25519_OID_PARAMS: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+/// DER PrintableString "edwards25519", used by older tokens (PKCS#11 3.0 §2.3.5).
+const ED25519_NAME_PARAMS: &[u8] = b"\x13\x0cedwards25519";
+
+/// A parsed `pkcs11:` URI. Only the attributes scqcs uses are kept.
+#[derive(Debug, Default, PartialEq)]
+pub struct Pkcs11Uri {
+    pub token: Option<String>,
+    pub serial: Option<String>,
+    pub slot_id: Option<u64>,
+    pub object: Option<String>,
+    pub id: Option<Vec<u8>>,
+    pub module_path: Option<PathBuf>,
+    pub pin_source: Option<PathBuf>,
+    pub pin_value: Option<String>,
+}
+
+impl Pkcs11Uri {
+    /// Parse an RFC 7512 URI. Unknown path attributes are rejected, since
+    /// silently ignoring one could select the wrong key.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let rest = uri
+            .strip_prefix("pkcs11:")
+            .ok_or_else(|| anyhow::anyhow!("PKCS#11 URI must start with 'pkcs11:'"))?;
+        let (path, query) = match rest.split_once('?') {
+            Some((p, q)) => (p, q),
+            None => (rest, ""),
+        };
+
+        let mut parsed = Pkcs11Uri::default();
+        for attr in path.split(';').filter(|a| !a.is_empty()) {
+            let (name, value) = attr
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("malformed PKCS#11 URI attribute '{}'", attr))?;
+            let value = percent_decode(value)?;
+            match name {
+                "token" => parsed.token = Some(utf8(value, name)?),
+                "serial" => parsed.serial = Some(utf8(value, name)?),
+                "object" => parsed.object = Some(utf8(value, name)?),
+                "id" => parsed.id = Some(value),
+                "slot-id" => {
+                    parsed.slot_id = Some(
+                        utf8(value, name)?
+                            .parse()
+                            .context("PKCS#11 URI slot-id must be a number")?,
+                    )
+                }
+                // Informational attributes that cannot select the wrong key
+                "manufacturer" | "model" | "library-manufacturer" | "library-description"
+                | "library-version" | "type" => {}
+                _ => bail!("unsupported PKCS#11 URI attribute '{}'", name),
+            }
+        }
+        for attr in query.split('&').filter(|a| !a.is_empty()) {
+            let (name, value) = attr
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("malformed PKCS#11 URI query '{}'", attr))?;
+            let value = utf8(percent_decode(value)?, name)?;
+            match name {
+                "module-path" => parsed.module_path = Some(PathBuf::from(value)),
+                "pin-source" => {
+                    let path = value.strip_prefix("file:").unwrap_or(&value);
+                    parsed.pin_source = Some(PathBuf::from(path));
+                }
+                "pin-value" => parsed.pin_value = Some(value),
+                _ => bail!("unsupported PKCS#11 URI query attribute '{}'", name),
+            }
+        }
+
+        if parsed.object.is_none() && parsed.id.is_none() {
+            bail!("PKCS#11 URI must name the key with 'object=' or 'id='");
+        }
+        Ok(parsed)
+    }
+
+    /// Resolve the user PIN: pin-value, then pin-source, then SCQCS_PKCS11_PIN.
+    fn pin(&self) -> Result<Option<AuthPin>> {
+        if let Some(pin) = &self.pin_value {
+            return Ok(Some(AuthPin::from(pin.clone())));
+        }
+        if let Some(path) = &self.pin_source {
+            let pin = std::fs::read_to_string(path)
+                .with_context(|| format!("reading PKCS#11 pin-source {}", path.display()))?;
+            return Ok(Some(AuthPin::from(pin.trim_end_matches(['\r', '\n']).to_string())));
+        }
+        match std::env::var(PIN_ENV) {
+            Ok(pin) if !pin.is_empty() => Ok(Some(AuthPin::from(pin))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Environment variable holding the token's user PIN.
+pub const PIN_ENV: &str = "SCQCS_PKCS11_PIN";
+
+/// A signer backed by a private key object on a PKCS#11 token.
+pub struct Pkcs11Signer {
+    // Field order matters: the session must close before the module is dropped.
+    session: Session,
+    _module: Pkcs11,
+    key: ObjectHandle,
+    algorithm: Algorithm,
+    public_key_b64: String,
+}
+
+impl Pkcs11Signer {
+    /// Load the module, log in to the selected token, and locate the key.
+    pub fn open(uri: &str) -> Result<Self> {
+        let uri = Pkcs11Uri::parse(uri)?;
+        let module_path = match &uri.module_path {
+            Some(p) => p.clone(),
+            None => std::env::var_os("SCQCS_PKCS11_MODULE")
+                .map(PathBuf::from)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "no PKCS#11 module: add '?module-path=...' to the URI \
+                         or set SCQCS_PKCS11_MODULE"
+                    )
+                })?,
+        };
+
+        let module = Pkcs11::new(&module_path)
+            .with_context(|| format!("loading PKCS#11 module {}", module_path.display()))?;
+        module
+            .initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK))
+            .context("initializing PKCS#11 module")?;
+
+        let slot = find_slot(&module, &uri)?;
+        let session = module
+            .open_ro_session(slot)
+            .context("opening PKCS#11 session")?;
+        if let Some(pin) = uri.pin()? {
+            session
+                .login(UserType::User, Some(&pin))
+                .context("PKCS#11 login failed")?;
+        }
+
+        let key = find_one(&session, ObjectClass::PRIVATE_KEY, &uri)?;
+        let public = find_one(&session, ObjectClass::PUBLIC_KEY, &uri)
+            .context("the token must also hold the matching public key object")?;
+        let (algorithm, public_key_b64) = read_public_key(&session, public)?;
+
+        Ok(Pkcs11Signer {
+            session,
+            _module: module,
+            key,
+            algorithm,
+            public_key_b64,
+        })
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn public_key(&self) -> Result<String> {
+        Ok(self.public_key_b64.clone())
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        let signature = match self.algorithm {
+            Algorithm::Ed25519 => self.session.sign(
+                &Mechanism::Eddsa(EddsaParams::new(EddsaSignatureScheme::Pure)),
+                self.key,
+                data,
+            ),
+            Algorithm::EcdsaP256 => {
+                let digest = Sha256::digest(data);
+                self.session.sign(&Mechanism::Ecdsa, self.key, &digest)
+            }
+            other => bail!("PKCS#11 signing is not supported for {}", other.as_str()),
+        }
+        .context("PKCS#11 sign operation failed")?;
+
+        if signature.len() != 64 {
+            bail!(
+                "PKCS#11 token returned a {}-byte signature, expected 64",
+                signature.len()
+            );
+        }
+        Ok(B64.encode(signature))
+    }
+}
+
+/// Pick the slot whose token matches the URI's token/serial/slot-id.
+/// Ambiguity is an error rather than a guess.
+fn find_slot(module: &Pkcs11, uri: &Pkcs11Uri) -> Result<cryptoki::slot::Slot> {
+    let mut matches = Vec::new();
+    for slot in module
+        .get_slots_with_token()
+        .context("listing PKCS#11 slots")?
+    {
+        if uri.slot_id.is_some_and(|id| id != slot.id()) {
+            continue;
+        }
+        let info = module.get_token_info(slot)?;
+        if uri.token.as_deref().is_some_and(|t| t != info.label()) {
+            continue;
+        }
+        if uri.serial.as_deref().is_some_and(|s| s != info.serial_number()) {
+            continue;
+        }
+        matches.push(slot);
+    }
+    match matches.len() {
+        0 => bail!("no PKCS#11 token matches the URI"),
+        1 => Ok(matches[0]),
+        n => bail!("{} PKCS#11 tokens match the URI; add token= or serial=", n),
+    }
+}
+
+/// Find exactly one object of `class` matching the URI's object/id.
+fn find_one(session: &Session, class: ObjectClass, uri: &Pkcs11Uri) -> Result<ObjectHandle> {
+    let mut template = vec![Attribute::Class(class)];
+    if let Some(label) = &uri.object {
+        template.push(Attribute::Label(label.as_bytes().to_vec()));
+    }
+    if let Some(id) = &uri.id {
+        template.push(Attribute::Id(id.clone()));
+    }
+    let found = session
+        .find_objects(&template)
+        .context("searching PKCS#11 objects")?;
+    match found.len() {
+        0 => bail!("no PKCS#11 {} matches the URI", class),
+        1 => Ok(found[0]),
+        n => bail!("{} PKCS#11 objects of class {} match the URI", n, class),
+    }
+}
+
+/// Read the key type, curve and point of a public key object and return it
+/// in the encoding the manifest uses for that algorithm.
+fn read_public_key(session: &Session, public: ObjectHandle) -> Result<(Algorithm, String)> {
+    let attrs = session
+        .get_attributes(
+            public,
+            &[
+                AttributeType::KeyType,
+                AttributeType::EcParams,
+                AttributeType::EcPoint,
+            ],
+        )
+        .context("reading PKCS#11 public key attributes")?;
+
+    let (mut key_type, mut params, mut point) = (None, None, None);
+    for attr in attrs {
+        match attr {
+            Attribute::KeyType(t) => key_type = Some(t),
+            Attribute::EcParams(p) => params = Some(p),
+            Attribute::EcPoint(p) => point = Some(p),
+            _ => {}
+        }
+    }
+    let (Some(key_type), Some(params), Some(point)) = (key_type, params, point) else {
+        bail!("PKCS#11 public key is missing CKA_KEY_TYPE, CKA_EC_PARAMS or CKA_EC_POINT");
+    };
+
+    let algorithm = algorithm_for(key_type, &params)?;
+    let public_key_b64 = encode_public_key(algorithm, &point)?;
+    Ok((algorithm, public_key_b64))
+}
+
+/// Map a token key type and curve parameters onto a supported algorithm.
+fn algorithm_for(key_type: KeyType, ec_params: &[u8]) -> Result<Algorithm> {
+    if key_type == KeyType::EC_EDWARDS
+        && (ec_params == ED25519_OID_PARAMS || ec_params == ED25519_NAME_PARAMS)
+    {
+        Ok(Algorithm::Ed25519)
+    } else if key_type == KeyType::EC && ec_params == P256_EC_PARAMS {
+        Ok(Algorithm::EcdsaP256)
+    } else {
+        bail!(
+            "unsupported PKCS#11 key (type {}); use an Ed25519 or P-256 key",
+            key_type
+        )
+    }
+}
+
+/// Convert a CKA_EC_POINT value to the manifest encoding: raw 32 bytes for
+/// Ed25519, SEC1 compressed for P-256. Tokens disagree on whether the point
+/// is wrapped in a DER OCTET STRING, so both forms are accepted.
+fn encode_public_key(algorithm: Algorithm, ec_point: &[u8]) -> Result<String> {
+    let raw = match ec_point {
+        [0x04, len, rest @ ..] if *len as usize == rest.len() && *len < 0x80 => {
+            // DER OCTET STRING, unless this is a bare uncompressed P-256 point
+            if algorithm == Algorithm::EcdsaP256 && ec_point.len() == 65 {
+                ec_point
+            } else {
+                rest
+            }
+        }
+        _ => ec_point,
+    };
+    match algorithm {
+        Algorithm::Ed25519 => {
+            if raw.len() != 32 {
+                bail!("PKCS#11 Ed25519 public key must be 32 bytes, was {}", raw.len());
+            }
+            Ok(B64.encode(raw))
+        }
+        Algorithm::EcdsaP256 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(raw)
+                .map_err(|_| anyhow::anyhow!("invalid P-256 public key on PKCS#11 token"))?;
+            Ok(B64.encode(key.to_encoded_point(true).as_bytes()))
+        }
+        other => bail!("PKCS#11 keys of type {} are not supported", other.as_str()),
+    }
+}
+
+/// RFC 3986 percent-decoding for URI attribute values.
+fn percent_decode(value: &str) -> Result<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .ok_or_else(|| anyhow::anyhow!("truncated percent-encoding in '{}'", value))?;
+            out.push(
+                u8::from_str_radix(hex, 16)
+                    .with_context(|| format!("bad percent-encoding '%{}'", hex))?,
+            );
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+fn utf8(value: Vec<u8>, attr: &str) -> Result<String> {
+    String::from_utf8(value)
+        .map_err(|_| anyhow::anyhow!("PKCS#11 URI attribute '{}' is not valid UTF-8", attr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign;
+
+    #[test]
+    fn parse_uri_with_path_and_query() {
+        let uri = Pkcs11Uri::parse(
+            "pkcs11:token=ci%20signing;object=vbw-builder;id=%01%02\
+             ?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-source=file:/run/pin",
+        )
+        .unwrap();
+        assert_eq!(uri.token.as_deref(), Some("ci signing"));
+        assert_eq!(uri.object.as_deref(), Some("vbw-builder"));
+        assert_eq!(uri.id, Some(vec![1, 2]));
+        assert_eq!(
+            uri.module_path,
+            Some(PathBuf::from("/usr/lib/softhsm/libsofthsm2.so"))
+        );
+        assert_eq!(uri.pin_source, Some(PathBuf::from("/run/pin")));
+    }
+
+    #[test]
+    fn parse_uri_rejects_ambiguous_or_unknown() {
+        // No object or id: would pick an arbitrary key
+        assert!(Pkcs11Uri::parse("pkcs11:token=t").is_err());
+        assert!(Pkcs11Uri::parse("pkcs11:object=k;bogus=1").is_err());
+        assert!(Pkcs11Uri::parse("pkcs11:object=k;id=%0").is_err());
+        assert!(Pkcs11Uri::parse("file:object=k").is_err());
+    }
+
+    #[test]
+    fn ec_point_wrapped_and_bare() {
+        let (_, pk) = sign::keygen_with(Algorithm::EcdsaP256);
+        let compressed = B64.decode(&pk).unwrap();
+        let point = p256::ecdsa::VerifyingKey::from_sec1_bytes(&compressed)
+            .unwrap()
+            .to_encoded_point(false);
+        let bare = point.as_bytes().to_vec();
+        let mut wrapped = vec![0x04, bare.len() as u8];
+        wrapped.extend_from_slice(&bare);
+
+        assert_eq!(encode_public_key(Algorithm::EcdsaP256, &bare).unwrap(), pk);
+        assert_eq!(encode_public_key(Algorithm::EcdsaP256, &wrapped).unwrap(), pk);
+
+        let ed = [7u8; 32];
+        let mut ed_wrapped = vec![0x04, 32];
+        ed_wrapped.extend_from_slice(&ed);
+        assert_eq!(
+            encode_public_key(Algorithm::Ed25519, &ed_wrapped).unwrap(),
+            B64.encode(ed)
+        );
+    }
+
+    #[test]
+    fn curve_detection() {
+        assert_eq!(
+            algorithm_for(KeyType::EC, P256_EC_PARAMS).unwrap(),
+            Algorithm::EcdsaP256
+        );
+        assert_eq!(
+            algorithm_for(KeyType::EC_EDWARDS, ED25519_NAME_PARAMS).unwrap(),
+            Algorithm::Ed25519
+        );
+        // secp384r1
+        let p384 = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+        assert!(algorithm_for(KeyType::EC, &p384).is_err());
+    }
+
+    /// Signs through a real token when SCQCS_TEST_PKCS11_URI is set, e.g. a
+    /// SoftHSM2 key (see docs/VBW.md). Skipped otherwise.
+    #[test]
+    fn softhsm_round_trip() {
+        let Ok(uri) = std::env::var("SCQCS_TEST_PKCS11_URI") else {
+            eprintln!("skipping: SCQCS_TEST_PKCS11_URI not set");
+            return;
+        };
+        let signer = Pkcs11Signer::open(&uri).unwrap();
+        let pk = signer.public_key().unwrap();
+        let sig = signer.sign(b"canonical manifest bytes").unwrap();
+        assert!(sign::verify_with(signer.algorithm(), &pk, b"canonical manifest bytes", &sig).unwrap());
+        assert!(!sign::verify_with(signer.algorithm(), &pk, b"other", &sig).unwrap());
+    }
+}
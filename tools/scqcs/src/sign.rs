@@ -1,18 +1,389 @@
-// sign.rs — Ed25519 key generation, signing, and verification
+// sign.rs — Key generation, signing, and verification
 //
-// Uses the `ed25519-dalek` crate (v2) for all cryptographic operations.
-// Keys are 32-byte seeds, signatures are 64 bytes, all encoded as
-// standard base64 for storage and transport.
+// Signing is pluggable: each supported algorithm implements the `Signer`
+// and `Verifier` traits below, and callers pick an implementation from the
+// algorithm tag recorded in the manifest (`BuilderIdentity.key_type`).
 //
-// REAL: This is real Ed25519 cryptography using OS-provided randomness.
+// Supported algorithms:
+//   - Ed25519 (`ed25519-dalek` v2) — the VBW 1.0 default. Keys are 32-byte
+//     seeds, signatures are 64 bytes.
+//   - ECDSA P-256 with SHA-256 (`p256`) — for compliance regimes that
+//     mandate NIST curves. Secret keys are 32-byte scalars, public keys are
+//     33-byte SEC1 compressed points, signatures are 64-byte fixed-width r||s.
+//   - ML-DSA-65 (`ml-dsa`, FIPS 204) — post-quantum, used only as the second
+//     half of a hybrid key. Secret keys are 32-byte seeds, public keys are
+//     1952 bytes, signatures are 3309 bytes. Signing is deterministic with an
+//     empty context string.
+//
+// HYBRID MODE: a hybrid secret key joins a classical key and an ML-DSA-65
+// key with ';' (not in the base64 alphabet), e.g.
+// "<ed25519 b64>;ml-dsa-65:<b64>". The builder signs the canonical manifest
+// with both halves; verify requires both signatures. A bundle stays
+// trustworthy as long as either algorithm is unbroken.
+//
+// All key and signature material is standard base64 for storage and
+// transport. Ed25519 secret keys are stored untagged (unchanged from 1.0);
+// other algorithms prefix the base64 with their tag, e.g. "ecdsa-p256:<b64>".
+//
+// RSA-PSS is deliberately not offered: the only maintained pure-Rust RSA
+// implementation has an open timing side-channel advisory (RUSTSEC-2023-0071)
+// affecting private-key operations.
+//
+// REAL: This is real cryptography using OS-provided randomness.
 // Keys generated here are production-grade.
 
 use anyhow::{bail, Context, Result};
 use base64::engine::general_purpose::STANDARD as B64;
 use base64::Engine;
-use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use ed25519_dalek::{Signer as _, SigningKey, Verifier as _, VerifyingKey};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::keyfile::{self, PassphraseSource};
+
+/// Signature algorithm of a key. Serialized as the tag used in manifests,
+/// policy files, signature filenames, and tagged secret keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    #[serde(rename = "ed25519")]
+    Ed25519,
+    #[serde(rename = "ecdsa-p256")]
+    EcdsaP256,
+    #[serde(rename = "ml-dsa-65")]
+    MlDsa65,
+}
+
+impl Algorithm {
+    /// All supported algorithms, in preference order.
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::Ed25519,
+        Algorithm::EcdsaP256,
+        Algorithm::MlDsa65,
+    ];
+
+    /// The algorithm's tag, e.g. "ed25519" or "ecdsa-p256".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algorithm::Ed25519 => "ed25519",
+            Algorithm::EcdsaP256 => "ecdsa-p256",
+            Algorithm::MlDsa65 => "ml-dsa-65",
+        }
+    }
+
+    /// Post-quantum algorithms may only appear as the second half of a
+    /// hybrid key, never as a key on their own.
+    pub fn is_post_quantum(&self) -> bool {
+        matches!(self, Algorithm::MlDsa65)
+    }
+
+    /// Parse an algorithm tag.
+    pub fn parse(tag: &str) -> Result<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|a| a.as_str() == tag)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unsupported key type '{}' (supported: {})",
+                    tag,
+                    Self::ALL
+                        .iter()
+                        .map(|a| a.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Something that can sign bytes with a private key.
+///
+/// Implementations hold key material for their lifetime and must zeroize it
+/// on drop.
+pub trait Signer {
+    /// The algorithm this signer produces signatures for.
+    fn algorithm(&self) -> Algorithm;
+    /// The base64-encoded public key matching this signer.
+    fn public_key(&self) -> Result<String>;
+    /// Sign `data` and return the base64-encoded signature.
+    fn sign(&self, data: &[u8]) -> Result<String>;
+    /// OpenSSH fingerprint of the key, for signers backed by ssh-agent.
+    fn key_fingerprint(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Something that can check signatures against a public key.
+pub trait Verifier {
+    /// Returns Ok(true) if valid, Ok(false) if the signature doesn't match.
+    /// Returns Err only if the signature bytes are malformed.
+    fn verify(&self, data: &[u8], signature_b64: &str) -> Result<bool>;
+}
+
+// ── Ed25519 ─────────────────────────────────────────────────────────────────
+
+/// Ed25519 signer. `SigningKey` zeroizes its seed on drop.
+pub struct Ed25519Signer {
+    key: SigningKey,
+}
+
+impl Ed25519Signer {
+    /// Build a signer from a base64-encoded 32-byte seed.
+    ///
+    /// Intermediate secret key bytes are zeroized after use to limit
+    /// the window during which key material exists in memory.
+    pub fn from_seed_b64(secret_key_b64: &str) -> Result<Self> {
+        let mut sk_array = decode_secret_32(secret_key_b64)?;
+        let key = SigningKey::from_bytes(&sk_array);
+        sk_array.zeroize();
+        Ok(Ed25519Signer { key })
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Ed25519
+    }
+
+    fn public_key(&self) -> Result<String> {
+        Ok(B64.encode(self.key.verifying_key().to_bytes()))
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        Ok(B64.encode(self.key.sign(data).to_bytes()))
+    }
+}
+
+/// Ed25519 verifier for a base64-encoded 32-byte public key.
+pub struct Ed25519Verifier {
+    key: VerifyingKey,
+}
+
+impl Ed25519Verifier {
+    pub fn from_public_key_b64(public_key_b64: &str) -> Result<Self> {
+        let pk_bytes = B64
+            .decode(public_key_b64)
+            .context("decoding public key base64")?;
+        let pk_array: [u8; 32] = pk_bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("public key must be 32 bytes"))?;
+        let key = VerifyingKey::from_bytes(&pk_array).context("invalid Ed25519 public key")?;
+        Ok(Ed25519Verifier { key })
+    }
+}
+
+impl Verifier for Ed25519Verifier {
+    fn verify(&self, data: &[u8], signature_b64: &str) -> Result<bool> {
+        let sig_bytes = B64
+            .decode(signature_b64)
+            .context("decoding signature base64")?;
+        let sig_array: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("signature must be 64 bytes"))?;
+        let signature = ed25519_dalek::Signature::from_bytes(&sig_array);
+        Ok(self.key.verify(data, &signature).is_ok())
+    }
+}
+
+// ── ECDSA P-256 ─────────────────────────────────────────────────────────────
+
+/// ECDSA P-256 (SHA-256) signer. `p256::ecdsa::SigningKey` zeroizes its
+/// scalar on drop. Signatures are deterministic (RFC 6979).
+pub struct EcdsaP256Signer {
+    key: p256::ecdsa::SigningKey,
+}
+
+impl EcdsaP256Signer {
+    /// Build a signer from a base64-encoded 32-byte big-endian scalar.
+    pub fn from_scalar_b64(secret_key_b64: &str) -> Result<Self> {
+        let mut sk_array = decode_secret_32(secret_key_b64)?;
+        let key = p256::ecdsa::SigningKey::from_bytes(&sk_array.into());
+        sk_array.zeroize();
+        Ok(EcdsaP256Signer {
+            key: key.context("invalid ECDSA P-256 secret key")?,
+        })
+    }
+}
+
+impl Signer for EcdsaP256Signer {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::EcdsaP256
+    }
+
+    fn public_key(&self) -> Result<String> {
+        Ok(B64.encode(self.key.verifying_key().to_encoded_point(true).as_bytes()))
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        use p256::ecdsa::signature::Signer as _;
+        let sig: p256::ecdsa::Signature = self.key.sign(data);
+        Ok(B64.encode(sig.to_bytes()))
+    }
+}
+
+/// ECDSA P-256 verifier for a base64-encoded SEC1 public key
+/// (compressed or uncompressed).
+pub struct EcdsaP256Verifier {
+    key: p256::ecdsa::VerifyingKey,
+}
+
+impl EcdsaP256Verifier {
+    pub fn from_public_key_b64(public_key_b64: &str) -> Result<Self> {
+        let pk_bytes = B64
+            .decode(public_key_b64)
+            .context("decoding public key base64")?;
+        let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&pk_bytes)
+            .map_err(|_| anyhow::anyhow!("invalid ECDSA P-256 public key"))?;
+        Ok(EcdsaP256Verifier { key })
+    }
+}
+
+impl Verifier for EcdsaP256Verifier {
+    fn verify(&self, data: &[u8], signature_b64: &str) -> Result<bool> {
+        use p256::ecdsa::signature::Verifier as _;
+        let sig_bytes = B64
+            .decode(signature_b64)
+            .context("decoding signature base64")?;
+        let signature = p256::ecdsa::Signature::from_slice(&sig_bytes)
+            .map_err(|_| anyhow::anyhow!("signature must be 64 bytes (r||s)"))?;
+        Ok(self.key.verify(data, &signature).is_ok())
+    }
+}
+
+// ── ML-DSA-65 ───────────────────────────────────────────────────────────────
+
+/// ML-DSA-65 signer. `ml_dsa::SigningKey` zeroizes its seed and expanded
+/// key on drop.
+pub struct MlDsa65Signer {
+    key: ml_dsa::SigningKey<ml_dsa::MlDsa65>,
+}
+
+impl MlDsa65Signer {
+    /// Build a signer from a base64-encoded 32-byte seed (FIPS 204 xi).
+    pub fn from_seed_b64(secret_key_b64: &str) -> Result<Self> {
+        let mut sk_array = decode_secret_32(secret_key_b64)?;
+        let mut seed = ml_dsa::Seed::from(sk_array);
+        let key = ml_dsa::SigningKey::<ml_dsa::MlDsa65>::from_seed(&seed);
+        sk_array.zeroize();
+        seed.as_mut_slice().zeroize();
+        Ok(MlDsa65Signer { key })
+    }
+}
+
+impl Signer for MlDsa65Signer {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::MlDsa65
+    }
+
+    fn public_key(&self) -> Result<String> {
+        use ml_dsa::Keypair as _;
+        Ok(B64.encode(self.key.verifying_key().encode()))
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        use ml_dsa::Signer as _;
+        let sig: ml_dsa::Signature<ml_dsa::MlDsa65> = self
+            .key
+            .try_sign(data)
+            .map_err(|_| anyhow::anyhow!("ML-DSA-65 signing failed"))?;
+        Ok(B64.encode(sig.encode()))
+    }
+}
+
+/// ML-DSA-65 verifier for a base64-encoded 1952-byte public key.
+pub struct MlDsa65Verifier {
+    key: ml_dsa::VerifyingKey<ml_dsa::MlDsa65>,
+}
+
+impl MlDsa65Verifier {
+    pub fn from_public_key_b64(public_key_b64: &str) -> Result<Self> {
+        let pk_bytes = B64
+            .decode(public_key_b64)
+            .context("decoding public key base64")?;
+        let encoded = ml_dsa::EncodedVerifyingKey::<ml_dsa::MlDsa65>::try_from(&pk_bytes[..])
+            .map_err(|_| anyhow::anyhow!("ML-DSA-65 public key must be 1952 bytes"))?;
+        Ok(MlDsa65Verifier {
+            key: ml_dsa::VerifyingKey::decode(&encoded),
+        })
+    }
+}
+
+impl Verifier for MlDsa65Verifier {
+    fn verify(&self, data: &[u8], signature_b64: &str) -> Result<bool> {
+        use ml_dsa::Verifier as _;
+        let sig_bytes = B64
+            .decode(signature_b64)
+            .context("decoding signature base64")?;
+        let signature = ml_dsa::Signature::<ml_dsa::MlDsa65>::try_from(&sig_bytes[..])
+            .map_err(|_| anyhow::anyhow!("malformed ML-DSA-65 signature"))?;
+        Ok(self.key.verify(data, &signature).is_ok())
+    }
+}
+
+// ── Hybrid key sets ─────────────────────────────────────────────────────────
+
+/// The signers behind one builder identity: a classical signer and, in
+/// hybrid mode, a post-quantum signer that signs the same bytes.
+pub struct KeySet {
+    pub classical: Box<dyn Signer>,
+    pub post_quantum: Option<Box<dyn Signer>>,
+}
+
+impl KeySet {
+    /// All signers in signing order (classical first).
+    pub fn signers(&self) -> impl Iterator<Item = &dyn Signer> {
+        std::iter::once(self.classical.as_ref()).chain(self.post_quantum.as_deref())
+    }
+}
+
+/// Build a key set from a secret key string. Accepts everything
+/// `signer_from_secret` does, plus hybrid keys of the form
+/// "<classical key>;ml-dsa-65:<base64>".
+pub fn keyset_from_secret(secret_key: &str) -> Result<KeySet> {
+    let (classical, post_quantum) = match secret_key.split_once(';') {
+        None => (secret_key, None),
+        Some((c, pq)) => (c, Some(pq)),
+    };
+
+    let classical = signer_from_secret(classical)?;
+    if classical.algorithm().is_post_quantum() {
+        bail!(
+            "{} keys can only be used as the second half of a hybrid key \
+             (generate one with `scqcs vbw keygen --hybrid`)",
+            classical.algorithm().as_str()
+        );
+    }
+
+    let post_quantum = match post_quantum {
+        None => None,
+        Some(pq) => {
+            let signer = signer_from_secret(pq)?;
+            if !signer.algorithm().is_post_quantum() {
+                bail!(
+                    "second half of a hybrid key must be post-quantum, found {}",
+                    signer.algorithm().as_str()
+                );
+            }
+            Some(signer)
+        }
+    };
+
+    Ok(KeySet {
+        classical,
+        post_quantum,
+    })
+}
+
+/// Generate a hybrid keypair: a classical `algorithm` key plus ML-DSA-65.
+/// Returns (hybrid_secret_key, classical_public_key_b64, ml_dsa_public_key_b64).
+pub fn keygen_hybrid(algorithm: Algorithm) -> (String, String, String) {
+    let (classical_sk, classical_pk) = keygen_with(algorithm);
+    let (pq_sk, pq_pk) = keygen_with(Algorithm::MlDsa65);
+    (format!("{};{}", classical_sk, pq_sk), classical_pk, pq_pk)
+}
+
+// ── Algorithm dispatch ──────────────────────────────────────────────────────
 
 /// Generate a new Ed25519 keypair using OS randomness.
 /// Returns (secret_key_base64, public_key_base64).
@@ -26,87 +397,226 @@ pub fn keygen() -> (String, String) {
     )
 }
 
-/// Sign arbitrary data with an Ed25519 secret key.
-/// The secret key is a base64-encoded 32-byte seed.
-/// Returns the signature as base64.
-///
-/// Intermediate secret key bytes are zeroized after use to limit
-/// the window during which key material exists in memory.
-pub fn sign(secret_key_b64: &str, data: &[u8]) -> Result<String> {
-    let mut sk_bytes = B64
-        .decode(secret_key_b64)
-        .context("decoding secret key base64")?;
-
-    if sk_bytes.len() != 32 {
-        let len = sk_bytes.len();
-        sk_bytes.zeroize();
-        anyhow::bail!("secret key must be 32 bytes, but was {} bytes", len);
+/// Generate a new keypair for `algorithm` using OS randomness.
+/// Returns (secret_key, public_key_base64). The secret key is tagged with
+/// the algorithm unless it is Ed25519 (see module docs).
+pub fn keygen_with(algorithm: Algorithm) -> (String, String) {
+    match algorithm {
+        Algorithm::Ed25519 => keygen(),
+        Algorithm::EcdsaP256 => {
+            let signing_key = p256::ecdsa::SigningKey::random(&mut rand::rngs::OsRng);
+            let mut sk_bytes = signing_key.to_bytes();
+            let sk = format!("{}:{}", Algorithm::EcdsaP256.as_str(), B64.encode(sk_bytes));
+            sk_bytes.zeroize();
+            let pk = B64.encode(signing_key.verifying_key().to_encoded_point(true).as_bytes());
+            (sk, pk)
+        }
+        Algorithm::MlDsa65 => {
+            use ml_dsa::Keypair as _;
+            use rand::RngCore;
+            let mut seed = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut seed);
+            let sk = format!("{}:{}", Algorithm::MlDsa65.as_str(), B64.encode(seed));
+            let signing_key =
+                ml_dsa::SigningKey::<ml_dsa::MlDsa65>::from_seed(&ml_dsa::Seed::from(seed));
+            seed.zeroize();
+            let pk = B64.encode(signing_key.verifying_key().encode());
+            (sk, pk)
+        }
     }
+}
 
-    let mut sk_array = [0u8; 32];
-    sk_array.copy_from_slice(&sk_bytes);
-    sk_bytes.zeroize();
+/// Build a signer from a secret key string as produced by `keygen_with`.
+///
+/// An untagged value is an Ed25519 seed (the VBW 1.0 format); a value of
+/// the form "<algorithm>:<base64>" selects that algorithm.
+pub fn signer_from_secret(secret_key: &str) -> Result<Box<dyn Signer>> {
+    // Checked first so a hybrid key never ends up echoed in a tag error.
+    if secret_key.contains(';') {
+        bail!("expected a single key, found a hybrid key");
+    }
+    match secret_key.split_once(':') {
+        None => Ok(Box::new(Ed25519Signer::from_seed_b64(secret_key)?)),
+        Some((tag, key_b64)) => match Algorithm::parse(tag)? {
+            Algorithm::Ed25519 => Ok(Box::new(Ed25519Signer::from_seed_b64(key_b64)?)),
+            Algorithm::EcdsaP256 => Ok(Box::new(EcdsaP256Signer::from_scalar_b64(key_b64)?)),
+            Algorithm::MlDsa65 => Ok(Box::new(MlDsa65Signer::from_seed_b64(key_b64)?)),
+        },
+    }
+}
 
-    let signing_key = SigningKey::from_bytes(&sk_array);
-    let sig = signing_key.sign(data);
-    sk_array.zeroize();
+/// Build a verifier for a base64-encoded public key of the given algorithm.
+pub fn verifier_for(algorithm: Algorithm, public_key_b64: &str) -> Result<Box<dyn Verifier>> {
+    match algorithm {
+        Algorithm::Ed25519 => Ok(Box::new(Ed25519Verifier::from_public_key_b64(
+            public_key_b64,
+        )?)),
+        Algorithm::EcdsaP256 => Ok(Box::new(EcdsaP256Verifier::from_public_key_b64(
+            public_key_b64,
+        )?)),
+        Algorithm::MlDsa65 => Ok(Box::new(MlDsa65Verifier::from_public_key_b64(
+            public_key_b64,
+        )?)),
+    }
+}
 
-    Ok(B64.encode(sig.to_bytes()))
+/// Sign arbitrary data with a secret key (Ed25519 seed or tagged key).
+/// Returns the signature as base64.
+///
+/// Build and attest hold a `Signer` for the whole run instead; this one-shot
+/// form is only needed by tests that fabricate bundles.
+#[cfg(test)]
+pub fn sign(secret_key: &str, data: &[u8]) -> Result<String> {
+    signer_from_secret(secret_key)?.sign(data)
 }
 
 /// Verify an Ed25519 signature.
 /// Returns Ok(true) if valid, Ok(false) if the signature doesn't match.
 /// Returns Err only if the key or signature bytes are malformed.
 pub fn verify(public_key_b64: &str, data: &[u8], signature_b64: &str) -> Result<bool> {
-    let pk_bytes = B64
-        .decode(public_key_b64)
-        .context("decoding public key base64")?;
-    let pk_array: [u8; 32] = pk_bytes
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("public key must be 32 bytes"))?;
-    let verifying_key =
-        VerifyingKey::from_bytes(&pk_array).context("invalid Ed25519 public key")?;
+    verify_with(Algorithm::Ed25519, public_key_b64, data, signature_b64)
+}
 
-    let sig_bytes = B64
-        .decode(signature_b64)
-        .context("decoding signature base64")?;
-    let sig_array: [u8; 64] = sig_bytes
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("signature must be 64 bytes"))?;
-    let signature = ed25519_dalek::Signature::from_bytes(&sig_array);
+/// Verify a signature made with `algorithm`.
+/// Same return contract as `verify`.
+pub fn verify_with(
+    algorithm: Algorithm,
+    public_key_b64: &str,
+    data: &[u8],
+    signature_b64: &str,
+) -> Result<bool> {
+    verifier_for(algorithm, public_key_b64)?.verify(data, signature_b64)
+}
+
+/// Decode a base64 signature (as stored in builder.<alg>.sig) to raw bytes.
+pub fn decode_signature(signature_b64: &str) -> Result<Vec<u8>> {
+    B64.decode(signature_b64.trim())
+        .context("decoding signature base64")
+}
 
-    Ok(verifying_key.verify(data, &signature).is_ok())
+/// Where build and attest get their signing key (from CLI flags).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeySource<'a> {
+    /// --keyfile: plain or passphrase-encrypted secret key file.
+    pub keyfile: Option<&'a Path>,
+    /// --signer: external key store URI ("pkcs11:..." or "ssh-agent:...").
+    pub signer: Option<&'a str>,
+    /// How to obtain the passphrase if the key turns out to be encrypted.
+    pub passphrase: PassphraseSource,
+}
+
+/// Environment variable holding the builder's secret key. It is never
+/// recorded in a bundle.
+pub const SIGNING_KEY_ENV: &str = "SCQCS_VBW_ED25519_SK_B64";
+
+/// Environment variables holding signing secrets: the key itself, its
+/// passphrase and a PKCS#11 PIN. None of them reaches the build command,
+/// a tool probe or a bundle, whatever the policy passes through.
+pub const SECRET_ENV_VARS: &[&str] = &[
+    SIGNING_KEY_ENV,
+    keyfile::PASSPHRASE_ENV,
+    crate::pkcs11::PIN_ENV,
+];
+
+/// True if `name` is one of SECRET_ENV_VARS.
+pub fn is_secret_env_var(name: &str) -> bool {
+    SECRET_ENV_VARS.contains(&name)
 }
 
 /// Load the builder's secret key from one of two sources (checked in order):
 ///   1. SCQCS_VBW_ED25519_SK_B64 environment variable (preferred for CI)
 ///   2. --keyfile path on disk (for local development)
 ///
-/// Returns the base64-encoded secret key string.
-pub fn load_secret_key(keyfile: Option<&Path>) -> Result<String> {
-    if let Ok(key) = std::env::var("SCQCS_VBW_ED25519_SK_B64") {
-        if !key.is_empty() {
-            return Ok(key);
+/// Either may hold a passphrase-encrypted key (see keyfile.rs), which is
+/// decrypted here. Returns the secret key string (base64 Ed25519 seed, or
+/// a tagged key for other algorithms — see `signer_from_secret`).
+pub fn load_secret_key(
+    keyfile: Option<&Path>,
+    passphrase: PassphraseSource,
+) -> Result<Zeroizing<String>> {
+    let contents = match std::env::var(SIGNING_KEY_ENV) {
+        Ok(key) if !key.is_empty() => Zeroizing::new(key),
+        _ => match keyfile {
+            Some(path) => Zeroizing::new(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("reading keyfile {}", path.display()))?,
+            ),
+            None => bail!(
+                "No signing key found. Set SCQCS_VBW_ED25519_SK_B64 env var \
+                 or pass --keyfile <path>"
+            ),
+        },
+    };
+
+    if keyfile::is_encrypted(&contents) {
+        let pass = passphrase.read("signing key", false)?;
+        return keyfile::decrypt(&contents, &pass);
+    }
+    Ok(Zeroizing::new(contents.trim().to_string()))
+}
+
+/// Load the builder's key set. A `--signer` URI selects an external key
+/// store ("pkcs11:..." or "ssh-agent:..."); otherwise the secret key comes
+/// from `load_secret_key`.
+pub fn load_keyset(source: &KeySource) -> Result<KeySet> {
+    match source.signer {
+        Some(uri) if uri.starts_with("pkcs11:") => Ok(KeySet {
+            classical: Box::new(crate::pkcs11::Pkcs11Signer::open(uri)?),
+            post_quantum: None,
+        }),
+        #[cfg(unix)]
+        Some(uri) if uri.starts_with("ssh-agent:") => Ok(KeySet {
+            classical: Box::new(crate::ssh_agent::SshAgentSigner::open(
+                &uri["ssh-agent:".len()..],
+            )?),
+            post_quantum: None,
+        }),
+        // The agent protocol runs over a Unix socket
+        #[cfg(not(unix))]
+        Some(uri) if uri.starts_with("ssh-agent:") => {
+            bail!("--signer ssh-agent: is only supported on Unix platforms")
         }
+        Some(uri) => bail!(
+            "unsupported --signer '{}' (expected a pkcs11: or ssh-agent: URI)",
+            uri.split(['?', ';']).next().unwrap_or(uri)
+        ),
+        None => keyset_from_secret(&load_secret_key(source.keyfile, source.passphrase)?),
     }
+}
 
-    if let Some(path) = keyfile {
-        let contents = std::fs::read_to_string(path)
-            .with_context(|| format!("reading keyfile {}", path.display()))?;
-        return Ok(contents.trim().to_string());
+/// OpenSSH-style fingerprint ("SHA256:<unpadded base64>") of an Ed25519
+/// public key, matching `ssh-keygen -l`. Kept here rather than in
+/// ssh_agent.rs so that verify can check fingerprints on every platform.
+pub fn ed25519_fingerprint(public_key_b64: &str) -> Result<String> {
+    let public_key = B64
+        .decode(public_key_b64)
+        .context("decoding public key base64")?;
+    if public_key.len() != 32 {
+        bail!("Ed25519 public key must be 32 bytes");
     }
+    Ok(ssh_fingerprint(&ed25519_ssh_blob(&public_key)))
+}
 
-    bail!(
-        "No signing key found. Set SCQCS_VBW_ED25519_SK_B64 env var \
-         or pass --keyfile <path>"
-    );
+/// Fingerprint of an SSH public key blob.
+pub fn ssh_fingerprint(blob: &[u8]) -> String {
+    use base64::engine::general_purpose::STANDARD_NO_PAD as B64_NO_PAD;
+    use sha2::{Digest, Sha256};
+    format!("SHA256:{}", B64_NO_PAD.encode(Sha256::digest(blob)))
 }
 
-/// Derive the public key from a secret key.
-/// Both are base64-encoded.
-///
-/// Intermediate secret key bytes are zeroized after use.
-pub fn public_key_from_secret(secret_key_b64: &str) -> Result<String> {
+/// SSH wire encoding of an Ed25519 public key: string "ssh-ed25519", string key.
+pub fn ed25519_ssh_blob(public_key: &[u8]) -> Vec<u8> {
+    let mut blob = Vec::new();
+    for field in [b"ssh-ed25519".as_slice(), public_key] {
+        blob.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        blob.extend_from_slice(field);
+    }
+    blob
+}
+
+/// Decode a base64 secret into a fixed 32-byte array, zeroizing the
+/// intermediate buffer on every path.
+fn decode_secret_32(secret_key_b64: &str) -> Result<[u8; 32]> {
     let mut sk_bytes = B64
         .decode(secret_key_b64)
         .context("decoding secret key base64")?;
@@ -114,16 +624,91 @@ pub fn public_key_from_secret(secret_key_b64: &str) -> Result<String> {
     if sk_bytes.len() != 32 {
         let len = sk_bytes.len();
         sk_bytes.zeroize();
-        anyhow::bail!("secret key must be 32 bytes, but was {} bytes", len);
+        bail!("secret key must be 32 bytes, but was {} bytes", len);
     }
 
     let mut sk_array = [0u8; 32];
     sk_array.copy_from_slice(&sk_bytes);
     sk_bytes.zeroize();
+    Ok(sk_array)
+}
 
-    let signing_key = SigningKey::from_bytes(&sk_array);
-    let verifying_key = signing_key.verifying_key();
-    sk_array.zeroize();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_matches_ssh_keygen() {
+        // ssh-keygen -lf: 256 SHA256:xz+TGVw+NzEN6OSO0Y+fbVcdNa+Pv1uY+7AxDGVFM0M
+        assert_eq!(
+            ed25519_fingerprint("NREZphmkZglnq9c6jAf2wMKs3jHtXzaYspeol/ExquA=").unwrap(),
+            "SHA256:xz+TGVw+NzEN6OSO0Y+fbVcdNa+Pv1uY+7AxDGVFM0M"
+        );
+    }
+
+    #[test]
+    fn ed25519_untagged_key_round_trip() {
+        let (sk, pk) = keygen();
+        let signer = signer_from_secret(&sk).unwrap();
+        assert_eq!(signer.algorithm(), Algorithm::Ed25519);
+        assert_eq!(signer.public_key().unwrap(), pk);
+
+        let sig = signer.sign(b"data").unwrap();
+        assert!(verify(&pk, b"data", &sig).unwrap());
+        assert!(!verify(&pk, b"other", &sig).unwrap());
+    }
+
+    #[test]
+    fn ecdsa_p256_tagged_key_round_trip() {
+        let (sk, pk) = keygen_with(Algorithm::EcdsaP256);
+        assert!(sk.starts_with("ecdsa-p256:"));
+
+        let signer = signer_from_secret(&sk).unwrap();
+        assert_eq!(signer.algorithm(), Algorithm::EcdsaP256);
+        assert_eq!(signer.public_key().unwrap(), pk);
 
-    Ok(B64.encode(verifying_key.to_bytes()))
+        let sig = signer.sign(b"data").unwrap();
+        assert!(verify_with(Algorithm::EcdsaP256, &pk, b"data", &sig).unwrap());
+        assert!(!verify_with(Algorithm::EcdsaP256, &pk, b"other", &sig).unwrap());
+    }
+
+    #[test]
+    fn signature_does_not_cross_algorithms() {
+        let (sk, _) = keygen_with(Algorithm::EcdsaP256);
+        let (_, ed_pk) = keygen();
+        let sig = sign(&sk, b"data").unwrap();
+        // Wrong key type for the algorithm is an error, not a silent pass
+        assert!(verify_with(Algorithm::EcdsaP256, &ed_pk, b"data", &sig).is_err());
+        assert!(!verify(&ed_pk, b"data", &sig).unwrap());
+    }
+
+    #[test]
+    fn hybrid_key_signs_with_both_halves() {
+        let (sk, ed_pk, pq_pk) = keygen_hybrid(Algorithm::Ed25519);
+        let keys = keyset_from_secret(&sk).unwrap();
+        assert_eq!(keys.classical.algorithm(), Algorithm::Ed25519);
+        let pq = keys.post_quantum.as_ref().unwrap();
+        assert_eq!(pq.algorithm(), Algorithm::MlDsa65);
+        assert_eq!(pq.public_key().unwrap(), pq_pk);
+
+        let ed_sig = keys.classical.sign(b"data").unwrap();
+        let pq_sig = pq.sign(b"data").unwrap();
+        assert!(verify(&ed_pk, b"data", &ed_sig).unwrap());
+        assert!(verify_with(Algorithm::MlDsa65, &pq_pk, b"data", &pq_sig).unwrap());
+        assert!(!verify_with(Algorithm::MlDsa65, &pq_pk, b"other", &pq_sig).unwrap());
+    }
+
+    #[test]
+    fn post_quantum_key_alone_rejected() {
+        let (pq_sk, _) = keygen_with(Algorithm::MlDsa65);
+        assert!(keyset_from_secret(&pq_sk).is_err());
+        let (ed_sk, _) = keygen();
+        assert!(keyset_from_secret(&format!("{};{}", ed_sk, ed_sk)).is_err());
+    }
+
+    #[test]
+    fn unknown_key_tag_rejected() {
+        assert!(signer_from_secret("rsa-pss:AAAA").is_err());
+        assert!(Algorithm::parse("rsa-pss").is_err());
+    }
 }
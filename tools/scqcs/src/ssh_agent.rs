@@ -0,0 +1,324 @@
+// ssh_agent.rs — ssh-agent signing backend
+//
+// Implements `sign::Signer` by asking a running ssh-agent to sign, so
+// developers can use the Ed25519 key they already hold for SSH and git
+// commit signing. The private key stays inside the agent.
+//
+// Selected with `--signer ssh-agent:[<selector>]`, where the selector is
+// either an OpenSSH fingerprint ("SHA256:...") or the key's comment. With
+// no selector the agent must hold exactly one Ed25519 key.
+//
+// Protocol (draft-miller-ssh-agent), over the Unix socket in SSH_AUTH_SOCK:
+//   SSH_AGENTC_REQUEST_IDENTITIES (11) -> SSH_AGENT_IDENTITIES_ANSWER (12)
+//   SSH_AGENTC_SIGN_REQUEST (13)       -> SSH_AGENT_SIGN_RESPONSE (14)
+// Every message is a uint32 length followed by a type byte and payload.
+//
+// For ssh-ed25519 keys the agent returns a plain Ed25519 signature over the
+// exact bytes sent (no SSHSIG wrapping, no hashing), so bundles signed this
+// way verify with the ordinary Ed25519 verifier. Only Ed25519 agent keys
+// are supported.
+//
+// REAL: Talks to a real agent. Tests spawn a private ssh-agent.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use crate::sign::{self, Algorithm, Signer};
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const ED25519_KEY_TYPE: &str = "ssh-ed25519";
+/// Agent replies larger than this are treated as a protocol error.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// A signer backed by one Ed25519 identity in an ssh-agent.
+pub struct SshAgentSigner {
+    socket: PathBuf,
+    key_blob: Vec<u8>,
+    public_key: [u8; 32],
+}
+
+impl SshAgentSigner {
+    /// Connect to the agent in SSH_AUTH_SOCK and select a key.
+    pub fn open(selector: &str) -> Result<Self> {
+        let socket = std::env::var_os("SSH_AUTH_SOCK")
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("SSH_AUTH_SOCK is not set; is ssh-agent running?"))?;
+        Self::connect(&socket, selector)
+    }
+
+    /// Select a key from the agent listening on `socket`. An empty selector
+    /// requires exactly one Ed25519 key in the agent.
+    pub fn connect(socket: &Path, selector: &str) -> Result<Self> {
+        let mut candidates = Vec::new();
+        for (blob, comment) in request_identities(socket)? {
+            let Some(public_key) = ed25519_public_key(&blob) else {
+                continue;
+            };
+            let fingerprint = sign::ssh_fingerprint(&blob);
+            if selector.is_empty() || selector == fingerprint || selector == comment {
+                candidates.push((blob, public_key, fingerprint, comment));
+            }
+        }
+
+        match candidates.len() {
+            0 if selector.is_empty() => bail!("ssh-agent holds no Ed25519 keys"),
+            0 => bail!("no Ed25519 key in ssh-agent matches '{}'", selector),
+            1 => {
+                let (key_blob, public_key, fingerprint, comment) = candidates.remove(0);
+                eprintln!("[vbw] Using ssh-agent key {} ({})", fingerprint, comment);
+                Ok(SshAgentSigner {
+                    socket: socket.to_path_buf(),
+                    key_blob,
+                    public_key,
+                })
+            }
+            n => bail!(
+                "{} Ed25519 keys in ssh-agent match; select one with \
+                 --signer ssh-agent:<SHA256 fingerprint or comment>:\n  {}",
+                n,
+                candidates
+                    .iter()
+                    .map(|(_, _, fp, comment)| format!("{} {}", fp, comment))
+                    .collect::<Vec<_>>()
+                    .join("\n  ")
+            ),
+        }
+    }
+}
+
+impl Signer for SshAgentSigner {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Ed25519
+    }
+
+    fn public_key(&self) -> Result<String> {
+        Ok(B64.encode(self.public_key))
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<String> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, &self.key_blob);
+        put_string(&mut request, data);
+        request.extend_from_slice(&0u32.to_be_bytes()); // flags
+
+        let (kind, body) = round_trip(&self.socket, &request)?;
+        match kind {
+            SSH_AGENT_SIGN_RESPONSE => {}
+            SSH_AGENT_FAILURE => bail!("ssh-agent refused to sign (key removed or confirmation denied?)"),
+            other => bail!("unexpected ssh-agent reply type {}", other),
+        }
+
+        let mut reader = WireReader::new(&body);
+        let sig_blob = reader.string()?;
+        let mut sig_reader = WireReader::new(sig_blob);
+        if sig_reader.string()? != ED25519_KEY_TYPE.as_bytes() {
+            bail!("ssh-agent returned a non-Ed25519 signature");
+        }
+        let signature = sig_reader.string()?;
+        if signature.len() != 64 {
+            bail!("ssh-agent returned a {}-byte Ed25519 signature", signature.len());
+        }
+        Ok(B64.encode(signature))
+    }
+
+    fn key_fingerprint(&self) -> Option<String> {
+        Some(sign::ssh_fingerprint(&self.key_blob))
+    }
+}
+
+/// The raw public key if `blob` is an ssh-ed25519 key, else None.
+fn ed25519_public_key(blob: &[u8]) -> Option<[u8; 32]> {
+    let mut reader = WireReader::new(blob);
+    if reader.string().ok()? != ED25519_KEY_TYPE.as_bytes() {
+        return None;
+    }
+    reader.string().ok()?.try_into().ok()
+}
+
+/// List the agent's identities as (key blob, comment).
+fn request_identities(socket: &Path) -> Result<Vec<(Vec<u8>, String)>> {
+    let (kind, body) = round_trip(socket, &[SSH_AGENTC_REQUEST_IDENTITIES])?;
+    if kind != SSH_AGENT_IDENTITIES_ANSWER {
+        bail!("unexpected ssh-agent reply type {} to identity request", kind);
+    }
+
+    let mut reader = WireReader::new(&body);
+    let count = reader.u32()?;
+    let mut identities = Vec::new();
+    for _ in 0..count {
+        let blob = reader.string()?.to_vec();
+        let comment = String::from_utf8_lossy(reader.string()?).into_owned();
+        identities.push((blob, comment));
+    }
+    Ok(identities)
+}
+
+/// Send one request and read one reply, returning (type, payload).
+fn round_trip(socket: &Path, request: &[u8]) -> Result<(u8, Vec<u8>)> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("connecting to ssh-agent at {}", socket.display()))?;
+
+    let mut framed = Vec::with_capacity(request.len() + 4);
+    framed.extend_from_slice(&(request.len() as u32).to_be_bytes());
+    framed.extend_from_slice(request);
+    stream.write_all(&framed).context("writing to ssh-agent")?;
+
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).context("reading from ssh-agent")?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_MESSAGE_LEN {
+        bail!("invalid ssh-agent reply length {}", len);
+    }
+    let mut reply = vec![0u8; len];
+    stream.read_exact(&mut reply).context("reading from ssh-agent")?;
+    let kind = reply.remove(0);
+    Ok((kind, reply))
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Bounds-checked reader for SSH wire-format fields.
+struct WireReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        WireReader { data }
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        if self.data.len() < 4 {
+            bail!("truncated ssh-agent message");
+        }
+        let (head, rest) = self.data.split_at(4);
+        self.data = rest;
+        Ok(u32::from_be_bytes([head[0], head[1], head[2], head[3]]))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if self.data.len() < len {
+            bail!("truncated ssh-agent message");
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+
+    #[test]
+    fn wire_reader_rejects_truncation() {
+        let mut buf = Vec::new();
+        put_string(&mut buf, b"ssh-ed25519");
+        buf.extend_from_slice(&32u32.to_be_bytes());
+        buf.extend_from_slice(&[0u8; 10]);
+        assert_eq!(ed25519_public_key(&buf), None);
+        assert!(WireReader::new(&[0, 0, 1]).u32().is_err());
+    }
+
+    /// A private ssh-agent that is killed when the test ends.
+    struct Agent {
+        child: Child,
+        dir: tempfile::TempDir,
+    }
+
+    impl Drop for Agent {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    impl Agent {
+        fn socket(&self) -> PathBuf {
+            self.dir.path().join("agent.sock")
+        }
+
+        fn add_key(&self, name: &str, comment: &str) {
+            let key = self.dir.path().join(name);
+            let status = Command::new("ssh-keygen")
+                .args(["-q", "-t", "ed25519", "-N", "", "-C", comment, "-f"])
+                .arg(&key)
+                .status()
+                .unwrap();
+            assert!(status.success());
+            let status = Command::new("ssh-add")
+                .arg(&key)
+                .env("SSH_AUTH_SOCK", self.socket())
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+    }
+
+    /// Start ssh-agent in the foreground on a private socket, or None if
+    /// OpenSSH isn't installed.
+    fn spawn_agent() -> Option<Agent> {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("agent.sock");
+        let child = Command::new("ssh-agent")
+            .arg("-D")
+            .arg("-a")
+            .arg(&socket)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let agent = Agent { child, dir };
+        for _ in 0..100 {
+            if socket.exists() {
+                return Some(agent);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("ssh-agent did not create its socket");
+    }
+
+    #[test]
+    fn sign_with_spawned_agent() {
+        let Some(agent) = spawn_agent() else {
+            eprintln!("skipping: ssh-agent not available");
+            return;
+        };
+        assert!(SshAgentSigner::connect(&agent.socket(), "").is_err());
+
+        agent.add_key("id_a", "alice@laptop");
+        let signer = SshAgentSigner::connect(&agent.socket(), "").unwrap();
+        let pk = signer.public_key().unwrap();
+        assert_eq!(
+            signer.key_fingerprint().unwrap(),
+            sign::ed25519_fingerprint(&pk).unwrap()
+        );
+
+        let sig = signer.sign(b"canonical manifest bytes").unwrap();
+        assert!(sign::verify(&pk, b"canonical manifest bytes", &sig).unwrap());
+
+        // Two keys: an empty selector is ambiguous; comment and fingerprint select
+        agent.add_key("id_b", "bob@laptop");
+        assert!(SshAgentSigner::connect(&agent.socket(), "").is_err());
+        let bob = SshAgentSigner::connect(&agent.socket(), "bob@laptop").unwrap();
+        assert_ne!(bob.public_key().unwrap(), pk);
+        let fp = signer.key_fingerprint().unwrap();
+        let alice = SshAgentSigner::connect(&agent.socket(), &fp).unwrap();
+        assert_eq!(alice.public_key().unwrap(), pk);
+    }
+}
@@ -0,0 +1,660 @@
+// tsa.rs — RFC 3161 trusted timestamps for builder signatures
+//
+// `created_at` in the manifest is self-reported: whoever holds the builder
+// key can write any time they like. A timestamp token from an independent
+// Time-Stamping Authority (TSA) proves the builder signature existed no
+// later than the TSA's `genTime`, which verify then uses as the reference
+// time for builder key validity and revocation checks.
+//
+// Build sends a TimeStampReq carrying SHA-256 of the decoded builder
+// signature (plus a random nonce, certReq=true) to the TSA over HTTP(S)
+// and stores the returned TimeStampToken (DER CMS SignedData) in the
+// bundle as signatures/builder.<alg>.tst.
+//
+// Token verification (`verify_token`) checks:
+//   1. The token is CMS SignedData wrapping an id-ct-TSTInfo.
+//   2. TSTInfo.messageImprint is SHA-256 of the data we expect.
+//   3. Exactly one SignerInfo, whose certificate is embedded in the token.
+//   4. Signed attributes carry contentType = id-ct-TSTInfo and a
+//      messageDigest matching the TSTInfo bytes.
+//   5. The signature over the signed attributes verifies with the
+//      certificate's key (RSA PKCS#1 v1.5 with SHA-256/384/512, or ECDSA
+//      P-256 with SHA-256).
+//   6. genTime falls inside the certificate's validity period.
+//
+// Whether that certificate is *trusted* is a policy decision made by the
+// caller: the policy pins TSA signing certificates by SHA-256 of their DER
+// encoding. There is no chain building or CRL/OCSP checking, which is why
+// the leaf is pinned directly.
+//
+// REAL: Produces and checks real RFC 3161 tokens. Works with public TSAs
+// (e.g. http://timestamp.digicert.com) and `openssl ts`.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use cms::cert::x509::Certificate;
+use cms::cert::CertificateChoices;
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerIdentifier, SignerInfo};
+use der::asn1::{Int, OctetString};
+use der::oid::ObjectIdentifier;
+use der::{Any, Decode, Encode};
+use rand::RngCore;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::io::Read;
+use std::time::Duration;
+use x509_tsp::{MessageImprint, TimeStampReq, TimeStampResp, TspVersion, TstInfo};
+
+const ID_SIGNED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.2");
+const ID_CT_TST_INFO: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+const ID_CONTENT_TYPE: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.3");
+const ID_MESSAGE_DIGEST: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.4");
+
+const ID_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.1");
+const ID_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.2");
+const ID_SHA512: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.3");
+
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const SHA256_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+const SHA384_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.12");
+const SHA512_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.13");
+const ID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const PRIME256V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+/// Largest TSA response accepted (tokens with a cert chain are a few KiB).
+const MAX_RESPONSE_LEN: u64 = 1024 * 1024;
+
+/// A timestamp token whose signature and imprint checked out.
+#[derive(Debug, Clone)]
+pub struct VerifiedTimestamp {
+    /// The TSA's attested time.
+    pub gen_time: DateTime<Utc>,
+    /// Lowercase hex SHA-256 of the TSA signing certificate (DER), for
+    /// comparison with the policy's pinned certificates.
+    pub tsa_cert_sha256: String,
+    /// The signing certificate's subject, for display.
+    pub tsa_subject: String,
+}
+
+/// Request a timestamp token over `data` from the TSA at `url`.
+/// Returns the DER-encoded TimeStampToken, already checked with
+/// `verify_token` (but not against any trust pins).
+pub fn request_timestamp(url: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; 8];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+    // Keep the INTEGER positive, and its first byte non-zero: Int::new
+    // does not strip leading zeros, and DER rejects them
+    nonce[0] = nonce[0] & 0x7f | 0x40;
+    let nonce = Int::new(&nonce)?;
+
+    let request = TimeStampReq {
+        version: TspVersion::V1,
+        message_imprint: sha256_imprint(data)?,
+        req_policy: None,
+        nonce: Some(nonce.clone()),
+        cert_req: true,
+        extensions: None,
+    }
+    .to_der()?;
+
+    let response = ureq::post(url)
+        .set("Content-Type", "application/timestamp-query")
+        .timeout(Duration::from_secs(30))
+        .send_bytes(&request)
+        .with_context(|| format!("requesting timestamp from {}", url))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_LEN)
+        .read_to_end(&mut body)
+        .context("reading TSA response")?;
+
+    let response = TimeStampResp::from_der(&body).context("parsing TSA response")?;
+    use cmpv2::status::PkiStatus;
+    if !matches!(
+        response.status.status,
+        PkiStatus::Accepted | PkiStatus::GrantedWithMods
+    ) {
+        bail!("TSA rejected the request (status {:?})", response.status.status);
+    }
+    let token = response
+        .time_stamp_token
+        .ok_or_else(|| anyhow::anyhow!("TSA response has no timestamp token"))?
+        .to_der()?;
+
+    // Check the reply answers *our* request before storing it
+    let tst_info = parse_tst_info(&decode_signed_data(&token)?)?;
+    if tst_info.nonce.as_ref() != Some(&nonce) {
+        bail!("TSA response nonce does not match the request");
+    }
+    verify_token(&token, data)?;
+    Ok(token)
+}
+
+/// Verify a DER TimeStampToken over `data` (see module docs for the checks).
+pub fn verify_token(token: &[u8], data: &[u8]) -> Result<VerifiedTimestamp> {
+    let signed_data = decode_signed_data(token)?;
+    let tst_info_der = econtent_bytes(&signed_data)?;
+    let tst_info = TstInfo::from_der(tst_info_der).context("parsing TSTInfo")?;
+
+    if tst_info.message_imprint != sha256_imprint(data)? {
+        bail!("timestamp token does not cover this signature (messageImprint mismatch)");
+    }
+
+    let signer_infos = signed_data.signer_infos.0.as_slice();
+    let [signer] = signer_infos else {
+        bail!(
+            "timestamp token must have exactly one signer, found {}",
+            signer_infos.len()
+        );
+    };
+    let cert = find_signer_cert(&signed_data, signer)?;
+    check_signed_attributes(signer, tst_info_der)?;
+    verify_signer_signature(signer, cert)?;
+
+    let gen_time = to_chrono(tst_info.gen_time.to_unix_duration())?;
+    let validity = &cert.tbs_certificate.validity;
+    let not_before = to_chrono(validity.not_before.to_unix_duration())?;
+    let not_after = to_chrono(validity.not_after.to_unix_duration())?;
+    if gen_time < not_before || gen_time > not_after {
+        bail!(
+            "timestamp {} is outside the TSA certificate's validity ({} to {})",
+            gen_time,
+            not_before,
+            not_after
+        );
+    }
+
+    Ok(VerifiedTimestamp {
+        gen_time,
+        tsa_cert_sha256: hex_lower(&Sha256::digest(cert.to_der()?)),
+        tsa_subject: cert.tbs_certificate.subject.to_string(),
+    })
+}
+
+fn sha256_imprint(data: &[u8]) -> Result<MessageImprint> {
+    Ok(MessageImprint {
+        hash_algorithm: spki::AlgorithmIdentifier {
+            oid: ID_SHA256,
+            parameters: Some(Any::null()),
+        },
+        hashed_message: OctetString::new(Sha256::digest(data).to_vec())?,
+    })
+}
+
+fn decode_signed_data(token: &[u8]) -> Result<SignedData> {
+    let content_info = ContentInfo::from_der(token).context("parsing timestamp token")?;
+    if content_info.content_type != ID_SIGNED_DATA {
+        bail!("timestamp token is not CMS SignedData");
+    }
+    content_info
+        .content
+        .decode_as::<SignedData>()
+        .context("parsing timestamp token SignedData")
+}
+
+/// The DER TSTInfo carried in the SignedData's encapsulated content.
+fn econtent_bytes(signed_data: &SignedData) -> Result<&[u8]> {
+    let encap = &signed_data.encap_content_info;
+    if encap.econtent_type != ID_CT_TST_INFO {
+        bail!("timestamp token does not contain a TSTInfo");
+    }
+    let econtent = encap
+        .econtent
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("timestamp token has no TSTInfo content"))?;
+    Ok(econtent.value())
+}
+
+fn parse_tst_info(signed_data: &SignedData) -> Result<TstInfo> {
+    TstInfo::from_der(econtent_bytes(signed_data)?).context("parsing TSTInfo")
+}
+
+/// Find the certificate named by the SignerInfo's sid among the token's certs.
+fn find_signer_cert<'a>(signed_data: &'a SignedData, signer: &SignerInfo) -> Result<&'a Certificate> {
+    let certs = signed_data
+        .certificates
+        .as_ref()
+        .map(|set| set.0.as_slice())
+        .unwrap_or(&[]);
+    for choice in certs {
+        let CertificateChoices::Certificate(cert) = choice else {
+            continue;
+        };
+        let tbs = &cert.tbs_certificate;
+        let matches = match &signer.sid {
+            SignerIdentifier::IssuerAndSerialNumber(ias) => {
+                ias.issuer == tbs.issuer && ias.serial_number == tbs.serial_number
+            }
+            SignerIdentifier::SubjectKeyIdentifier(ski) => tbs
+                .get::<x509_cert::ext::pkix::SubjectKeyIdentifier>()
+                .ok()
+                .flatten()
+                .is_some_and(|(_, cert_ski)| cert_ski == *ski),
+        };
+        if matches {
+            return Ok(cert);
+        }
+    }
+    bail!("timestamp token does not include the TSA signing certificate (was certReq set?)")
+}
+
+/// Check the contentType and messageDigest signed attributes.
+fn check_signed_attributes(signer: &SignerInfo, tst_info_der: &[u8]) -> Result<()> {
+    let attrs = signer
+        .signed_attrs
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("timestamp token has no signed attributes"))?;
+
+    let single_value = |oid: ObjectIdentifier| -> Result<&Any> {
+        let attr = attrs
+            .iter()
+            .find(|a| a.oid == oid)
+            .ok_or_else(|| anyhow::anyhow!("timestamp token is missing signed attribute {}", oid))?;
+        match attr.values.as_slice() {
+            [value] => Ok(value),
+            _ => bail!("signed attribute {} must have exactly one value", oid),
+        }
+    };
+
+    let content_type: ObjectIdentifier = single_value(ID_CONTENT_TYPE)?.decode_as()?;
+    if content_type != ID_CT_TST_INFO {
+        bail!("timestamp token contentType attribute is not id-ct-TSTInfo");
+    }
+
+    let digest: OctetString = single_value(ID_MESSAGE_DIGEST)?.decode_as()?;
+    if digest.as_bytes() != digest_with(&signer.digest_alg.oid, tst_info_der)? {
+        bail!("timestamp token messageDigest does not match its TSTInfo");
+    }
+    Ok(())
+}
+
+/// Verify the SignerInfo signature over the DER SET OF signed attributes.
+fn verify_signer_signature(signer: &SignerInfo, cert: &Certificate) -> Result<()> {
+    let signed_bytes = signer
+        .signed_attrs
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("timestamp token has no signed attributes"))?
+        .to_der()?;
+    let signature = signer.signature.as_bytes();
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    let key_bytes = spki.subject_public_key.raw_bytes();
+    let digest_oid = signer.digest_alg.oid;
+    let sig_oid = signer.signature_algorithm.oid;
+
+    let valid = if spki.algorithm.oid == RSA_ENCRYPTION {
+        use rsa::pkcs1::DecodeRsaPublicKey;
+        use rsa::signature::Verifier as _;
+        let key = rsa::RsaPublicKey::from_pkcs1_der(key_bytes).context("invalid TSA RSA key")?;
+        let sig = rsa::pkcs1v15::Signature::try_from(signature)
+            .map_err(|_| anyhow::anyhow!("malformed TSA RSA signature"))?;
+        // rsaEncryption as the signature algorithm means "hash per digest_alg"
+        let hash = if sig_oid == RSA_ENCRYPTION {
+            digest_oid
+        } else {
+            match sig_oid {
+                SHA256_WITH_RSA => ID_SHA256,
+                SHA384_WITH_RSA => ID_SHA384,
+                SHA512_WITH_RSA => ID_SHA512,
+                other => bail!("unsupported TSA signature algorithm {}", other),
+            }
+        };
+        match hash {
+            ID_SHA256 => rsa::pkcs1v15::VerifyingKey::<Sha256>::new(key)
+                .verify(&signed_bytes, &sig)
+                .is_ok(),
+            ID_SHA384 => rsa::pkcs1v15::VerifyingKey::<Sha384>::new(key)
+                .verify(&signed_bytes, &sig)
+                .is_ok(),
+            ID_SHA512 => rsa::pkcs1v15::VerifyingKey::<Sha512>::new(key)
+                .verify(&signed_bytes, &sig)
+                .is_ok(),
+            other => bail!("unsupported TSA digest algorithm {}", other),
+        }
+    } else if spki.algorithm.oid == ID_EC_PUBLIC_KEY {
+        use p256::ecdsa::signature::Verifier as _;
+        let curve: Option<ObjectIdentifier> = spki
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|p| p.decode_as().ok());
+        if curve != Some(PRIME256V1) || sig_oid != ECDSA_WITH_SHA256 {
+            bail!("unsupported TSA ECDSA key or signature algorithm (only P-256 with SHA-256)");
+        }
+        let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key_bytes)
+            .map_err(|_| anyhow::anyhow!("invalid TSA P-256 key"))?;
+        let sig = p256::ecdsa::Signature::from_der(signature)
+            .map_err(|_| anyhow::anyhow!("malformed TSA ECDSA signature"))?;
+        key.verify(&signed_bytes, &sig).is_ok()
+    } else {
+        bail!("unsupported TSA key algorithm {}", spki.algorithm.oid);
+    };
+
+    if !valid {
+        bail!("timestamp token signature INVALID");
+    }
+    Ok(())
+}
+
+fn digest_with(oid: &ObjectIdentifier, data: &[u8]) -> Result<Vec<u8>> {
+    Ok(match *oid {
+        ID_SHA256 => Sha256::digest(data).to_vec(),
+        ID_SHA384 => Sha384::digest(data).to_vec(),
+        ID_SHA512 => Sha512::digest(data).to_vec(),
+        other => bail!("unsupported digest algorithm {}", other),
+    })
+}
+
+fn to_chrono(since_epoch: Duration) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(since_epoch.as_secs() as i64, since_epoch.subsec_nanos())
+        .ok_or_else(|| anyhow::anyhow!("timestamp out of range"))
+}
+
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A minimal in-process TSA (self-signed P-256 certificate) for tests.
+#[cfg(test)]
+pub mod stand_in {
+    use super::*;
+    use cms::cert::IssuerAndSerialNumber;
+    use cms::content_info::CmsVersion;
+    use cms::signed_data::{CertificateSet, EncapsulatedContentInfo, SignerInfos};
+    use der::asn1::{GeneralizedTime, SetOfVec};
+    use der::Tag;
+    use p256::ecdsa::signature::Signer as _;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::str::FromStr;
+    use x509_cert::attr::Attribute;
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned};
+    use x509_cert::time::{Time, Validity};
+    use x509_cert::{TbsCertificate, Version};
+
+    pub struct StandInTsa {
+        key: p256::ecdsa::SigningKey,
+        pub cert: Certificate,
+    }
+
+    fn ecdsa_sha256() -> AlgorithmIdentifierOwned {
+        AlgorithmIdentifierOwned {
+            oid: ECDSA_WITH_SHA256,
+            parameters: None,
+        }
+    }
+
+    impl StandInTsa {
+        pub fn new() -> Self {
+            let key = p256::ecdsa::SigningKey::random(&mut rand::rngs::OsRng);
+            let name = Name::from_str("CN=scqcs stand-in TSA").unwrap();
+            let now = std::time::SystemTime::now();
+            let day = Duration::from_secs(86400);
+            let tbs = TbsCertificate {
+                version: Version::V1,
+                serial_number: SerialNumber::new(&[1]).unwrap(),
+                signature: ecdsa_sha256(),
+                issuer: name.clone(),
+                validity: Validity {
+                    not_before: Time::try_from(now - day).unwrap(),
+                    not_after: Time::try_from(now + day).unwrap(),
+                },
+                subject: name,
+                subject_public_key_info: SubjectPublicKeyInfoOwned {
+                    algorithm: AlgorithmIdentifierOwned {
+                        oid: ID_EC_PUBLIC_KEY,
+                        parameters: Some(Any::encode_from(&PRIME256V1).unwrap()),
+                    },
+                    subject_public_key: der::asn1::BitString::from_bytes(
+                        key.verifying_key().to_encoded_point(false).as_bytes(),
+                    )
+                    .unwrap(),
+                },
+                issuer_unique_id: None,
+                subject_unique_id: None,
+                extensions: None,
+            };
+            let sig: p256::ecdsa::Signature = key.sign(&tbs.to_der().unwrap());
+            let cert = Certificate {
+                tbs_certificate: tbs,
+                signature_algorithm: ecdsa_sha256(),
+                signature: der::asn1::BitString::from_bytes(sig.to_der().as_bytes()).unwrap(),
+            };
+            StandInTsa { key, cert }
+        }
+
+        pub fn cert_sha256(&self) -> String {
+            hex_lower(&Sha256::digest(self.cert.to_der().unwrap()))
+        }
+
+        /// Issue a token for `imprint` stamped at `gen_time`.
+        pub fn issue(
+            &self,
+            imprint: MessageImprint,
+            nonce: Option<Int>,
+            gen_time: std::time::SystemTime,
+        ) -> Vec<u8> {
+            let tst_info = TstInfo {
+                version: TspVersion::V1,
+                policy: ObjectIdentifier::new_unwrap("1.3.6.1.4.1.99999.1"),
+                message_imprint: imprint,
+                serial_number: Int::new(&[1]).unwrap(),
+                gen_time: GeneralizedTime::from_system_time(gen_time).unwrap(),
+                accuracy: None,
+                ordering: false,
+                nonce,
+                tsa: None,
+                extensions: None,
+            }
+            .to_der()
+            .unwrap();
+
+            let attr = |oid, value: Any| Attribute {
+                oid,
+                values: SetOfVec::try_from(vec![value]).unwrap(),
+            };
+            let signed_attrs = SetOfVec::try_from(vec![
+                attr(ID_CONTENT_TYPE, Any::encode_from(&ID_CT_TST_INFO).unwrap()),
+                attr(
+                    ID_MESSAGE_DIGEST,
+                    Any::encode_from(&OctetString::new(Sha256::digest(&tst_info).to_vec()).unwrap())
+                        .unwrap(),
+                ),
+            ])
+            .unwrap();
+            let sig: p256::ecdsa::Signature = self.key.sign(&signed_attrs.to_der().unwrap());
+
+            let sha256 = AlgorithmIdentifierOwned {
+                oid: ID_SHA256,
+                parameters: None,
+            };
+            let signed_data = SignedData {
+                version: CmsVersion::V3,
+                digest_algorithms: SetOfVec::try_from(vec![sha256.clone()]).unwrap(),
+                encap_content_info: EncapsulatedContentInfo {
+                    econtent_type: ID_CT_TST_INFO,
+                    econtent: Some(Any::new(Tag::OctetString, tst_info).unwrap()),
+                },
+                certificates: Some(CertificateSet(
+                    SetOfVec::try_from(vec![CertificateChoices::Certificate(self.cert.clone())])
+                        .unwrap(),
+                )),
+                crls: None,
+                signer_infos: SignerInfos(
+                    SetOfVec::try_from(vec![SignerInfo {
+                        version: CmsVersion::V1,
+                        sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+                            issuer: self.cert.tbs_certificate.issuer.clone(),
+                            serial_number: self.cert.tbs_certificate.serial_number.clone(),
+                        }),
+                        digest_alg: sha256,
+                        signed_attrs: Some(signed_attrs),
+                        signature_algorithm: ecdsa_sha256(),
+                        signature: OctetString::new(sig.to_der().as_bytes()).unwrap(),
+                        unsigned_attrs: None,
+                    }])
+                    .unwrap(),
+                ),
+            };
+            ContentInfo {
+                content_type: ID_SIGNED_DATA,
+                content: Any::encode_from(&signed_data).unwrap(),
+            }
+            .to_der()
+            .unwrap()
+        }
+
+        /// Serve `requests` HTTP timestamp requests on 127.0.0.1 and return
+        /// the URL. The server thread exits after the last request.
+        pub fn serve(self, requests: usize) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/tsa", listener.local_addr().unwrap());
+            std::thread::spawn(move || {
+                for stream in listener.incoming().take(requests).flatten() {
+                    let _ = self.answer(stream);
+                }
+            });
+            url
+        }
+
+        /// Answer one HTTP timestamp request. A request that is not a
+        /// TimeStampReq gets a 400 rather than taking the server down.
+        fn answer(&self, mut stream: TcpStream) -> std::io::Result<()> {
+            let mut reader = BufReader::new(stream.try_clone()?);
+            let mut content_length = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line)?;
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = v.trim().parse::<usize>().ok();
+                }
+            }
+            // The body may arrive in several segments: read all of it
+            let mut body = vec![0u8; content_length.unwrap_or(0)];
+            reader.read_exact(&mut body)?;
+
+            let (status, resp) = match TimeStampReq::from_der(&body) {
+                Ok(req) => {
+                    let token =
+                        self.issue(req.message_imprint, req.nonce, std::time::SystemTime::now());
+                    let resp = TimeStampResp {
+                        status: cmpv2::status::PkiStatusInfo {
+                            status: cmpv2::status::PkiStatus::Accepted,
+                            status_string: None,
+                            fail_info: None,
+                        },
+                        time_stamp_token: Some(ContentInfo::from_der(&token).unwrap()),
+                    }
+                    .to_der()
+                    .unwrap();
+                    ("200 OK", resp)
+                }
+                Err(_) => ("400 Bad Request", Vec::new()),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/timestamp-reply\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                resp.len()
+            )?;
+            stream.write_all(&resp)
+        }
+    }
+
+    pub fn imprint_of(data: &[u8]) -> MessageImprint {
+        sha256_imprint(data).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stand_in::*;
+    use super::*;
+
+    #[test]
+    fn request_and_verify_against_stand_in_tsa() {
+        let tsa = StandInTsa::new();
+        let pin = tsa.cert_sha256();
+        let url = tsa.serve(1);
+
+        let token = request_timestamp(&url, b"builder signature").unwrap();
+        let verified = verify_token(&token, b"builder signature").unwrap();
+        assert_eq!(verified.tsa_cert_sha256, pin);
+        assert!(verified.tsa_subject.contains("stand-in TSA"));
+        assert!((Utc::now() - verified.gen_time).num_seconds().abs() < 60);
+
+        // The token covers exactly the data it was requested for
+        assert!(verify_token(&token, b"other signature").is_err());
+    }
+
+    #[test]
+    fn stand_in_tsa_rejects_malformed_request() {
+        let tsa = StandInTsa::new();
+        let url = tsa.serve(2);
+
+        match ureq::post(&url).send_bytes(b"not a TimeStampReq") {
+            Err(ureq::Error::Status(code, _)) => assert_eq!(code, 400),
+            other => panic!("expected a 400, got {:?}", other.map(|r| r.status())),
+        }
+        // The server is still up
+        request_timestamp(&url, b"builder signature").unwrap();
+    }
+
+    #[test]
+    fn tampered_token_rejected() {
+        let tsa = StandInTsa::new();
+        let token = tsa.issue(imprint_of(b"sig"), None, std::time::SystemTime::now());
+        assert!(verify_token(&token, b"sig").is_ok());
+
+        // Locate fields by parsing the token, then edit them in place
+        let signed_data = decode_signed_data(&token).unwrap();
+        let tst_info = parse_tst_info(&signed_data).unwrap();
+        let tamper = |needle: &[u8], edit: &dyn Fn(&mut [u8])| {
+            let found: Vec<usize> = token
+                .windows(needle.len())
+                .enumerate()
+                .filter(|(_, w)| *w == needle)
+                .map(|(i, _)| i)
+                .collect();
+            assert_eq!(found.len(), 1, "field is not unique in the token");
+            let mut tampered = token.clone();
+            edit(&mut tampered[found[0]..found[0] + needle.len()]);
+            verify_token(&tampered, b"sig").unwrap_err().to_string()
+        };
+        let serial = tst_info.serial_number.to_der().unwrap();
+        let gen_time = tst_info.gen_time.to_der().unwrap();
+
+        // Another genTime (a digit of its seconds, before the 'Z'): the
+        // TSTInfo no longer matches the signed digest
+        let err = tamper(&gen_time, &|t| t[t.len() - 2] ^= 0x01);
+        assert!(err.contains("messageDigest does not match"), "{}", err);
+
+        // Another serial number (serialNumber directly precedes genTime)
+        let err = tamper(&[serial.as_slice(), &gen_time].concat(), &|t| {
+            t[serial.len() - 1] ^= 0x02
+        });
+        assert!(err.contains("messageDigest does not match"), "{}", err);
+
+        // A signature over other signed attributes
+        let signer = &signed_data.signer_infos.0.as_slice()[0];
+        let err = tamper(signer.signature.as_bytes(), &|t| t[t.len() - 1] ^= 0x01);
+        assert!(err.contains("signature INVALID"), "{}", err);
+    }
+
+    #[test]
+    fn gen_time_outside_cert_validity_rejected() {
+        let tsa = StandInTsa::new();
+        let long_ago = std::time::SystemTime::now() - Duration::from_secs(10 * 86400);
+        let token = tsa.issue(imprint_of(b"sig"), None, long_ago);
+        let err = verify_token(&token, b"sig").unwrap_err();
+        assert!(err.to_string().contains("validity"));
+    }
+}
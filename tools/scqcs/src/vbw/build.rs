@@ -4,36 +4,100 @@
 // detect lockfiles, gather git state, run the build command, hash outputs,
 // assemble the manifest, sign it, and write the bundle to vbw/.
 //
-// SIGNING: The Ed25519 signature covers canonical manifest bytes (sorted
+// SIGNING: The builder signature covers canonical manifest bytes (sorted
 // keys, compact JSON — see canonical.rs), NOT the pretty-printed file on
 // disk. The stored hash in hashes/manifest.sha256 is also over canonical bytes.
+// The signature file is named after the key's algorithm, e.g.
+// signatures/builder.ed25519.sig or signatures/builder.ecdsa-p256.sig.
+//
+// TIMESTAMPING: With --tsa-url (or policy signing.timestamp.tsa_url), the
+// decoded builder signature is sent to an RFC 3161 TSA and the returned
+// token is stored as signatures/builder.<alg>.tst. See tsa.rs.
+//
+// CHAINING: With --previous <bundle>, the new manifest records the previous
+// bundle's canonical manifest hash and the next sequence number.
+//
+// NETWORK: Mode B builds run with HTTP(S)_PROXY pointed at a local proxy
+// that only lets the policy's allowlisted hosts through and logs every
+// attempt to network.json. See proxy.rs.
+//
+// ENVIRONMENT: The build command never sees the builder's signing key
+// variable. With policy `environment.clean` it starts from an empty
+// environment plus the passthrough variables, recorded as build_env in
+// environment.json. See envvars.rs.
+//
+// TRACING: With --trace-network, the build's whole process tree runs under
+// a ptrace tracer that records every connection and DNS query it attempts
+// into network-access.json, in any mode. With --trace-exec it records the
+// programs the build runs; they are merged into environment.json's tools,
+// so environment.json is written after the build. With --trace-inputs it
+// records the files the build reads into inputs.json; untracked files in
+// the repository are listed in enforcement.untracked_inputs. See trace.rs
+// and inputs.rs.
+//
+// OIDC: With --oidc, the builder key is bound to a CI identity by an OIDC
+// ID token whose audience is derived from the public key. The token is
+// stored as signatures/builder.oidc.jwt and its claims recorded in
+// builder_identity.oidc. With --ephemeral-key the key is generated for
+// this build and discarded. See oidc.rs.
+//
+// WRITING: The bundle is assembled in a staging directory next to vbw/ and
+// then swapped into place, so a crash never leaves a half-written bundle.
+// Build refuses to replace an existing bundle without --force; nothing from
+// it (co-signatures, timestamp tokens) is carried into the new one. The
+// policy file and pipeline steps in vbw/ are inputs and are copied in.
+//
+// SELF-VERIFICATION: With --verify, the staged bundle goes through the full
+// verify pipeline before it replaces vbw/; --rehash-outputs-after then
+// hashes the output directory again after a delay.
+//
+// RESULT: The build command's exit status, wall time, CPU time and peak RSS
+// (from wait4) are recorded in build_result, and transcript.jsonl is hashed
+// into the manifest. A failed build writes no bundle unless
+// --record-failure is given; then the bundle is written and signed as
+// evidence and build still fails.
+//
+// UPSTREAM: With --upstream <bundle>:<file>, a file taken from another
+// project's bundle is recorded as a vbw-artifact material. See upstream.rs.
 //
 // WHAT IS REAL:
 //   - Cryptographic hashing (SHA-256) of all files and artifacts
-//   - Ed25519 signing of canonical manifest bytes
+//   - Ed25519 / ECDSA P-256 signing of canonical manifest bytes
 //   - Git commit/branch/dirty detection
 //   - Source tree hashing via git ls-tree
 //   - Lockfile detection and hashing
-//   - Environment capture (OS, tools, container detection)
-//   - Build command execution with interleaved transcript capture
+//   - Environment capture (OS, tools, container identity, CI context)
+//   - Build command execution with byte-exact transcript capture (transcript.rs)
 //   - Enforcement honesty: manifest records what was actually enforced
+//   - Mode A sandbox: network, mount and filesystem isolation (sandbox.rs)
 //
 // WHAT IS NOT YET IMPLEMENTED (TODOs):
 //   - Vendor tarball hashing (archive_sha256 + extracted_tree_hash)
 //   - Individual dependency hash verification from lockfiles
 
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::BufWriter;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::process::{ChildStderr, ChildStdout, Command, Stdio};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::hash;
 use crate::sign;
 use crate::vbw::canonical;
+use crate::vbw::ci;
+use crate::vbw::container;
+use crate::vbw::envvars;
+use crate::vbw::inputs;
 use crate::vbw::model::*;
+use crate::vbw::proxy::RecordingProxy;
+use crate::vbw::sandbox::{self, SandboxReport};
+use crate::vbw::tools;
+use crate::vbw::trace::{self, ResourceUsage, TraceOptions, TraceReport};
+use crate::vbw::transcript::{self, TranscriptFile, MAX_TRANSCRIPT_BYTES};
+use crate::vbw::verify;
 
 /// Lockfile names to auto-detect in the project root.
 const LOCKFILE_NAMES: &[&str] = &[
@@ -48,16 +112,100 @@ const LOCKFILE_NAMES: &[&str] = &[
     "Pipfile.lock",
 ];
 
+/// Options for `run_build` (from the `vbw build` CLI flags).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuildOptions<'a> {
+    /// Build command and arguments (everything after --).
+    pub build_cmd: &'a [String],
+    /// Project name (default: current directory name).
+    pub project_name: Option<&'a str>,
+    /// Where build artifacts live (default: dist).
+    pub output_dir: Option<&'a str>,
+    /// Where the builder signing key comes from.
+    pub key_source: sign::KeySource<'a>,
+    /// Builder key identifier (default: builder@local).
+    pub key_id: Option<&'a str>,
+    /// Policy file (default: vbw/policy.json).
+    pub policy_path: Option<&'a str>,
+    /// RFC 3161 TSA URL, overriding the policy's.
+    pub tsa_url: Option<&'a str>,
+    /// Previous bundle in the release chain, linked via
+    /// previous_manifest_hash.
+    pub previous_bundle: Option<&'a Path>,
+    /// Run as the named pipeline step, writing a sub-witness to
+    /// vbw/steps/<name>/ instead of a bundle.
+    pub step: Option<&'a str>,
+    /// Earlier steps' outputs consumed by this build ("<step>" or
+    /// "<step>:<path>").
+    pub inputs: &'a [String],
+    /// Artifacts consumed from other projects' bundles
+    /// ("<bundle>:<file>").
+    pub upstream: &'a [String],
+    /// Trace the connections and DNS queries of the build's process tree
+    /// into network-access.json.
+    pub trace_network: bool,
+    /// Trace the programs the build executes into environment.json's
+    /// tools.
+    pub trace_exec: bool,
+    /// Trace the files the build reads into inputs.json.
+    pub trace_inputs: bool,
+    /// OCI image index, manifest or config of the build container.
+    pub container_image: Option<&'a Path>,
+    /// Bind the builder key to an OIDC identity: "github" requests an ID
+    /// token from GitHub Actions, anything else is a token file.
+    pub oidc: Option<&'a str>,
+    /// Sign with a fresh key generated for this build only.
+    pub ephemeral_key: bool,
+    /// Run the verify pipeline on the written bundle and fail if it is
+    /// UNVERIFIED.
+    pub verify: bool,
+    /// After verifying, wait this long and hash the output directory
+    /// again, failing if any output changed.
+    pub rehash_outputs_after: Option<Duration>,
+    /// JWKS to check the OIDC token against when verifying.
+    pub oidc_jwks: Option<&'a Path>,
+    /// Replace an existing bundle in vbw/.
+    pub force: bool,
+    /// Write a signed bundle even when the build command fails, then
+    /// fail the build.
+    pub record_failure: bool,
+}
+
 /// Run the full VBW build workflow.
-pub fn run_build(
-    build_cmd: &[String],
-    project_name: Option<&str>,
-    output_dir: Option<&str>,
-    keyfile: Option<&Path>,
-    key_id: Option<&str>,
-    policy_path: Option<&str>,
-) -> Result<()> {
+pub fn run_build(options: &BuildOptions) -> Result<()> {
+    let BuildOptions {
+        build_cmd,
+        project_name,
+        output_dir,
+        key_source,
+        key_id,
+        policy_path,
+        tsa_url,
+        previous_bundle,
+        step,
+        inputs,
+        upstream,
+        trace_network,
+        trace_exec,
+        trace_inputs,
+        container_image,
+        oidc,
+        ephemeral_key,
+        verify,
+        rehash_outputs_after,
+        oidc_jwks,
+        force,
+        record_failure,
+    } = *options;
     let vbw_dir = PathBuf::from("vbw");
+
+    // A new bundle replaces the old one wholesale, co-signatures included
+    if step.is_none() && vbw_dir.join("manifest.json").exists() && !force {
+        anyhow::bail!(
+            "vbw/ already holds a witness bundle; pass --force to replace it \
+             (its co-signatures and timestamp token are discarded)"
+        );
+    }
     let dist_dir = PathBuf::from(output_dir.unwrap_or("dist"));
 
     // 1. Load or auto-generate policy
@@ -67,10 +215,54 @@ pub fn run_build(
     let policy = load_or_create_policy(&policy_file)?;
     let policy_json = serde_json::to_string_pretty(&policy)?;
     let policy_hash = hash::sha256_hex(policy_json.as_bytes());
+    let timestamp_policy = policy
+        .requirements
+        .signing
+        .as_ref()
+        .and_then(|s| s.timestamp.clone())
+        .unwrap_or_default();
+    let tsa_url = tsa_url
+        .map(str::to_string)
+        .or(timestamp_policy.tsa_url.clone());
+    if tsa_url.is_none() && timestamp_policy.required.unwrap_or(false) {
+        anyhow::bail!(
+            "policy requires a trusted timestamp but no TSA is configured \
+             (set signing.timestamp.tsa_url or pass --tsa-url)"
+        );
+    }
+    let require_verified_oidc = policy
+        .requirements
+        .signing
+        .as_ref()
+        .and_then(|s| s.require_verified_oidc)
+        .unwrap_or(false);
+    if verify && require_verified_oidc && oidc_jwks.is_none() {
+        anyhow::bail!(
+            "policy requires a verified OIDC identity, so --verify needs the issuer's keys \
+             (pass --oidc-jwks)"
+        );
+    }
+
+    // Read the previous bundle now, so a bad path fails before the build runs
+    let previous = previous_bundle.map(load_previous_link).transpose()?;
+
+    // Likewise check step inputs against the producing steps' recorded outputs
+    let steps_dir = vbw_dir.join("steps");
+    if let Some(step) = step {
+        if !is_valid_step_name(step) {
+            anyhow::bail!("invalid step name '{}' (use 1-64 of a-z, 0-9, '-', '_')", step);
+        }
+        if oidc.is_some() {
+            anyhow::bail!("--oidc binds the final bundle's key; it cannot be used with --step");
+        }
+    }
+    let step_inputs = resolve_step_inputs(&steps_dir, inputs)?;
+    let upstream_inputs = crate::vbw::upstream::resolve_upstream_inputs(upstream)?;
 
     // 2. Attempt mode enforcement and set SOURCE_DATE_EPOCH for Mode A
     let mode = &policy.requirements.reproducibility.mode;
     let sde_before = std::env::var("SOURCE_DATE_EPOCH").ok();
+    #[allow(clippy::collapsible_if)]
     if *mode == ReproducibilityMode::A_DETERMINISTIC {
         if sde_before.is_none() {
             // Set SOURCE_DATE_EPOCH to current time if not already set.
@@ -89,18 +281,45 @@ pub fn run_build(
         None
     };
 
-    // 3. Load signing key
-    let secret_key = sign::load_secret_key(keyfile)?;
-    let public_key = sign::public_key_from_secret(&secret_key)?;
+    // 3. Load signing key (algorithm is selected by the key itself, which
+    //    may live on a PKCS#11 token when --signer is given).
+    //    A hybrid key adds a post-quantum signer alongside the classical one.
+    //    An ephemeral key exists only in memory, for this build.
+    let keys = if ephemeral_key {
+        eprintln!("[vbw] Signing with an ephemeral Ed25519 key");
+        sign::keyset_from_secret(&zeroize::Zeroizing::new(sign::keygen().0))?
+    } else {
+        sign::load_keyset(&key_source)?
+    };
+    let key_type = keys.classical.algorithm();
+    let (public_key_ed25519, public_key) =
+        public_key_fields(key_type, keys.classical.public_key()?);
+    let hybrid = match &keys.post_quantum {
+        Some(pq) => Some(HybridKey {
+            key_type: pq.algorithm(),
+            public_key: pq.public_key()?,
+        }),
+        None => None,
+    };
     let resolved_key_id = key_id.unwrap_or("builder@local").to_string();
 
     // 4. Capture environment
-    let environment = capture_environment(&policy)?;
-    let env_json = serde_json::to_string_pretty(&environment)?;
-    let env_hash = hash::sha256_hex(env_json.as_bytes());
+    let clean_env = envvars::clean_environment(
+        policy.requirements.environment.as_ref(),
+        envvars::process_vars(),
+    );
+    if let Some(env) = &clean_env {
+        eprintln!(
+            "[vbw] Clean build environment: {} variable(s) passed through",
+            env.len()
+        );
+    }
+    let mut environment = capture_environment(&policy, clean_env.as_ref(), container_image)?;
 
     // 5. Detect and hash lockfiles → materials_lock
-    let materials_lock = detect_materials()?;
+    let mut materials_lock = detect_materials()?;
+    materials_lock.materials.extend(step_inputs);
+    materials_lock.materials.extend(upstream_inputs);
     let mat_json = serde_json::to_string_pretty(&materials_lock)?;
     let mat_hash = hash::sha256_hex(mat_json.as_bytes());
 
@@ -118,28 +337,142 @@ pub fn run_build(
         None
     };
 
-    // 9. Run build command, capture interleaved transcript.
-    //    Mode A: attempt to wrap the build in a network namespace.
-    let (transcript, network_blocked) = if *mode == ReproducibilityMode::A_DETERMINISTIC {
-        match run_build_network_isolated(build_cmd) {
-            Ok(t) => {
-                eprintln!("[vbw] Mode A: build ran with network isolation (unshare -rn)");
-                (t, true)
-            }
-            Err(e) => {
-                eprintln!(
-                    "[vbw] WARNING: Mode A network isolation failed ({}), \
-                     running without isolation",
-                    e
-                );
-                eprintln!("[vbw] Running build: {}", build_cmd.join(" "));
-                (run_build_command(build_cmd)?, false)
+    // 9. Run build command, capture its output into the transcript file.
+    //    Mode A: attempt to run the build in the sandbox.
+    //    Mode B: route HTTP(S) through the allowlist proxy.
+    let proxy = if *mode == ReproducibilityMode::B_LOCKED_NETWORK {
+        let network = &policy.requirements.network;
+        let proxy = RecordingProxy::start(
+            network.allowed,
+            network.allowlist.as_deref().unwrap_or(&[]),
+        )?;
+        eprintln!("[vbw] Mode B: HTTP(S) traffic goes through the allowlist proxy");
+        Some(proxy)
+    } else {
+        None
+    };
+    let proxy_env = proxy.as_ref().map(|p| p.env()).unwrap_or_default();
+    let trace_options = TraceOptions {
+        network: trace_network,
+        exec: trace_exec,
+        inputs: trace_inputs,
+        from_start_marker: false,
+    };
+    if trace_network {
+        eprintln!("[vbw] Tracing network access of the build's process tree");
+    }
+    if trace_exec {
+        eprintln!("[vbw] Tracing programs executed by the build's process tree");
+    }
+    if trace_inputs {
+        eprintln!("[vbw] Tracing files read by the build's process tree");
+    }
+    let transcript = TranscriptFile::new()?;
+    let ((build_result, trace_report), network_blocked, sandbox_report) =
+        if *mode == ReproducibilityMode::A_DETERMINISTIC {
+            match run_build_sandboxed(
+                build_cmd,
+                &dist_dir,
+                clean_env.as_ref(),
+                trace_options,
+                transcript.path(),
+            ) {
+                Ok((t, report)) => {
+                    eprintln!(
+                        "[vbw] Mode A: sandbox layers in effect: {}",
+                        report.succeeded.join(", ")
+                    );
+                    if !report.complete() {
+                        eprintln!(
+                            "[vbw] WARNING: Mode A sandbox layers failed: {}",
+                            report.failed.join(", ")
+                        );
+                    }
+                    (t, true, Some(report))
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[vbw] WARNING: Mode A sandbox failed ({}), \
+                         running without isolation",
+                        e
+                    );
+                    eprintln!("[vbw] Running build: {}", build_cmd.join(" "));
+                    (
+                        run_build_command(
+                            build_cmd,
+                            clean_env.as_ref(),
+                            &[],
+                            trace_options,
+                            transcript.path(),
+                        )?,
+                        false,
+                        None,
+                    )
+                }
             }
+        } else {
+            eprintln!("[vbw] Running build: {}", build_cmd.join(" "));
+            (
+                run_build_command(
+                    build_cmd,
+                    clean_env.as_ref(),
+                    &proxy_env,
+                    trace_options,
+                    transcript.path(),
+                )?,
+                false,
+                None,
+            )
+        };
+    eprintln!("[vbw] Build command: {}", build_result.summary());
+    if !build_result.success {
+        if !record_failure {
+            anyhow::bail!("Build command failed with {}", build_result.describe_exit());
         }
-    } else {
-        eprintln!("[vbw] Running build: {}", build_cmd.join(" "));
-        (run_build_command(build_cmd)?, false)
+        eprintln!(
+            "[vbw] WARNING: build command failed ({}); recording a failed-build bundle",
+            build_result.describe_exit()
+        );
+    }
+    if let Some(access) = &trace_report.network {
+        eprintln!(
+            "[vbw] Traced {} network destination(s), {} DNS name(s)",
+            access.connections.len(),
+            access.dns_queries.len()
+        );
+    }
+    // Classify inputs against the detected tools, before executed programs
+    // join them
+    let inputs_log = match trace_report.files_read {
+        Some(files_read) => Some(inputs::classify_inputs(files_read, &environment.tools)?),
+        None => None,
     };
+    let untracked_inputs = inputs_log.as_ref().map(inputs::untracked);
+    if let (Some(log), Some(untracked)) = (&inputs_log, &untracked_inputs) {
+        eprintln!(
+            "[vbw] Traced {} file(s) read, {} untracked in the repository",
+            log.files.len(),
+            untracked.len()
+        );
+        for path in untracked {
+            eprintln!("[vbw] WARNING: build read untracked file {}", path);
+        }
+    }
+    if let Some(executables) = trace_report.executables {
+        eprintln!("[vbw] Traced {} executed program(s)", executables.len());
+        tools::record_invocations(&mut environment.tools, executables);
+    }
+    let env_json = serde_json::to_string_pretty(&environment)?;
+    let env_hash = hash::sha256_hex(env_json.as_bytes());
+    let network_log = proxy.map(RecordingProxy::finish);
+    if let Some(log) = &network_log {
+        let blocked = log.connections.iter().filter(|c| !c.allowed).count();
+        eprintln!(
+            "[vbw] Mode B: {} connection(s) through the proxy, {} blocked",
+            log.connections.len(),
+            blocked
+        );
+    }
 
     // Mode B: verify lockfile integrity after the build
     let lockfiles_intact = if let Some(ref pre_hashes) = pre_build_lockfile_hashes {
@@ -173,7 +506,14 @@ pub fn run_build(
     };
 
     // Compute enforcement record now that we know what actually happened
-    let enforcement = compute_enforcement(&policy, network_blocked, lockfiles_intact);
+    let enforcement = compute_enforcement(
+        &policy,
+        network_blocked,
+        lockfiles_intact,
+        sandbox_report.as_ref(),
+        network_log.as_ref(),
+        untracked_inputs,
+    );
     if !enforcement.mode_enforced {
         eprintln!(
             "[vbw] WARNING: Requested mode {:?} but full enforcement was not achieved.",
@@ -189,6 +529,77 @@ pub fn run_build(
     let out_json = serde_json::to_string_pretty(&outputs)?;
     let out_hash = hash::sha256_hex(out_json.as_bytes());
 
+    let net_json = match &network_log {
+        Some(log) => Some(serde_json::to_string_pretty(log)?),
+        None => None,
+    };
+    let access_json = match &trace_report.network {
+        Some(access) => Some(serde_json::to_string_pretty(access)?),
+        None => None,
+    };
+    let inputs_json = match &inputs_log {
+        Some(log) => Some(serde_json::to_string_pretty(log)?),
+        None => None,
+    };
+
+    // Bind the key to the CI identity now, so the token is fresh
+    let oidc_token = match oidc {
+        Some(source) => Some(bind_oidc_identity(
+            source,
+            &keys.classical.public_key()?,
+            &git_info.commit,
+        )?),
+        None => None,
+    };
+
+    let builder_identity = BuilderIdentity {
+        key_id: resolved_key_id,
+        key_type: Some(key_type),
+        public_key_ed25519,
+        public_key,
+        issuer: oidc_token.as_ref().map(|(_, issuer, _)| issuer.clone()),
+        oidc: oidc_token.as_ref().map(|(_, _, binding)| binding.clone()),
+        hybrid,
+        ssh_fingerprint: keys.classical.key_fingerprint(),
+    };
+
+    // Pipeline step: write the signed sub-witness and stop here
+    if let Some(step) = step {
+        let step_manifest = StepManifest {
+            vbw_version: "1.0".to_string(),
+            step: step.to_string(),
+            build_id: uuid::Uuid::new_v4().to_string(),
+            created_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            git: GitRef {
+                commit: git_info.commit,
+                branch: git_info.branch,
+                tag: git_info.tag,
+                dirty: git_info.dirty,
+            },
+            source_commit_tree_hash,
+            command: build_cmd.to_vec(),
+            environment_hash: env_hash,
+            materials_lock_hash: mat_hash,
+            outputs_hash: out_hash,
+            transcript_hash: hash::hash_file(transcript.path())?,
+            policy_hash,
+            builder_identity,
+            build_result: Some(build_result),
+            enforcement: Some(enforcement),
+        };
+        return write_step(
+            &steps_dir.join(step),
+            &step_manifest,
+            &keys,
+            &[
+                ("environment.json", &env_json),
+                ("materials.lock.json", &mat_json),
+                ("outputs.json", &out_json),
+            ],
+            transcript,
+        );
+    }
+
     // 11. Determine project name
     let proj_name = project_name
         .map(|s| s.to_string())
@@ -199,7 +610,18 @@ pub fn run_build(
         })
         .unwrap_or_else(|| "unknown".to_string());
 
-    // 12. Build manifest
+    if let Some(previous) = &previous {
+        if previous.project != proj_name {
+            anyhow::bail!(
+                "previous bundle is for project '{}', not '{}'",
+                previous.project,
+                proj_name
+            );
+        }
+    }
+
+    // 12. Build manifest, composing any pipeline steps in vbw/steps/
+    let steps = collect_steps(&steps_dir, &git_info.commit)?;
     let build_id = uuid::Uuid::new_v4().to_string();
     let created_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
 
@@ -223,15 +645,23 @@ pub fn run_build(
         materials_lock_hash: mat_hash,
         environment_hash: env_hash,
         outputs_hash: out_hash,
-        builder_identity: BuilderIdentity {
-            key_id: resolved_key_id,
-            public_key_ed25519: public_key,
-            issuer: None,
-        },
+        builder_identity,
         policy_ref: PolicyRef {
             path: policy_file.to_string_lossy().to_string(),
             hash_sha256: policy_hash,
         },
+        sequence: previous.as_ref().map(|p| p.sequence + 1),
+        previous_manifest_hash: previous.as_ref().map(|p| p.manifest_hash.clone()),
+        steps,
+        network_log_hash: net_json.as_deref().map(|j| hash::sha256_hex(j.as_bytes())),
+        network_access_hash: access_json
+            .as_deref()
+            .map(|j| hash::sha256_hex(j.as_bytes())),
+        inputs_hash: inputs_json
+            .as_deref()
+            .map(|j| hash::sha256_hex(j.as_bytes())),
+        transcript_hash: Some(hash::hash_file(transcript.path())?),
+        build_result: Some(build_result),
         enforcement: Some(enforcement),
         notes: None,
         ext: None,
@@ -242,84 +672,624 @@ pub fn run_build(
     //     The pretty-printed file on disk is for human readability only.
     let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
     let manifest_hash = hash::sha256_hex(&canonical_bytes);
-    let signature = sign::sign(&secret_key, &canonical_bytes)?;
+    // Hybrid mode: every signer signs the same canonical bytes.
+    let mut signatures = Vec::new();
+    for signer in keys.signers() {
+        signatures.push((
+            builder_signature_filename(signer.algorithm()),
+            signer.sign(&canonical_bytes)?,
+        ));
+    }
+
+    // Timestamp the primary builder signature. The token covers the raw
+    // signature bytes, so it proves the signature existed at genTime.
+    let timestamp_filename = builder_timestamp_filename(keys.classical.algorithm());
+    let timestamp_token = match &tsa_url {
+        Some(url) => {
+            let signature = sign::decode_signature(&signatures[0].1)?;
+            eprintln!("[vbw] Requesting RFC 3161 timestamp from {}", url);
+            Some(crate::tsa::request_timestamp(url, &signature)?)
+        }
+        None => None,
+    };
 
     // Write pretty manifest for human readability
     let manifest_json_pretty = serde_json::to_string_pretty(&manifest)?;
 
-    // 14. Write all files
-    fs::create_dir_all(vbw_dir.join("signatures"))?;
-    fs::create_dir_all(vbw_dir.join("hashes"))?;
+    // 14. Assemble the bundle in a staging directory next to vbw/, then
+    //     swap it into place, so a crash never leaves a half-written bundle
+    //     and nothing from an earlier bundle (co-signatures, timestamp
+    //     tokens) is carried forward. Pipeline steps are part of the bundle.
+    let staged = StagedDir::new(&vbw_dir)?;
+    let bundle = staged.path();
+    fs::create_dir_all(bundle.join("signatures"))?;
+    fs::create_dir_all(bundle.join("hashes"))?;
+
+    fs::write(bundle.join("manifest.json"), &manifest_json_pretty)?;
+    fs::write(bundle.join("environment.json"), &env_json)?;
+    fs::write(bundle.join("materials.lock.json"), &mat_json)?;
+    fs::write(bundle.join("outputs.json"), &out_json)?;
+    transcript.persist(&bundle.join(TRANSCRIPT_FILE))?;
+    fs::write(bundle.join("policy.json"), &policy_json)?;
+    if let Some(json) = &net_json {
+        fs::write(bundle.join(NETWORK_LOG_FILE), json)?;
+    }
+    if let Some(json) = &access_json {
+        fs::write(bundle.join(NETWORK_ACCESS_FILE), json)?;
+    }
+    if let Some(json) = &inputs_json {
+        fs::write(bundle.join(INPUTS_FILE), json)?;
+    }
+    for (filename, signature) in &signatures {
+        fs::write(bundle.join("signatures").join(filename), signature)?;
+    }
+    if let Some(token) = &timestamp_token {
+        fs::write(bundle.join("signatures").join(&timestamp_filename), token)?;
+    }
+    if let Some((token, _, _)) = &oidc_token {
+        fs::write(bundle.join("signatures").join(OIDC_TOKEN_FILE), token)?;
+    }
+    fs::write(bundle.join("hashes/manifest.sha256"), &manifest_hash)?;
+    if manifest.steps.is_some() {
+        copy_dir(&steps_dir, &bundle.join("steps"))?;
+    }
 
-    fs::write(vbw_dir.join("manifest.json"), &manifest_json_pretty)?;
-    fs::write(vbw_dir.join("environment.json"), &env_json)?;
-    fs::write(vbw_dir.join("materials.lock.json"), &mat_json)?;
-    fs::write(vbw_dir.join("outputs.json"), &out_json)?;
-    fs::write(vbw_dir.join("transcript.txt"), &transcript)?;
-    fs::write(vbw_dir.join("policy.json"), &policy_json)?;
-    fs::write(vbw_dir.join("signatures/builder.ed25519.sig"), &signature)?;
-    fs::write(vbw_dir.join("hashes/manifest.sha256"), &manifest_hash)?;
+    // 15. Check the bundle as a consumer would before it replaces anything.
+    //     A failed build's bundle is UNVERIFIED by design, so it is not.
+    let succeeded = manifest.build_result.as_ref().is_some_and(|r| r.success);
+    if verify && succeeded {
+        self_verify(bundle, upstream, oidc_jwks)?;
+    }
+    if let Some(delay) = rehash_outputs_after.filter(|_| succeeded) {
+        check_outputs_settled(&dist_dir, &outputs, delay)?;
+    }
+    staged.commit()?;
 
     eprintln!("[vbw] Witness bundle written to vbw/");
     eprintln!("[vbw] Signature covers canonical manifest bytes (sorted keys, compact JSON).");
+    if keys.post_quantum.is_some() {
+        eprintln!("[vbw] Hybrid signing: {} signatures written.", signatures.len());
+    }
+    if timestamp_token.is_some() {
+        eprintln!("[vbw] Timestamp token: signatures/{}", timestamp_filename);
+    }
     eprintln!("[vbw] Build ID: {}", manifest.build_id);
     eprintln!("[vbw] Manifest hash (canonical): {}", manifest_hash);
+    if let Some(steps) = &manifest.steps {
+        let names: Vec<&str> = steps.iter().map(|s| s.name.as_str()).collect();
+        eprintln!("[vbw] Pipeline steps: {}", names.join(" → "));
+    }
     eprintln!("[vbw] Artifacts: {} file(s)", outputs.artifacts.len());
 
+    match &manifest.build_result {
+        Some(result) if !result.success => anyhow::bail!(
+            "Build command failed with {}; the failed build is witnessed in vbw/",
+            result.describe_exit()
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Run the full verify pipeline on a freshly assembled bundle (with the
+/// --upstream bundles the build consumed, and the --oidc-jwks keys).
+fn self_verify(bundle: &Path, upstream: &[String], oidc_jwks: Option<&Path>) -> Result<()> {
+    eprintln!("[vbw] Self-verification of the new bundle:");
+    let upstream_dirs: Vec<PathBuf> = upstream
+        .iter()
+        .filter_map(|spec| spec.split_once(':'))
+        .map(|(bundle, _)| PathBuf::from(bundle))
+        .collect();
+    let verdict = verify::run_verify(
+        bundle,
+        &verify::VerifyOptions {
+            upstream_dirs: &upstream_dirs,
+            oidc_jwks,
+        },
+    )?;
+    if let verify::Verdict::Unverified(errors) = verdict {
+        anyhow::bail!(
+            "self-verification failed: the new bundle is UNVERIFIED ({} error(s)); \
+             vbw/ was left unchanged",
+            errors.len()
+        );
+    }
+    Ok(())
+}
+
+/// Wait `delay`, then hash the output directory again and fail if any
+/// output changed since it was recorded.
+fn check_outputs_settled(dist_dir: &Path, outputs: &Outputs, delay: Duration) -> Result<()> {
+    eprintln!(
+        "[vbw] Hashing {} again in {}s",
+        dist_dir.display(),
+        delay.as_secs()
+    );
+    thread::sleep(delay);
+    let changes = output_changes(outputs, &collect_outputs(dist_dir)?);
+    if !changes.is_empty() {
+        anyhow::bail!(
+            "outputs changed after they were hashed: {}",
+            changes.join(", ")
+        );
+    }
+    eprintln!("[vbw] Outputs unchanged");
+    Ok(())
+}
+
+/// Differences between the recorded outputs and a later hash of the
+/// output directory, as "<path> (changed|new|removed)".
+fn output_changes(recorded: &Outputs, current: &Outputs) -> Vec<String> {
+    let before: BTreeMap<&str, &str> = recorded
+        .artifacts
+        .iter()
+        .map(|a| (a.path.as_str(), a.sha256.as_str()))
+        .collect();
+    let after: BTreeMap<&str, &str> = current
+        .artifacts
+        .iter()
+        .map(|a| (a.path.as_str(), a.sha256.as_str()))
+        .collect();
+    let mut changes = Vec::new();
+    for (path, sha256) in &after {
+        match before.get(path) {
+            Some(recorded) if recorded == sha256 => {}
+            Some(_) => changes.push(format!("{} (changed)", path)),
+            None => changes.push(format!("{} (new)", path)),
+        }
+    }
+    for path in before.keys().filter(|p| !after.contains_key(*p)) {
+        changes.push(format!("{} (removed)", path));
+    }
+    changes
+}
+
+/// Obtain an OIDC ID token for the builder key from `source` ("github" or
+/// a token file) and check that it fits this build. Returns the token,
+/// its issuer and the binding recorded in the manifest.
+fn bind_oidc_identity(
+    source: &str,
+    public_key_b64: &str,
+    commit: &str,
+) -> Result<(String, String, OidcBinding)> {
+    let audience = crate::oidc::key_audience(public_key_b64)?;
+    let token = if source == "github" {
+        eprintln!("[vbw] Requesting a GitHub Actions ID token for {}", audience);
+        crate::oidc::request_github_token(&audience)?
+    } else {
+        fs::read_to_string(source)
+            .with_context(|| format!("reading OIDC token {}", source))?
+            .trim()
+            .to_string()
+    };
+    let claims = crate::oidc::decode(&token)?.claims;
+    if !claims.has_audience(&audience) {
+        anyhow::bail!(
+            "OIDC token is not bound to the builder key: its audience must be {}",
+            audience
+        );
+    }
+    if claims.exp <= chrono::Utc::now().timestamp() {
+        anyhow::bail!("OIDC token has expired");
+    }
+    if let Some(sha) = claims.sha.as_deref().filter(|sha| *sha != commit) {
+        anyhow::bail!("OIDC token is for commit {}, but HEAD is {}", sha, commit);
+    }
+    eprintln!(
+        "[vbw] Builder key bound to OIDC identity {} (issuer {})",
+        claims.sub, claims.iss
+    );
+    let binding = OidcBinding::from_token(&token, &claims, &audience);
+    Ok((token, claims.iss, binding))
+}
+
+/// Sign a step manifest and write the step's sub-witness to `step_dir`.
+fn write_step(
+    step_dir: &Path,
+    step_manifest: &StepManifest,
+    keys: &sign::KeySet,
+    files: &[(&str, &str)],
+    transcript: TranscriptFile,
+) -> Result<()> {
+    let canonical_bytes = canonical::canonical_manifest_bytes(step_manifest);
+
+    // Replace any earlier run of this step wholesale, so no stale files remain
+    fs::create_dir_all(step_dir.parent().unwrap_or(Path::new(".")))?;
+    let staged = StagedDir::new(step_dir)?;
+    let dir = staged.path();
+    fs::create_dir_all(dir.join("signatures"))?;
+    fs::write(
+        dir.join("step.json"),
+        serde_json::to_string_pretty(step_manifest)?,
+    )?;
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents)?;
+    }
+    transcript.persist(&dir.join(TRANSCRIPT_FILE))?;
+    for signer in keys.signers() {
+        fs::write(
+            dir.join("signatures")
+                .join(builder_signature_filename(signer.algorithm())),
+            signer.sign(&canonical_bytes)?,
+        )?;
+    }
+    staged.commit()?;
+
+    eprintln!(
+        "[vbw] Step '{}' witnessed: {}",
+        step_manifest.step,
+        step_dir.display()
+    );
+    eprintln!(
+        "[vbw] Step hash (canonical): {}",
+        hash::sha256_hex(&canonical_bytes)
+    );
+    Ok(())
+}
+
+/// A directory assembled next to `target` and then swapped into its place.
+/// Dropped without `commit`, it is removed and `target` is left as it was.
+struct StagedDir {
+    path: PathBuf,
+    target: PathBuf,
+    committed: bool,
+}
+
+impl StagedDir {
+    fn new(target: &Path) -> Result<Self> {
+        let name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "vbw".to_string());
+        let path = target.with_file_name(format!(".{}.tmp-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir(&path).with_context(|| format!("creating {}", path.display()))?;
+        Ok(StagedDir {
+            path,
+            target: target.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the staged directory to the target, replacing what was there.
+    /// On Linux an existing target is exchanged atomically
+    /// (renameat2 RENAME_EXCHANGE); elsewhere it is renamed aside first.
+    fn commit(mut self) -> Result<()> {
+        self.committed = true;
+        if !self.target.exists() {
+            fs::rename(&self.path, &self.target)
+                .with_context(|| format!("moving bundle into {}", self.target.display()))?;
+            return Ok(());
+        }
+        #[cfg(target_os = "linux")]
+        if exchange_dirs(&self.path, &self.target).is_ok() {
+            // The staging path now holds the old directory
+            return fs::remove_dir_all(&self.path)
+                .with_context(|| format!("removing {}", self.path.display()));
+        }
+        let aside = self.path.with_extension("old");
+        fs::rename(&self.target, &aside)
+            .with_context(|| format!("moving {} aside", self.target.display()))?;
+        fs::rename(&self.path, &self.target)
+            .with_context(|| format!("moving bundle into {}", self.target.display()))?;
+        fs::remove_dir_all(&aside).with_context(|| format!("removing {}", aside.display()))
+    }
+}
+
+impl Drop for StagedDir {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Atomically exchange two directories.
+#[cfg(target_os = "linux")]
+fn exchange_dirs(a: &Path, b: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    let rc = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Recursively copy the directory `from` to `to`.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from).with_context(|| format!("reading {}", from.display()))? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), &dest)
+                .with_context(|| format!("copying {}", entry.path().display()))?;
+        }
+    }
     Ok(())
 }
 
+/// Resolve `--input <step>[:<path>]` against the step sub-witnesses in
+/// `steps_dir`. Each consumed artifact is re-hashed on disk and must match
+/// the producing step's outputs.json. Returns them as step-output materials.
+fn resolve_step_inputs(steps_dir: &Path, inputs: &[String]) -> Result<Vec<MaterialEntry>> {
+    let mut materials: Vec<MaterialEntry> = Vec::new();
+    for spec in inputs {
+        let (step, path) = match spec.split_once(':') {
+            Some((step, path)) => (step, Some(path)),
+            None => (spec.as_str(), None),
+        };
+        if !is_valid_step_name(step) {
+            anyhow::bail!("invalid step name in --input '{}'", spec);
+        }
+        let outputs_path = steps_dir.join(step).join("outputs.json");
+        let outputs: Outputs = serde_json::from_str(
+            &fs::read_to_string(&outputs_path).with_context(|| {
+                format!(
+                    "reading outputs of step '{}' ({}); run `vbw build --step {}` first",
+                    step,
+                    outputs_path.display(),
+                    step
+                )
+            })?,
+        )
+        .with_context(|| format!("parsing {}", outputs_path.display()))?;
+
+        let selected: Vec<&Artifact> = outputs
+            .artifacts
+            .iter()
+            .filter(|a| path.is_none_or(|p| a.path == p))
+            .collect();
+        if selected.is_empty() {
+            match path {
+                Some(p) => anyhow::bail!("step '{}' has no output '{}'", step, p),
+                None => anyhow::bail!("step '{}' has no outputs", step),
+            }
+        }
+
+        for artifact in selected {
+            let actual = hash::hash_file(Path::new(&artifact.path)).with_context(|| {
+                format!("hashing input {} from step '{}'", artifact.path, step)
+            })?;
+            if actual != artifact.sha256 {
+                anyhow::bail!(
+                    "input {} does not match the output of step '{}' (expected {}, found {})",
+                    artifact.path,
+                    step,
+                    artifact.sha256,
+                    actual
+                );
+            }
+            if materials
+                .iter()
+                .any(|m| m.name == artifact.path && m.source.as_deref() == Some(step))
+            {
+                continue;
+            }
+            materials.push(MaterialEntry {
+                name: artifact.path.clone(),
+                kind: STEP_OUTPUT_KIND.to_string(),
+                source: Some(step.to_string()),
+                sha256: actual,
+                upstream_manifest_hash: None,
+                upstream_path: None,
+                archive_sha256: None,
+                extracted_tree_hash: None,
+            });
+        }
+        eprintln!("[vbw] Input from step '{}': {}", step, path.unwrap_or("all outputs"));
+    }
+    Ok(materials)
+}
+
+/// Collect the step sub-witnesses in `steps_dir` for the final build, in
+/// execution order: every step after the steps it takes inputs from (ties
+/// broken by creation time). Steps must come from the same commit.
+/// Returns None when there are no steps.
+fn collect_steps(steps_dir: &Path, commit: &str) -> Result<Option<Vec<StepRef>>> {
+    if !steps_dir.exists() {
+        return Ok(None);
+    }
+    struct Loaded {
+        step_ref: StepRef,
+        created_at: String,
+        depends_on: Vec<String>,
+    }
+
+    let mut names: Vec<String> = fs::read_dir(steps_dir)
+        .with_context(|| format!("reading {}", steps_dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    let mut loaded = Vec::new();
+    for name in names {
+        if !is_valid_step_name(&name) {
+            anyhow::bail!("invalid step directory {}/{}", steps_dir.display(), name);
+        }
+        let dir = steps_dir.join(&name);
+        let step_manifest: StepManifest = serde_json::from_str(
+            &fs::read_to_string(dir.join("step.json"))
+                .with_context(|| format!("reading {}/step.json", dir.display()))?,
+        )
+        .with_context(|| format!("parsing {}/step.json", dir.display()))?;
+        if step_manifest.step != name {
+            anyhow::bail!(
+                "{}/step.json is for step '{}'",
+                dir.display(),
+                step_manifest.step
+            );
+        }
+        if step_manifest.git.commit != commit {
+            anyhow::bail!(
+                "step '{}' was built from commit {}, not {} (stale step? remove {})",
+                name,
+                step_manifest.git.commit,
+                commit,
+                dir.display()
+            );
+        }
+        let materials: MaterialsLock = serde_json::from_str(
+            &fs::read_to_string(dir.join("materials.lock.json"))
+                .with_context(|| format!("reading {}/materials.lock.json", dir.display()))?,
+        )?;
+        let depends_on = materials
+            .materials
+            .into_iter()
+            .filter(|m| m.kind == STEP_OUTPUT_KIND)
+            .filter_map(|m| m.source)
+            .collect();
+        loaded.push(Loaded {
+            step_ref: StepRef {
+                name,
+                step_hash: hash::sha256_hex(&canonical::canonical_manifest_bytes(&step_manifest)),
+            },
+            created_at: step_manifest.created_at,
+            depends_on,
+        });
+    }
+    if loaded.is_empty() {
+        return Ok(None);
+    }
+
+    let mut ordered: Vec<StepRef> = Vec::new();
+    while !loaded.is_empty() {
+        let ready = loaded
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                s.depends_on
+                    .iter()
+                    .all(|d| ordered.iter().any(|o| o.name == *d))
+            })
+            .min_by(|(_, a), (_, b)| {
+                (&a.created_at, &a.step_ref.name).cmp(&(&b.created_at, &b.step_ref.name))
+            })
+            .map(|(i, _)| i);
+        match ready {
+            Some(i) => ordered.push(loaded.remove(i).step_ref),
+            None => {
+                let stuck: Vec<String> = loaded.iter().map(|s| s.step_ref.name.clone()).collect();
+                anyhow::bail!(
+                    "pipeline steps {} take inputs from steps that are missing or circular",
+                    stuck.join(", ")
+                );
+            }
+        }
+    }
+    Ok(Some(ordered))
+}
+
 /// Compute enforcement flags based on what was actually enforced at build time.
+///
+/// Mode A counts as enforced only if every sandbox layer held and
+/// SOURCE_DATE_EPOCH was set; the notes list which layers succeeded.
+///
+/// Mode B records the destinations the allowlist proxy refused.
+///
+/// In any mode, untracked files the build read (--trace-inputs) are
+/// recorded as a provenance gap. They do not affect `mode_enforced`.
 fn compute_enforcement(
     policy: &Policy,
     network_blocked: bool,
     lockfiles_intact: bool,
+    sandbox: Option<&SandboxReport>,
+    network_log: Option<&NetworkLog>,
+    untracked_inputs: Option<Vec<String>>,
 ) -> Enforcement {
     let mode = &policy.requirements.reproducibility.mode;
     let sde_set = std::env::var("SOURCE_DATE_EPOCH").is_ok();
 
-    match mode {
+    let mut enforcement = match mode {
         ReproducibilityMode::A_DETERMINISTIC => {
-            let enforced = network_blocked && sde_set;
+            let sandbox_complete = sandbox.is_some_and(|s| s.complete());
+            let enforced = network_blocked && sandbox_complete && sde_set;
             let mut notes_parts = Vec::new();
             if !network_blocked {
                 notes_parts.push(
-                    "network isolation via unshare failed (may require user namespaces)",
+                    "network isolation via unshare failed (may require user namespaces)"
+                        .to_string(),
                 );
             }
+            if let Some(report) = sandbox {
+                notes_parts.push(format!(
+                    "sandbox layers in effect: {}",
+                    report.succeeded.join(", ")
+                ));
+                if !report.complete() {
+                    notes_parts.push(format!(
+                        "sandbox layers failed: {}",
+                        report.failed.join(", ")
+                    ));
+                }
+            }
             if !sde_set {
-                notes_parts.push("SOURCE_DATE_EPOCH was not set");
+                notes_parts.push("SOURCE_DATE_EPOCH was not set".to_string());
             }
             Enforcement {
                 mode_requested: mode.clone(),
                 mode_enforced: enforced,
                 network_blocked,
                 source_date_epoch_set: sde_set,
-                notes: if enforced {
-                    None
-                } else {
-                    Some(format!(
-                        "Mode A partially enforced: {}",
-                        notes_parts.join("; ")
-                    ))
-                },
+                network_violations: None,
+                untracked_inputs: None,
+                notes: Some(format!(
+                    "Mode A {}: {}",
+                    if enforced { "enforced" } else { "partially enforced" },
+                    notes_parts.join("; ")
+                )),
             }
         }
         ReproducibilityMode::B_LOCKED_NETWORK => {
             let enforced = lockfiles_intact;
+            let mut violations: Vec<String> = Vec::new();
+            for c in network_log.iter().flat_map(|l| &l.connections) {
+                let destination = format!("{}:{}", c.host, c.port);
+                if !c.allowed && !violations.contains(&destination) {
+                    violations.push(destination);
+                }
+            }
+            let mut notes_parts = Vec::new();
+            if !enforced {
+                notes_parts.push(
+                    "lockfile integrity check failed — lockfiles were modified during the build"
+                        .to_string(),
+                );
+            }
+            if !violations.is_empty() {
+                notes_parts.push(format!(
+                    "proxy blocked connections outside the network allowlist: {}",
+                    violations.join(", ")
+                ));
+            }
             Enforcement {
                 mode_requested: mode.clone(),
                 mode_enforced: enforced,
                 network_blocked: false,
                 source_date_epoch_set: sde_set,
-                notes: if enforced {
+                network_violations: network_log.map(|_| violations),
+                untracked_inputs: None,
+                notes: if notes_parts.is_empty() {
                     None
                 } else {
-                    Some(
-                        "Mode B: lockfile integrity check failed — lockfiles were \
-                         modified during the build"
-                            .to_string(),
-                    )
+                    Some(format!("Mode B: {}", notes_parts.join("; ")))
                 },
             }
         }
@@ -329,9 +1299,23 @@ fn compute_enforcement(
             mode_enforced: true,
             network_blocked: false,
             source_date_epoch_set: sde_set,
+            network_violations: None,
+            untracked_inputs: None,
             notes: None,
         },
+    };
+    if let Some(untracked) = untracked_inputs.as_ref().filter(|u| !u.is_empty()) {
+        let gap = format!(
+            "build read files not tracked by git (provenance gap): {}",
+            untracked.join(", ")
+        );
+        enforcement.notes = Some(match enforcement.notes.take() {
+            Some(notes) => format!("{}; {}", notes, gap),
+            None => gap,
+        });
     }
+    enforcement.untracked_inputs = untracked_inputs;
+    enforcement
 }
 
 /// Snapshot the SHA-256 hashes of all detected lockfiles.
@@ -348,61 +1332,76 @@ fn snapshot_lockfile_hashes() -> Result<std::collections::BTreeMap<String, Strin
     Ok(hashes)
 }
 
-/// Attempt to run the build command inside a network-isolated namespace.
-///
-/// Uses `unshare -rn` to create a user+network namespace where only
-/// loopback is available. This blocks all network access during the build.
+/// Run the build command in the Mode A sandbox (network, mount and
+/// filesystem isolation; see sandbox.rs) and report which layers held.
 ///
-/// Falls back to an error if:
+/// Fails if not even network isolation is available:
 ///   - We're not on Linux
 ///   - User namespaces are disabled (requires kernel.unprivileged_userns_clone=1)
 ///   - unshare binary is not available
-fn run_build_network_isolated(build_cmd: &[String]) -> Result<String> {
-    if build_cmd.is_empty() {
-        anyhow::bail!("No build command specified");
-    }
-
-    // Test if unshare -rn is available by running a trivial command
-    let test = Command::new("unshare")
-        .args(["--user", "--net", "--", "true"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
-
-    match test {
-        Ok(status) if status.success() => {}
-        _ => anyhow::bail!("unshare -rn not available or user namespaces disabled"),
-    }
+fn run_build_sandboxed(
+    build_cmd: &[String],
+    output_dir: &Path,
+    clean_env: Option<&BTreeMap<String, String>>,
+    trace_options: TraceOptions,
+    transcript_path: &Path,
+) -> Result<((BuildResult, TraceReport), SandboxReport)> {
+    let source_dir = std::env::current_dir().context("resolving source directory")?;
+    let mut sandbox = sandbox::sandboxed_command(build_cmd, &source_dir, output_dir)?;
 
     eprintln!(
-        "[vbw] Mode A: running build with network isolation: unshare -rn -- {}",
+        "[vbw] Mode A: running build in sandbox: {}",
         build_cmd.join(" ")
     );
+    set_build_env(&mut sandbox.command, clean_env);
+    // Record the build, not the sandbox setup that runs before it
+    let trace_options = TraceOptions {
+        from_start_marker: true,
+        ..trace_options
+    };
+    let captured = run_captured(&mut sandbox.command, trace_options, transcript_path)?;
+    Ok((captured, sandbox.report()))
+}
 
-    // Build the command: unshare --user --net -- <build_cmd...>
-    let mut args: Vec<String> = vec![
-        "--user".to_string(),
-        "--net".to_string(),
-        "--".to_string(),
-    ];
-    args.extend_from_slice(build_cmd);
-
-    let child = Command::new("unshare")
-        .args(&args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .with_context(|| "spawning unshare for network-isolated build")?;
-
-    let (transcript, status) = capture_transcript(child)?;
-    if !status.success() {
+/// The parts of the previous bundle's manifest a chained build links to.
+struct PreviousLink {
+    manifest_hash: String,
+    sequence: u64,
+    project: String,
+}
+
+/// Read the previous bundle's manifest and compute its canonical hash.
+/// Its signatures are not checked here; `vbw verify-chain` does that.
+fn load_previous_link(bundle_dir: &Path) -> Result<PreviousLink> {
+    let manifest_path = bundle_dir.join("manifest.json");
+    let manifest_json = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("reading previous bundle {}", manifest_path.display()))?;
+    let manifest: Manifest = serde_json::from_str(&manifest_json)
+        .with_context(|| format!("parsing {}", manifest_path.display()))?;
+    let manifest_hash = hash::sha256_hex(&canonical::canonical_manifest_bytes(&manifest));
+
+    // A stale or edited manifest.json would link to a bundle that never existed
+    let stored_path = bundle_dir.join("hashes/manifest.sha256");
+    let stored = fs::read_to_string(&stored_path)
+        .with_context(|| format!("reading {}", stored_path.display()))?;
+    if stored.trim() != manifest_hash {
         anyhow::bail!(
-            "Build command failed with exit code: {}",
-            status.code().unwrap_or(-1)
+            "previous bundle {} is inconsistent: manifest hash {} does not match hashes/manifest.sha256",
+            bundle_dir.display(),
+            manifest_hash
         );
     }
 
-    Ok(transcript)
+    let sequence = manifest.sequence.unwrap_or(0);
+    eprintln!(
+        "[vbw] Chaining to previous bundle: sequence {}, manifest {}",
+        sequence, manifest_hash
+    );
+    Ok(PreviousLink {
+        manifest_hash,
+        sequence,
+        project: manifest.project.name,
+    })
 }
 
 fn load_or_create_policy(path: &Path) -> Result<Policy> {
@@ -426,36 +1425,20 @@ fn load_or_create_policy(path: &Path) -> Result<Policy> {
 /// Capture the current build environment.
 ///
 /// NOTE: This implementation targets Unix/Linux and CI runners (GitHub Actions,
-/// Docker). OS detection uses `uname` and tool paths use `which`. On non-Unix
-/// systems the OS fields will fall back to "unknown".
-fn capture_environment(policy: &Policy) -> Result<Environment> {
+/// Docker). OS detection uses `uname`; on non-Unix systems the OS fields will
+/// fall back to "unknown". Tools are detected by the registry in tools.rs.
+fn capture_environment(
+    policy: &Policy,
+    clean_env: Option<&BTreeMap<String, String>>,
+    container_image: Option<&Path>,
+) -> Result<Environment> {
     let os_name = get_cmd_output("uname", &["-s"]).unwrap_or_else(|_| "unknown".into());
     let os_version = get_cmd_output("uname", &["-r"]).ok();
     let kernel = get_cmd_output("uname", &["-v"]).ok();
     let arch = get_cmd_output("uname", &["-m"]).ok();
 
-    let mut tools: Vec<ToolInfo> = Vec::new();
-
-    // Detect common tools
-    for (name, flag) in &[
-        ("node", "--version"),
-        ("npm", "--version"),
-        ("cargo", "--version"),
-        ("rustc", "--version"),
-        ("gcc", "--version"),
-        ("python3", "--version"),
-        ("go", "version"),
-    ] {
-        if let Ok(version) = get_cmd_output(name, &[flag]) {
-            let version_line = version.lines().next().unwrap_or(&version).to_string();
-            tools.push(ToolInfo {
-                name: name.to_string(),
-                version: version_line,
-                path: which_cmd(name).ok(),
-                invocation: None,
-            });
-        }
-    }
+    let mut tools = tools::detect_tools(policy.requirements.toolchain.as_ref(), clean_env)?;
+    eprintln!("[vbw] Detected {} tool(s)", tools.len());
 
     // At least one tool required
     if tools.is_empty() {
@@ -463,14 +1446,39 @@ fn capture_environment(policy: &Policy) -> Result<Environment> {
             name: "sh".to_string(),
             version: get_cmd_output("sh", &["--version"]).unwrap_or_else(|_| "unknown".into()),
             path: Some("/bin/sh".to_string()),
+            sha256: hash::hash_file(Path::new("/bin/sh")).ok(),
             invocation: None,
+            details: None,
         });
     }
 
-    let container = detect_container();
+    let container = container::detect_container(container_image)?;
+    let ci = ci::detect_ci(envvars::process_vars());
+    if let Some(ci) = &ci {
+        eprintln!(
+            "[vbw] CI: {} run {}",
+            ci.provider,
+            ci.run_id.as_deref().unwrap_or("unknown")
+        );
+    }
     let mode = policy.requirements.reproducibility.mode.clone();
     let network_allowed = policy.requirements.network.allowed;
     let allowlist = policy.requirements.network.allowlist.clone();
+    let env_vars = envvars::capture(
+        policy.requirements.environment.as_ref(),
+        envvars::process_vars(),
+    );
+    if let Some(vars) = &env_vars {
+        let hidden = vars
+            .values()
+            .filter(|v| *v == envvars::REDACTED || v.starts_with("sha256:"))
+            .count();
+        eprintln!(
+            "[vbw] Captured {} environment variable(s), {} secret value(s) hidden",
+            vars.len(),
+            hidden
+        );
+    }
 
     Ok(Environment {
         os: OsInfo {
@@ -480,8 +1488,15 @@ fn capture_environment(policy: &Policy) -> Result<Environment> {
             arch,
         },
         container,
+        ci,
         tools,
-        env: None,
+        env: env_vars,
+        build_env: clean_env.map(|env| {
+            envvars::hide_secrets(
+                policy.requirements.environment.as_ref().unwrap_or(&Default::default()),
+                env.clone(),
+            )
+        }),
         locale: std::env::var("LANG").ok(),
         timezone: std::env::var("TZ").ok(),
         reproducibility: Reproducibility {
@@ -497,45 +1512,6 @@ fn capture_environment(policy: &Policy) -> Result<Environment> {
     })
 }
 
-/// Detect if we're running inside a container or CI environment.
-///
-/// This is heuristic-based, not authoritative. Returns None for bare-metal.
-/// GitHub Actions is reported with type "none" because it's a VM, not a container,
-/// but we include it so the environment record reflects CI context.
-fn detect_container() -> Option<ContainerInfo> {
-    // Check for /.dockerenv (standard Docker marker file)
-    if Path::new("/.dockerenv").exists() {
-        return Some(ContainerInfo {
-            container_type: "docker".to_string(),
-            image: std::env::var("CONTAINER_IMAGE").ok(),
-            image_digest: std::env::var("CONTAINER_IMAGE_DIGEST")
-                .unwrap_or_else(|_| "unknown".to_string()),
-        });
-    }
-
-    // Check /proc/self/cgroup for container indicators
-    if let Ok(cgroup) = fs::read_to_string("/proc/self/cgroup") {
-        if cgroup.contains("docker") || cgroup.contains("containerd") {
-            return Some(ContainerInfo {
-                container_type: "docker".to_string(),
-                image: None,
-                image_digest: "unknown".to_string(),
-            });
-        }
-    }
-
-    // Check for GitHub Actions container
-    if std::env::var("GITHUB_ACTIONS").is_ok() {
-        return Some(ContainerInfo {
-            container_type: "none".to_string(),
-            image: Some("github-actions-runner".to_string()),
-            image_digest: std::env::var("ImageOS").unwrap_or_else(|_| "unknown".to_string()),
-        });
-    }
-
-    None
-}
-
 fn detect_materials() -> Result<MaterialsLock> {
     let mut lockfiles = Vec::new();
     let mut materials = Vec::new();
@@ -553,6 +1529,8 @@ fn detect_materials() -> Result<MaterialsLock> {
                 kind: lockfile_kind(name).to_string(),
                 source: None,
                 sha256: file_hash,
+                upstream_manifest_hash: None,
+                upstream_path: None,
                 archive_sha256: None,
                 extracted_tree_hash: None,
             });
@@ -581,112 +1559,130 @@ fn lockfile_kind(name: &str) -> &str {
     }
 }
 
-/// Maximum transcript size in bytes (128 MiB). Prevents unbounded memory
-/// consumption if a build command produces excessive output (DoS protection).
-const MAX_TRANSCRIPT_BYTES: usize = 128 * 1024 * 1024;
-
-/// Maximum length of a single line captured in the transcript (64 KiB).
-/// Prevents a single extremely long line from consuming excessive memory.
-const MAX_LINE_LENGTH: usize = 64 * 1024;
-
-/// Read lines from a pipe, truncate overlong lines, tag with stream name
-/// and timestamp, echo to stderr, and send to the channel.
-fn pipe_lines<R: std::io::Read>(reader: BufReader<R>, stream: &str, tx: mpsc::Sender<String>) {
-    for line in reader.lines().map_while(Result::ok) {
-        let line = if line.len() > MAX_LINE_LENGTH {
-            format!(
-                "{}... [truncated, {} bytes total]",
-                &line[..MAX_LINE_LENGTH],
-                line.len()
-            )
-        } else {
-            line
+/// Run `command` to completion with its output captured into the
+/// transcript file, under the syscall tracer if `trace_options` asks for
+/// it. The result records how it ended; a failed command is not an error
+/// here.
+fn run_captured(
+    command: &mut Command,
+    trace_options: TraceOptions,
+    transcript_path: &Path,
+) -> Result<(BuildResult, TraceReport)> {
+    let out = fs::File::create(transcript_path)
+        .with_context(|| format!("creating {}", transcript_path.display()))?;
+    let out = BufWriter::new(out);
+    let capture = move |stdout: ChildStdout, stderr: ChildStderr| {
+        transcript::capture(stdout, stderr, out, MAX_TRANSCRIPT_BYTES)
+    };
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let started = Instant::now();
+    let (captured, status, report) = if trace_options.any() {
+        trace::run_traced(command, trace_options, capture)?
+    } else {
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("spawning {}", command.get_program().to_string_lossy()))?;
+        let stdout = child.stdout.take().expect("stdout was piped");
+        let stderr = child.stderr.take().expect("stderr was piped");
+        let captured = capture(stdout, stderr);
+        let (status, resources) = wait_with_usage(&mut child)?;
+        let report = TraceReport {
+            resources,
+            ..Default::default()
         };
-        let ts = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
-        let tagged = format!("[{}] [{}] {}", ts, stream, line);
-        eprintln!("{}", line);
-        let _ = tx.send(tagged);
-    }
+        (captured, status, report)
+    };
+    let result = build_result(status, started.elapsed(), report.resources);
+    captured?;
+    Ok((result, report))
 }
 
-/// Capture interleaved stdout/stderr from a spawned child process into a
-/// timestamped transcript string.
-///
-/// Each line is tagged with a stream identifier and ISO-8601 timestamp:
-///   [2026-01-01T00:00:00.123Z] [stdout] line contents here
-///   [2026-01-01T00:00:00.456Z] [stderr] warning: something
-///
-/// Lines from both streams are collected via a channel and written in
-/// arrival order, which approximates true interleaving.
-///
-/// The transcript is capped at MAX_TRANSCRIPT_BYTES to prevent memory
-/// exhaustion from pathological build output.
-fn capture_transcript(mut child: std::process::Child) -> Result<(String, std::process::ExitStatus)> {
-    let (tx, rx) = mpsc::channel::<String>();
-
-    let stdout = child.stdout.take().expect("stdout was piped");
-    let tx_out = tx.clone();
-    let stdout_thread = thread::spawn(move || {
-        pipe_lines(BufReader::new(stdout), "stdout", tx_out);
-    });
-
-    let stderr = child.stderr.take().expect("stderr was piped");
-    let tx_err = tx;
-    let stderr_thread = thread::spawn(move || {
-        pipe_lines(BufReader::new(stderr), "stderr", tx_err);
-    });
-
-    // We must join threads (dropping transmitters) before collecting.
-    stdout_thread.join().expect("stdout reader thread panicked");
-    stderr_thread.join().expect("stderr reader thread panicked");
-
-    let mut transcript = String::new();
-    let mut truncated = false;
-    for line in rx.iter() {
-        if transcript.len() + line.len() + 1 > MAX_TRANSCRIPT_BYTES {
-            truncated = true;
+/// Wait for `child`, with its resource usage where wait4() exists.
+#[cfg(unix)]
+fn wait_with_usage(
+    child: &mut std::process::Child,
+) -> Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+    let pid = child.id() as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: rusage is plain data; wait4 fills it in
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } == pid {
             break;
         }
-        transcript.push_str(&line);
-        transcript.push('\n');
-    }
-    if truncated {
-        let msg = format!(
-            "\n[vbw] TRANSCRIPT TRUNCATED at {} bytes (limit: {} bytes)\n",
-            transcript.len(),
-            MAX_TRANSCRIPT_BYTES
-        );
-        transcript.push_str(&msg);
-        eprintln!("{}", msg.trim());
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::EINTR) {
+            return Err(error).context("waiting for build command");
+        }
     }
+    Ok((
+        std::process::ExitStatus::from_raw(status),
+        Some(ResourceUsage::from_rusage(&usage)),
+    ))
+}
 
+#[cfg(not(unix))]
+fn wait_with_usage(
+    child: &mut std::process::Child,
+) -> Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
     let status = child.wait().context("waiting for build command")?;
-    Ok((transcript, status))
+    Ok((status, None))
+}
+
+/// The manifest's record of how the build command ended.
+fn build_result(
+    status: std::process::ExitStatus,
+    wall_time: Duration,
+    resources: Option<ResourceUsage>,
+) -> BuildResult {
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal = None;
+    BuildResult {
+        success: status.success(),
+        exit_code: status.code(),
+        signal,
+        wall_time_ms: wall_time.as_millis() as u64,
+        user_cpu_ms: resources.map(|r| r.user_cpu.as_millis() as u64),
+        system_cpu_ms: resources.map(|r| r.system_cpu.as_millis() as u64),
+        max_rss_kib: resources.map(|r| r.max_rss_kib),
+    }
+}
+
+/// Give the build command its environment: only `clean_env` if the policy
+/// asks for a clean environment, else vbw's own. Either way without the
+/// signing secrets (sign::SECRET_ENV_VARS).
+fn set_build_env(command: &mut Command, clean_env: Option<&BTreeMap<String, String>>) {
+    if let Some(env) = clean_env {
+        command.env_clear().envs(env);
+    }
+    for name in sign::SECRET_ENV_VARS {
+        command.env_remove(name);
+    }
 }
 
 /// Run the user's build command, capturing interleaved stdout and stderr
-/// with timestamps for forensic value.
-fn run_build_command(cmd: &[String]) -> Result<String> {
+/// with timestamps for forensic value. `env` is added to the build
+/// environment (e.g. the Mode B proxy variables).
+fn run_build_command(
+    cmd: &[String],
+    clean_env: Option<&BTreeMap<String, String>>,
+    env: &[(&str, String)],
+    trace_options: TraceOptions,
+    transcript_path: &Path,
+) -> Result<(BuildResult, TraceReport)> {
     if cmd.is_empty() {
         anyhow::bail!("No build command specified");
     }
 
-    let child = Command::new(&cmd[0])
+    let mut command = Command::new(&cmd[0]);
+    set_build_env(&mut command, clean_env);
+    command
         .args(&cmd[1..])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .with_context(|| format!("spawning build command: {}", cmd[0]))?;
-
-    let (transcript, status) = capture_transcript(child)?;
-    if !status.success() {
-        anyhow::bail!(
-            "Build command failed with exit code: {}",
-            status.code().unwrap_or(-1)
-        );
-    }
-
-    Ok(transcript)
+        .envs(env.iter().map(|(k, v)| (*k, v)));
+    run_captured(&mut command, trace_options, transcript_path)
 }
 
 fn collect_outputs(dist_dir: &Path) -> Result<Outputs> {
@@ -776,6 +1772,299 @@ fn get_cmd_output(cmd: &str, args: &[&str]) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-fn which_cmd(name: &str) -> Result<String> {
-    get_cmd_output("which", &[name])
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_command_never_sees_signing_secrets() {
+        let secrets: BTreeMap<String, String> = sign::SECRET_ENV_VARS
+            .iter()
+            .map(|name| (name.to_string(), "hunter2".to_string()))
+            .collect();
+        let mut clean: BTreeMap<String, String> = secrets.clone();
+        clean.insert("PATH".to_string(), "/usr/bin:/bin".to_string());
+        // Inherited from vbw's environment, and in a clean environment
+        for clean_env in [None, Some(&clean)] {
+            let mut command = Command::new("/bin/sh");
+            command.args(["-c", "env"]).envs(&secrets);
+            set_build_env(&mut command, clean_env);
+            let output = command.output().unwrap();
+            let env = String::from_utf8(output.stdout).unwrap();
+            for name in sign::SECRET_ENV_VARS {
+                assert!(!env.contains(name), "{} reached the build: {}", name, env);
+            }
+        }
+    }
+
+    /// run_build works in the current directory, which is process-wide:
+    /// tests that run a build hold this lock.
+    static CWD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// A git repository with one commit, to build in.
+    fn git_project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "-q"][..],
+            &["commit", "-q", "--allow-empty", "-m", "initial"],
+        ] {
+            let status = Command::new("git")
+                .args(["-c", "user.name=vbw", "-c", "user.email=vbw@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        // Skip toolchain detection, which runs every tool's --version
+        let mut policy = Policy::default_policy();
+        policy.requirements.toolchain = Some(ToolchainRequirement {
+            detectors: Some(vec![]),
+            custom: None,
+        });
+        let policy = serde_json::to_string_pretty(&policy).unwrap();
+        fs::write(dir.path().join("policy.json"), policy).unwrap();
+        dir
+    }
+
+    /// Build `sh -c <script>` in `dir` under its policy.json, signed with
+    /// an ephemeral key.
+    fn build_in(dir: &Path, script: &str, options: BuildOptions) -> Result<()> {
+        let _cwd = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = std::env::current_dir().unwrap();
+        std::env::set_current_dir(dir).unwrap();
+        let build_cmd = ["sh", "-c", script].map(String::from);
+        let result = run_build(&BuildOptions {
+            build_cmd: &build_cmd,
+            policy_path: Some("policy.json"),
+            ephemeral_key: true,
+            ..options
+        });
+        std::env::set_current_dir(previous).unwrap();
+        result
+    }
+
+    /// Leftover staging directories (.vbw.tmp-*) in `dir`.
+    fn staging_dirs(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".vbw.tmp-"))
+            .collect()
+    }
+
+    const BUILD: &str = "mkdir -p dist && echo artifact > dist/out";
+
+    #[test]
+    fn existing_bundle_is_replaced_only_with_force() {
+        let project = git_project();
+        let dir = project.path();
+        build_in(dir, BUILD, BuildOptions::default()).unwrap();
+        let manifest = fs::read(dir.join("vbw/manifest.json")).unwrap();
+        // Signatures added to the bundle after it was built
+        let cosig = dir.join("vbw/signatures/maintainer.release.cosig.json");
+        let token = dir.join("vbw/signatures/builder.ed25519.tst");
+        fs::write(&cosig, "{}").unwrap();
+        fs::write(&token, "token").unwrap();
+
+        let err = build_in(dir, BUILD, BuildOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("--force"), "{:#}", err);
+        assert_eq!(fs::read(dir.join("vbw/manifest.json")).unwrap(), manifest);
+        assert!(cosig.exists() && token.exists());
+
+        let force = BuildOptions {
+            force: true,
+            ..Default::default()
+        };
+        build_in(dir, BUILD, force).unwrap();
+        assert_ne!(fs::read(dir.join("vbw/manifest.json")).unwrap(), manifest);
+        // Nothing from the old bundle survives: these no longer match it
+        assert!(!cosig.exists());
+        assert!(!token.exists());
+        assert!(staging_dirs(dir).is_empty());
+    }
+
+    #[test]
+    fn verify_under_verified_oidc_policy_needs_jwks() {
+        let project = git_project();
+        let dir = project.path();
+        let policy_path = dir.join("policy.json");
+        let mut policy: Policy =
+            serde_json::from_str(&fs::read_to_string(&policy_path).unwrap()).unwrap();
+        policy.requirements.signing.as_mut().unwrap().require_verified_oidc = Some(true);
+        fs::write(&policy_path, serde_json::to_string_pretty(&policy).unwrap()).unwrap();
+
+        let verify = BuildOptions {
+            verify: true,
+            ..Default::default()
+        };
+        let err = build_in(dir, BUILD, verify).unwrap_err();
+        assert!(err.to_string().contains("--oidc-jwks"), "{:#}", err);
+        assert!(!dir.join("dist").exists(), "the build ran");
+    }
+
+    #[test]
+    fn oidc_is_rejected_for_steps() {
+        let project = git_project();
+        let options = BuildOptions {
+            step: Some("compile"),
+            oidc: Some("github"),
+            ..Default::default()
+        };
+        let err = build_in(project.path(), BUILD, options).unwrap_err();
+        assert!(err.to_string().contains("--step"), "{:#}", err);
+        assert!(!project.path().join("dist").exists(), "the build ran");
+    }
+
+    #[test]
+    fn transcript_is_written_outside_the_project() {
+        let project = git_project();
+        let dir = project.path();
+        build_in(
+            dir,
+            "mkdir -p dist && ls -a > dist/listing && echo output",
+            BuildOptions::default(),
+        )
+        .unwrap();
+        let listing = fs::read_to_string(dir.join("dist/listing")).unwrap();
+        assert!(!listing.contains("transcript"), "{}", listing);
+        assert!(fs::read_to_string(dir.join("vbw/transcript.jsonl"))
+            .unwrap()
+            .contains("output"));
+    }
+
+    #[test]
+    fn staged_dir_replaces_target_wholesale_on_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("vbw");
+        fs::create_dir_all(target.join("signatures")).unwrap();
+        fs::write(target.join("manifest.json"), "old").unwrap();
+        fs::write(target.join("signatures/a.cosig.json"), "{}").unwrap();
+
+        let staged = StagedDir::new(&target).unwrap();
+        fs::write(staged.path().join("manifest.json"), "new").unwrap();
+        staged.commit().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("manifest.json")).unwrap(),
+            "new"
+        );
+        assert!(!target.join("signatures").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn staged_dir_dropped_before_commit_leaves_target_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("vbw");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("manifest.json"), "old").unwrap();
+
+        let staged = StagedDir::new(&target).unwrap();
+        fs::write(staged.path().join("manifest.json"), "half-written").unwrap();
+        // A build that fails before commit drops its staging directory
+        drop(staged);
+
+        assert_eq!(
+            fs::read_to_string(target.join("manifest.json")).unwrap(),
+            "old"
+        );
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 1);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // With no bundle yet, none appears
+        let fresh = dir.path().join("fresh");
+        let staged = StagedDir::new(&fresh).unwrap();
+        fs::write(staged.path().join("manifest.json"), "half-written").unwrap();
+        drop(staged);
+        assert!(!fresh.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failed_self_verify_fails_build_and_keeps_previous_bundle() {
+        let project = git_project();
+        let dir = project.path();
+        build_in(dir, BUILD, BuildOptions::default()).unwrap();
+        let manifest = fs::read(dir.join("vbw/manifest.json")).unwrap();
+
+        // An output that resolves outside the project: the build hashes
+        // it, verify rejects it
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret"), "outside").unwrap();
+        let script = format!(
+            "{} && ln -s {}/secret dist/leak",
+            BUILD,
+            outside.path().display()
+        );
+        let options = BuildOptions {
+            verify: true,
+            force: true,
+            ..Default::default()
+        };
+        let err = build_in(dir, &script, options).unwrap_err();
+        assert!(err.to_string().contains("self-verification"), "{:#}", err);
+        assert_eq!(fs::read(dir.join("vbw/manifest.json")).unwrap(), manifest);
+        assert!(staging_dirs(dir).is_empty());
+    }
+
+    #[test]
+    fn outputs_changed_after_hashing_fail_the_build() {
+        let project = git_project();
+        let dir = project.path();
+        // A straggler rewrites the output once the bundle is being staged
+        let script = format!(
+            "{} && (for i in $(seq 100); do \
+                 ls .vbw.tmp-*/manifest.json && break; sleep 0.05; done; \
+                 echo rewritten > dist/out) >/dev/null 2>&1 &",
+            BUILD
+        );
+        let options = BuildOptions {
+            rehash_outputs_after: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let err = build_in(dir, &script, options).unwrap_err();
+        assert!(err.to_string().contains("dist/out (changed)"), "{:#}", err);
+        assert!(!dir.join("vbw/manifest.json").exists());
+        assert!(staging_dirs(dir).is_empty());
+
+        // Settled outputs pass
+        build_in(dir, BUILD, options).unwrap();
+        assert!(dir.join("vbw/manifest.json").exists());
+    }
+
+    #[test]
+    fn output_changes_lists_changed_new_and_removed() {
+        let artifact = |path: &str, sha256: &str| Artifact {
+            path: path.to_string(),
+            sha256: sha256.to_string(),
+            size_bytes: 0,
+            mime: None,
+            build_id: None,
+            notes: None,
+        };
+        let recorded = Outputs {
+            artifacts: vec![
+                artifact("dist/same", "aa"),
+                artifact("dist/changed", "bb"),
+                artifact("dist/removed", "cc"),
+            ],
+        };
+        let current = Outputs {
+            artifacts: vec![
+                artifact("dist/same", "aa"),
+                artifact("dist/changed", "b2"),
+                artifact("dist/new", "dd"),
+            ],
+        };
+        assert_eq!(
+            output_changes(&recorded, &current),
+            vec![
+                "dist/changed (changed)",
+                "dist/new (new)",
+                "dist/removed (removed)"
+            ]
+        );
+        assert!(output_changes(&recorded, &recorded).is_empty());
+    }
 }
@@ -12,6 +12,8 @@
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::vbw::model::Cosignature;
+
 /// Serialize a manifest struct to canonical JSON bytes.
 ///
 /// The canonical form is: sorted object keys at every level, compact
@@ -22,6 +24,25 @@ pub fn canonical_manifest_bytes<T: Serialize>(manifest: &T) -> Vec<u8> {
     canonical_json(&value).into_bytes()
 }
 
+/// Compute the bytes a co-signature statement signs over.
+///
+/// The payload is the canonical manifest bytes, a single `\n` separator,
+/// then the canonical JSON of the statement with its `signature` field
+/// removed. Binding the metadata into the payload means the statement type,
+/// timestamp, and comment cannot be altered without invalidating the
+/// signature.
+pub fn cosignature_signing_bytes(manifest_bytes: &[u8], cosignature: &Cosignature) -> Vec<u8> {
+    let mut value =
+        serde_json::to_value(cosignature).expect("cosignature must be serializable to Value");
+    if let Value::Object(ref mut map) = value {
+        map.remove("signature");
+    }
+    let mut out = manifest_bytes.to_vec();
+    out.push(b'\n');
+    out.extend_from_slice(canonical_json(&value).as_bytes());
+    out
+}
+
 /// Produce canonical JSON from a serde_json::Value.
 ///
 /// - Objects: keys sorted lexicographically, no whitespace
@@ -105,13 +126,26 @@ mod tests {
             outputs_hash: "d".repeat(64),
             builder_identity: BuilderIdentity {
                 key_id: "test@golden".to_string(),
+                key_type: None,
                 public_key_ed25519: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string(),
+                public_key: None,
                 issuer: None,
+                oidc: None,
+                hybrid: None,
+                ssh_fingerprint: None,
             },
             policy_ref: PolicyRef {
                 path: "vbw/policy.json".to_string(),
                 hash_sha256: "e".repeat(64),
             },
+            sequence: None,
+            previous_manifest_hash: None,
+            steps: None,
+            network_log_hash: None,
+            network_access_hash: None,
+            inputs_hash: None,
+            transcript_hash: None,
+            build_result: None,
             notes: None,
             ext: None,
             enforcement: None,
@@ -229,6 +263,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cosignature_signing_bytes_bind_metadata() {
+        let m = test_manifest();
+        let canonical_bytes = canonical_manifest_bytes(&m);
+        let cosig = Cosignature {
+            cosignature_version: "1.0".to_string(),
+            key_id: "maintainer@org".to_string(),
+            key_type: None,
+            public_key_ed25519: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string(),
+            public_key: None,
+            signed_at: "2026-01-02T00:00:00Z".to_string(),
+            statement: "reviewed".to_string(),
+            comment: None,
+            manifest_hash: hash::sha256_hex(&canonical_bytes),
+            signature: "ignored".to_string(),
+        };
+        let payload = cosignature_signing_bytes(&canonical_bytes, &cosig);
+
+        // Manifest bytes come first, followed by the separator
+        assert!(payload.starts_with(&canonical_bytes));
+        assert_eq!(payload[canonical_bytes.len()], b'\n');
+
+        // The signature field itself is never part of the payload
+        let mut resigned = cosig.clone();
+        resigned.signature = "different".to_string();
+        assert_eq!(payload, cosignature_signing_bytes(&canonical_bytes, &resigned));
+
+        // Any metadata change alters the payload
+        let mut upgraded = cosig.clone();
+        upgraded.statement = "approved-for-release".to_string();
+        assert_ne!(payload, cosignature_signing_bytes(&canonical_bytes, &upgraded));
+    }
+
     #[test]
     fn sign_and_verify_canonical_bytes() {
         let m = test_manifest();
@@ -0,0 +1,410 @@
+// chain.rs — Release chain verification (`vbw verify-chain`)
+//
+// A chained build records the previous bundle's canonical manifest hash
+// and sequence number in its own (signed) manifest. Given a release
+// history — a list of bundle directories in any order — verify-chain
+// checks each manifest's integrity and builder signature, then walks the
+// links and reports:
+//
+//   - Gaps:         a sequence number is missing (a release was skipped or
+//                   withheld from the history)
+//   - Broken links: a bundle's previous_manifest_hash does not match the
+//                   bundle that holds the preceding sequence number (a
+//                   release was replaced), or the sequence does not follow
+//                   on from its predecessor
+//   - Forks:        two bundles claim the same predecessor, or the history
+//                   has more than one root
+//
+// Only manifests and builder signatures are checked. Output artifacts of
+// past releases are usually not on disk; use `vbw verify` on a single
+// bundle for the full check.
+//
+// The earliest bundle supplied may link to a predecessor that is not in
+// the list (a truncated history); that is a warning, not an error.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::hash;
+use crate::vbw::canonical;
+use crate::vbw::model::*;
+use crate::vbw::verify::{check_builder_signatures, emit_verdict, Verdict};
+
+/// One bundle of the history, after its manifest checked out.
+struct ChainEntry {
+    dir: PathBuf,
+    manifest: Manifest,
+    manifest_hash: String,
+}
+
+impl ChainEntry {
+    fn sequence(&self) -> u64 {
+        self.manifest.sequence.unwrap_or(0)
+    }
+
+    fn label(&self) -> String {
+        format!("#{} ({})", self.sequence(), self.dir.display())
+    }
+}
+
+/// Check that the chain fields in a manifest are well-formed. Used by both
+/// `verify` and `verify-chain`.
+pub fn check_link_fields(manifest: &Manifest, errors: &mut Vec<String>) {
+    match (&manifest.previous_manifest_hash, manifest.sequence) {
+        (None, None) => {}
+        (Some(previous), Some(sequence)) => {
+            if sequence == 0 {
+                errors.push("Chained manifest has sequence 0 (must be at least 1)".to_string());
+            }
+            if previous.len() != 64
+                || !previous
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+            {
+                errors.push(format!(
+                    "previous_manifest_hash '{}' is not a lowercase hex SHA-256",
+                    previous
+                ));
+            }
+        }
+        _ => errors.push(
+            "Manifest must set both previous_manifest_hash and sequence, or neither".to_string(),
+        ),
+    }
+}
+
+/// Verify a release history made of the bundles in `bundle_dirs`.
+pub fn run_verify_chain(bundle_dirs: &[PathBuf]) -> Result<Verdict> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+
+    // 1. Load each manifest and check its hash and builder signature(s)
+    let mut entries: Vec<ChainEntry> = Vec::new();
+    for dir in bundle_dirs {
+        match load_entry(dir, &mut errors) {
+            Ok(Some(entry)) => {
+                if let Some(dup) = entries.iter().find(|e| e.manifest_hash == entry.manifest_hash) {
+                    warnings.push(format!(
+                        "{} and {} are the same bundle (listed twice)",
+                        dup.dir.display(),
+                        entry.dir.display()
+                    ));
+                } else {
+                    entries.push(entry);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => errors.push(format!("{}: {:#}", dir.display(), e)),
+        }
+    }
+    if entries.is_empty() {
+        errors.push("No valid bundles to check".to_string());
+        return emit_verdict(errors, warnings);
+    }
+    entries.sort_by(|a, b| {
+        a.sequence()
+            .cmp(&b.sequence())
+            .then_with(|| a.manifest.created_at.cmp(&b.manifest.created_at))
+    });
+
+    // 2. Every bundle must belong to the same project
+    let project = &entries[0].manifest.project.name;
+    for entry in &entries[1..] {
+        if entry.manifest.project.name != *project {
+            errors.push(format!(
+                "{} is for project '{}', not '{}'",
+                entry.label(),
+                entry.manifest.project.name,
+                project
+            ));
+        }
+    }
+
+    // 3. Walk the links
+    let by_hash: BTreeMap<&str, &ChainEntry> = entries
+        .iter()
+        .map(|e| (e.manifest_hash.as_str(), e))
+        .collect();
+    let first_sequence = entries[0].sequence();
+    let mut children: BTreeMap<&str, Vec<&ChainEntry>> = BTreeMap::new();
+    let mut roots: Vec<&ChainEntry> = Vec::new();
+
+    for entry in &entries {
+        let Some(previous) = entry.manifest.previous_manifest_hash.as_deref() else {
+            roots.push(entry);
+            continue;
+        };
+        children.entry(previous).or_default().push(entry);
+        let sequence = entry.sequence();
+
+        if let Some(parent) = by_hash.get(previous) {
+            if parent.sequence() + 1 != sequence {
+                errors.push(format!(
+                    "Broken link: {} follows {} but is numbered {} (expected {})",
+                    entry.label(),
+                    parent.label(),
+                    sequence,
+                    parent.sequence() + 1
+                ));
+            }
+            continue;
+        }
+
+        let at_previous_sequence: Vec<&ChainEntry> = entries
+            .iter()
+            .filter(|e| e.sequence() + 1 == sequence)
+            .collect();
+        if !at_previous_sequence.is_empty() {
+            for other in at_previous_sequence {
+                errors.push(format!(
+                    "Broken link: {} expects previous manifest {}, but {} has manifest {} (replaced release?)",
+                    entry.label(),
+                    previous,
+                    other.label(),
+                    other.manifest_hash
+                ));
+            }
+        } else if sequence == first_sequence {
+            warnings.push(format!(
+                "History starts at {}; its predecessor (manifest {}) was not supplied",
+                entry.label(),
+                previous
+            ));
+        } else {
+            let before = entries
+                .iter()
+                .map(|e| e.sequence())
+                .filter(|s| *s < sequence)
+                .max()
+                .unwrap_or(0);
+            errors.push(format!(
+                "Gap: no bundle with sequence {} before {} (missing after #{})",
+                describe_range(before + 1, sequence - 1),
+                entry.label(),
+                before
+            ));
+        }
+    }
+
+    // 4. Forks: more than one successor for a release, or more than one root
+    for (previous, successors) in &children {
+        if successors.len() > 1 {
+            let labels: Vec<String> = successors.iter().map(|e| e.label()).collect();
+            errors.push(format!(
+                "Fork: {} all follow manifest {}",
+                labels.join(", "),
+                previous
+            ));
+        }
+    }
+    if roots.len() > 1 {
+        let labels: Vec<String> = roots.iter().map(|e| e.dir.display().to_string()).collect();
+        errors.push(format!(
+            "Fork: {} unchained bundles (each starts its own history): {}",
+            roots.len(),
+            labels.join(", ")
+        ));
+    }
+
+    // 5. Builder key changes are legitimate (rotation) but worth a look
+    for pair in entries.windows(2) {
+        let (a, b) = (&pair[0].manifest.builder_identity, &pair[1].manifest.builder_identity);
+        if a.algorithm() != b.algorithm() || a.public_key_b64() != b.public_key_b64() {
+            warnings.push(format!(
+                "Builder key changed at {} ('{}' -> '{}')",
+                pair[1].label(),
+                a.key_id,
+                b.key_id
+            ));
+        }
+    }
+
+    for entry in &entries {
+        eprintln!(
+            "[vbw] #{:<4} {}  {}  {}",
+            entry.sequence(),
+            &entry.manifest_hash[..16],
+            entry.manifest.created_at,
+            entry.dir.display()
+        );
+    }
+    eprintln!("[vbw] Chain: {} bundle(s) checked", entries.len());
+
+    emit_verdict(errors, warnings)
+}
+
+/// Load a bundle's manifest and check its hash and builder signatures.
+/// Problems are recorded in `errors` and yield `None`.
+fn load_entry(dir: &Path, errors: &mut Vec<String>) -> Result<Option<ChainEntry>> {
+    let manifest_path = dir.join("manifest.json");
+    let manifest_json = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("reading {}", manifest_path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_str(&manifest_json).context("parsing manifest.json")?;
+    let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+    let manifest_hash = hash::sha256_hex(&canonical_bytes);
+
+    let errors_before = errors.len();
+    let stored_path = dir.join("hashes/manifest.sha256");
+    let stored = fs::read_to_string(&stored_path)
+        .with_context(|| format!("reading {}", stored_path.display()))?;
+    if stored.trim() != manifest_hash {
+        errors.push(format!(
+            "{}: manifest hash mismatch (stored={}, computed={})",
+            dir.display(),
+            stored.trim(),
+            manifest_hash
+        ));
+    }
+
+    check_builder_signatures(
+        &dir.join("signatures"),
+        &manifest.builder_identity,
+        &canonical_bytes,
+        &dir.display().to_string(),
+        errors,
+    );
+
+    let mut link_errors = Vec::new();
+    check_link_fields(&manifest, &mut link_errors);
+    errors.extend(
+        link_errors
+            .into_iter()
+            .map(|e| format!("{}: {}", dir.display(), e)),
+    );
+
+    if errors.len() > errors_before {
+        return Ok(None);
+    }
+    Ok(Some(ChainEntry {
+        dir: dir.to_path_buf(),
+        manifest,
+        manifest_hash,
+    }))
+}
+
+fn describe_range(from: u64, to: u64) -> String {
+    if from == to {
+        from.to_string()
+    } else {
+        format!("{}..{}", from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign;
+
+    /// Write a minimal signed bundle (manifest, hash, signature) and return
+    /// its manifest hash.
+    fn write_bundle(dir: &Path, sk: &str, pk: &str, link: Option<(u64, &str)>) -> String {
+        let mut manifest: Manifest = serde_json::from_value(serde_json::json!({
+            "vbw_version": "1.0",
+            "build_id": uuid::Uuid::new_v4().to_string(),
+            "created_at": "2026-01-01T00:00:00Z",
+            "project": { "name": "demo" },
+            "git": { "commit": "a".repeat(40), "dirty": false },
+            "source_commit_tree_hash": "b".repeat(64),
+            "materials_lock_hash": "c".repeat(64),
+            "environment_hash": "d".repeat(64),
+            "outputs_hash": "e".repeat(64),
+            "builder_identity": { "key_id": "ci", "public_key_ed25519": pk },
+            "policy_ref": { "path": "vbw/policy.json", "hash_sha256": "f".repeat(64) }
+        }))
+        .unwrap();
+        manifest.sequence = link.map(|(s, _)| s);
+        manifest.previous_manifest_hash = link.map(|(_, p)| p.to_string());
+
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        let manifest_hash = hash::sha256_hex(&canonical_bytes);
+        fs::create_dir_all(dir.join("signatures")).unwrap();
+        fs::create_dir_all(dir.join("hashes")).unwrap();
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("signatures/builder.ed25519.sig"),
+            sign::sign(sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("hashes/manifest.sha256"), &manifest_hash).unwrap();
+        manifest_hash
+    }
+
+    fn expect_error(verdict: Verdict, needle: &str) {
+        match verdict {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains(needle)),
+                "Expected error containing '{}', got: {:?}",
+                needle,
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn linear_chain_verifies_in_any_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (sk, pk) = sign::keygen();
+        let dirs: Vec<PathBuf> = (0..3).map(|i| tmp.path().join(format!("r{}", i))).collect();
+        let h0 = write_bundle(&dirs[0], &sk, &pk, None);
+        let h1 = write_bundle(&dirs[1], &sk, &pk, Some((1, &h0)));
+        write_bundle(&dirs[2], &sk, &pk, Some((2, &h1)));
+
+        let shuffled = vec![dirs[2].clone(), dirs[0].clone(), dirs[1].clone()];
+        assert_eq!(run_verify_chain(&shuffled).unwrap(), Verdict::Verified);
+
+        // Starting mid-history is fine, with a warning
+        match run_verify_chain(&dirs[1..]).unwrap() {
+            Verdict::VerifiedWithVariance(w) => assert!(w[0].contains("History starts")),
+            v => panic!("Expected variance, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn reports_gaps_broken_links_and_forks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (sk, pk) = sign::keygen();
+        let dir = |name: &str| tmp.path().join(name);
+        let h0 = write_bundle(&dir("r0"), &sk, &pk, None);
+        let h1 = write_bundle(&dir("r1"), &sk, &pk, Some((1, &h0)));
+        let h2 = write_bundle(&dir("r2"), &sk, &pk, Some((2, &h1)));
+        write_bundle(&dir("r3"), &sk, &pk, Some((3, &h2)));
+
+        // r2 withheld
+        expect_error(
+            run_verify_chain(&[dir("r0"), dir("r1"), dir("r3")]).unwrap(),
+            "Gap: no bundle with sequence 2",
+        );
+
+        // r1 replaced by a different release 1
+        write_bundle(&dir("r1-evil"), &sk, &pk, Some((1, &h0)));
+        expect_error(
+            run_verify_chain(&[dir("r0"), dir("r1-evil"), dir("r2")]).unwrap(),
+            "Broken link",
+        );
+
+        // Both releases 1 presented: a fork after r0
+        expect_error(
+            run_verify_chain(&[dir("r0"), dir("r1"), dir("r1-evil")]).unwrap(),
+            "Fork",
+        );
+
+        // Tampered manifest: the bundle is rejected outright
+        let path = dir("r3").join("manifest.json");
+        let tampered = fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"sequence\": 3", "\"sequence\": 2");
+        fs::write(&path, tampered).unwrap();
+        expect_error(
+            run_verify_chain(&[dir("r0"), dir("r1"), dir("r2"), dir("r3")]).unwrap(),
+            "manifest hash mismatch",
+        );
+    }
+}
@@ -0,0 +1,648 @@
+// container.rs — Container identity of the build (environment.json container)
+//
+// RUNTIME: detected from the strongest evidence available:
+//   - /run/.containerenv       podman's marker; also holds the container
+//                              id, image name and image id
+//   - /.dockerenv              docker's marker
+//   - /run/systemd/container   the runtime name written for systemd
+//                              (systemd-nspawn, podman, docker, lxc); the
+//                              `container` variable carries the same
+//   - /proc/self/cgroup        cgroup v1 and v2 paths: docker-<id>.scope,
+//                              libpod-<id>.scope, cri-containerd-<id>.scope,
+//                              crio-<id>.scope, /docker/<id>, /lxc/<name>
+//   - /proc/self/mountinfo     the host source of /etc/hostname, /etc/hosts
+//                              and /etc/resolv.conf, e.g.
+//                              /var/lib/docker/containers/<id>/hostname
+// mountinfo matters on cgroup v2, where a private cgroup namespace shows
+// the container its cgroup as "0::/". Kubernetes (KUBERNETES_SERVICE_HOST,
+// the service account mount, kubepods cgroups, kubelet mounts) is recorded
+// as the orchestrator, next to the runtime.
+//
+// IMAGE: nothing inside a container reliably names its image. With
+// `vbw build --container-image <file>` the build reads an OCI (or Docker
+// v2) image index, image manifest or image config, e.g. saved with
+// `skopeo inspect --raw` or `skopeo inspect --config --raw`:
+//   - index or manifest:  its SHA-256 is the image digest
+//                         (image_digest_source "oci-index"/"oci-manifest")
+//   - manifest:           its config descriptor names the image id
+//   - config:             its SHA-256 is the image id
+// The digest is computed by vbw from the file, but the file comes from
+// the builder. The build fails if the file contradicts the image id podman
+// reports or the CONTAINER_IMAGE_DIGEST variable. When podman's image id
+// matches a manifest's config descriptor, the runtime has confirmed the
+// file ("oci-manifest+runtime"). Without a file, CONTAINER_IMAGE_DIGEST
+// is recorded as claimed (image_digest_source "env").
+//
+// POLICY: requirements.container.allowed_image_digests makes verify
+// require one of the listed digests. A listed digest the runtime did not
+// confirm, from an OCI file alone or claimed through the variable, is a
+// warning.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::hash;
+use crate::vbw::model::{ContainerInfo, ContainerRequirement};
+
+/// Variable a CI system can set to the image digest it started the build in.
+pub const IMAGE_DIGEST_ENV: &str = "CONTAINER_IMAGE_DIGEST";
+
+/// Sources container detection reads, gathered up front so identification
+/// can be tested without a container.
+#[derive(Default)]
+struct Sources {
+    containerenv: Option<String>,
+    dockerenv: bool,
+    systemd_container: Option<String>,
+    container_var: Option<String>,
+    cgroup: Option<String>,
+    mountinfo: Option<String>,
+    kubernetes: bool,
+}
+
+impl Sources {
+    fn read() -> Self {
+        Sources {
+            containerenv: fs::read_to_string("/run/.containerenv").ok(),
+            dockerenv: Path::new("/.dockerenv").exists(),
+            systemd_container: fs::read_to_string("/run/systemd/container").ok(),
+            container_var: std::env::var("container").ok(),
+            cgroup: fs::read_to_string("/proc/self/cgroup").ok(),
+            mountinfo: fs::read_to_string("/proc/self/mountinfo").ok(),
+            kubernetes: std::env::var_os("KUBERNETES_SERVICE_HOST").is_some()
+                || Path::new("/var/run/secrets/kubernetes.io/serviceaccount").exists(),
+        }
+    }
+}
+
+/// What the sources say about the container.
+#[derive(Debug, Default, PartialEq)]
+struct Identity {
+    runtime: Option<String>,
+    id: Option<String>,
+    kubernetes: bool,
+    image: Option<String>,
+    /// Image id reported by the runtime ("sha256:<hex>")
+    image_id: Option<String>,
+    evidence: Vec<String>,
+}
+
+/// Detect the container the build runs in. `image_file` is an OCI image
+/// index, manifest or config describing its image.
+pub fn detect_container(image_file: Option<&Path>) -> Result<Option<ContainerInfo>> {
+    let identity = identify(&Sources::read());
+    let claimed_digest = std::env::var(IMAGE_DIGEST_ENV).ok();
+    let image = match image_file {
+        Some(path) => Some(read_oci_image(path)?),
+        None => None,
+    };
+
+    // Hosted CI runners are VMs, not containers; ci.rs records their image
+    if identity.runtime.is_none() && !identity.kubernetes && image.is_none() {
+        return Ok(None);
+    }
+
+    let mut image_id = identity.image_id;
+    let (image_digest, image_digest_source) = match &image {
+        Some(oci) => {
+            let mut confirmed = false;
+            if let (Some(runtime_id), Some(file_id)) = (&image_id, &oci.image_id) {
+                if runtime_id != file_id {
+                    anyhow::bail!(
+                        "--container-image describes image {} but the container runs image {}",
+                        file_id,
+                        runtime_id
+                    );
+                }
+                confirmed = true;
+            }
+            image_id = image_id.or(oci.image_id.clone());
+            match &oci.digest {
+                Some(digest) => {
+                    if let Some(claimed) = &claimed_digest {
+                        if normalize_digest(claimed) != *digest {
+                            anyhow::bail!(
+                                "{} is {} but --container-image has digest {}",
+                                IMAGE_DIGEST_ENV,
+                                claimed,
+                                digest
+                            );
+                        }
+                    }
+                    let source = if confirmed {
+                        format!("{}+runtime", oci.kind)
+                    } else {
+                        oci.kind.to_string()
+                    };
+                    (digest.clone(), Some(source))
+                }
+                None => claimed_digest_or_unknown(claimed_digest),
+            }
+        }
+        None => claimed_digest_or_unknown(claimed_digest),
+    };
+    if image.is_some() {
+        eprintln!(
+            "[vbw] Container image: digest {}, id {}",
+            image_digest,
+            image_id.as_deref().unwrap_or("unknown")
+        );
+    }
+
+    Ok(Some(ContainerInfo {
+        container_type: identity.runtime.unwrap_or_else(|| "oci".to_string()),
+        id: identity.id,
+        orchestrator: identity.kubernetes.then(|| "kubernetes".to_string()),
+        image: std::env::var("CONTAINER_IMAGE").ok().or(identity.image),
+        image_digest,
+        image_digest_source,
+        image_id,
+        evidence: (!identity.evidence.is_empty()).then_some(identity.evidence),
+    }))
+}
+
+fn claimed_digest_or_unknown(claimed: Option<String>) -> (String, Option<String>) {
+    match claimed {
+        Some(digest) => (normalize_digest(&digest), Some("env".to_string())),
+        None => ("unknown".to_string(), None),
+    }
+}
+
+/// "sha256:<lowercase hex>", accepting a bare hex digest.
+fn normalize_digest(digest: &str) -> String {
+    let digest = digest.trim().to_ascii_lowercase();
+    if digest.contains(':') {
+        digest
+    } else {
+        format!("sha256:{}", digest)
+    }
+}
+
+fn identify(sources: &Sources) -> Identity {
+    let mut identity = Identity {
+        kubernetes: sources.kubernetes,
+        ..Default::default()
+    };
+    if sources.kubernetes {
+        identity.evidence.push("kubernetes".to_string());
+    }
+    if let Some(text) = &sources.containerenv {
+        let fields = parse_containerenv(text);
+        let engine = fields.get("engine").map(String::as_str).unwrap_or("podman");
+        // CRI-O writes the same file under Kubernetes
+        let runtime = if engine.starts_with("cri-o") {
+            "cri-o"
+        } else {
+            "podman"
+        };
+        note_runtime(&mut identity, runtime, "/run/.containerenv");
+        identity.id = fields.get("id").filter(|id| is_container_id(id)).cloned();
+        identity.image = fields.get("image").filter(|i| !i.is_empty()).cloned();
+        identity.image_id = fields
+            .get("imageid")
+            .filter(|id| is_container_id(id))
+            .map(|id| format!("sha256:{}", id));
+    }
+    if sources.dockerenv {
+        note_runtime(&mut identity, "docker", "/.dockerenv");
+    }
+    let declared = sources
+        .systemd_container
+        .as_deref()
+        .map(|s| (s, "/run/systemd/container"))
+        .or(sources
+            .container_var
+            .as_deref()
+            .map(|s| (s, "container variable")));
+    if let Some((name, evidence)) = declared {
+        let name = name.trim();
+        if !name.is_empty() {
+            let runtime = if name == "oci" { "oci" } else { name };
+            note_runtime(&mut identity, runtime, evidence);
+        }
+    }
+
+    for (source, text, parse) in [
+        ("cgroup", &sources.cgroup, parse_cgroup as fn(&str) -> Found),
+        (
+            "mountinfo",
+            &sources.mountinfo,
+            parse_mountinfo as fn(&str) -> Found,
+        ),
+    ] {
+        let Some(text) = text else {
+            continue;
+        };
+        let found = parse(text);
+        if found.kubernetes && !identity.kubernetes {
+            identity.kubernetes = true;
+            identity.evidence.push(format!("kubernetes ({})", source));
+        }
+        if found.runtime.is_none() && found.id.is_none() {
+            continue;
+        }
+        match found.runtime {
+            Some(runtime) => note_runtime(&mut identity, runtime, source),
+            None => identity.evidence.push(source.to_string()),
+        }
+        if identity.id.is_none() {
+            identity.id = found.id;
+        }
+    }
+    identity
+}
+
+/// Record the runtime `evidence` points to; stronger evidence came first.
+fn note_runtime(identity: &mut Identity, runtime: &str, evidence: &str) {
+    if identity.runtime.is_none() {
+        identity.runtime = Some(runtime.to_string());
+    }
+    identity.evidence.push(evidence.to_string());
+}
+
+/// `key=value` lines of /run/.containerenv, quotes removed.
+fn parse_containerenv(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Runtime and container id found in a cgroup or mountinfo listing.
+#[derive(Debug, Default, PartialEq)]
+struct Found {
+    runtime: Option<&'static str>,
+    id: Option<String>,
+    kubernetes: bool,
+}
+
+/// Scope-unit prefixes of container cgroups (systemd cgroup driver).
+const SCOPE_PREFIXES: &[(&str, &str)] = &[
+    ("docker-", "docker"),
+    ("libpod-", "podman"),
+    ("cri-containerd-", "containerd"),
+    ("crio-", "cri-o"),
+];
+
+/// Parse /proc/self/cgroup: `hierarchy:controllers:path` lines, v1 or v2.
+fn parse_cgroup(text: &str) -> Found {
+    let mut found = Found::default();
+    for line in text.lines() {
+        let Some(path) = line.splitn(3, ':').nth(2) else {
+            continue;
+        };
+        if path.contains("kubepods") {
+            found.kubernetes = true;
+        }
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for (i, segment) in segments.iter().enumerate() {
+            let unit = segment.strip_suffix(".scope").unwrap_or(segment);
+            for (prefix, runtime) in SCOPE_PREFIXES {
+                if let Some(id) = unit.strip_prefix(prefix).filter(|id| is_container_id(id)) {
+                    found.runtime.get_or_insert(runtime);
+                    found.id.get_or_insert_with(|| id.to_string());
+                }
+            }
+            if i > 0 && is_container_id(unit) {
+                // cgroupfs driver: /docker/<id>, /kubepods/<qos>/pod<uid>/<id>
+                if segments[i - 1] == "docker" {
+                    found.runtime.get_or_insert("docker");
+                }
+                found.id.get_or_insert_with(|| unit.to_string());
+            }
+            if *segment == "lxc" || segment.starts_with("lxc.payload") {
+                found.runtime.get_or_insert("lxc");
+            }
+        }
+    }
+    found
+}
+
+/// Mount points whose host source names the container.
+const RUNTIME_MOUNTS: &[&str] = &["/etc/hostname", "/etc/hosts", "/etc/resolv.conf"];
+
+/// Parse /proc/self/mountinfo: the root (4th field) of the runtime's
+/// /etc/hostname, /etc/hosts and /etc/resolv.conf bind mounts.
+fn parse_mountinfo(text: &str) -> Found {
+    let mut found = Found::default();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split(' ').collect();
+        let (Some(root), Some(mount_point)) = (fields.get(3), fields.get(4)) else {
+            continue;
+        };
+        if !RUNTIME_MOUNTS.contains(mount_point) {
+            continue;
+        }
+        if root.contains("/kubelet/pods/") {
+            found.kubernetes = true;
+        }
+        let segments: Vec<&str> = root.split('/').collect();
+        for (i, segment) in segments.iter().enumerate() {
+            if i == 0 || !is_container_id(segment) {
+                continue;
+            }
+            let runtime = match segments[i - 1] {
+                "containers" if root.contains("/docker/") => Some("docker"),
+                // containers/storage, shared by podman and CRI-O
+                "overlay-containers" => Some("podman"),
+                _ if root.contains("io.containerd") => Some("containerd"),
+                _ => None,
+            };
+            if let Some(runtime) = runtime {
+                found.runtime.get_or_insert(runtime);
+                found.id.get_or_insert_with(|| segment.to_string());
+            }
+        }
+    }
+    if found.kubernetes && found.runtime == Some("podman") {
+        found.runtime = Some("cri-o");
+    }
+    found
+}
+
+/// A full container or image id: 64 lowercase hex digits.
+fn is_container_id(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// ---------------------------------------------------------------------------
+// OCI IMAGE FILES
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq)]
+enum OciKind {
+    Index,
+    Manifest,
+    Config,
+}
+
+impl std::fmt::Display for OciKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OciKind::Index => "oci-index",
+            OciKind::Manifest => "oci-manifest",
+            OciKind::Config => "oci-config",
+        })
+    }
+}
+
+#[derive(Debug)]
+struct OciImage {
+    kind: OciKind,
+    /// Image digest (index or manifest only)
+    digest: Option<String>,
+    /// Image id: the config digest (manifest or config only)
+    image_id: Option<String>,
+}
+
+/// Read an OCI or Docker v2 image index, manifest or config and compute
+/// the digests it establishes.
+fn read_oci_image(path: &Path) -> Result<OciImage> {
+    let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    parse_oci_image(&bytes).with_context(|| format!("--container-image {}", path.display()))
+}
+
+fn parse_oci_image(bytes: &[u8]) -> Result<OciImage> {
+    let value: serde_json::Value = serde_json::from_slice(bytes).context("not a JSON document")?;
+    let digest = format!("sha256:{}", hash::sha256_hex(bytes));
+    let media_type = value["mediaType"].as_str().unwrap_or_default();
+    let kind = match media_type {
+        "application/vnd.oci.image.index.v1+json"
+        | "application/vnd.docker.distribution.manifest.list.v2+json" => OciKind::Index,
+        "application/vnd.oci.image.manifest.v1+json"
+        | "application/vnd.docker.distribution.manifest.v2+json" => OciKind::Manifest,
+        _ if value["manifests"].is_array() => OciKind::Index,
+        _ if value["config"]["digest"].is_string() && value["layers"].is_array() => {
+            OciKind::Manifest
+        }
+        _ if value["rootfs"].is_object() => OciKind::Config,
+        _ => anyhow::bail!("not an OCI image index, image manifest or image config"),
+    };
+    Ok(match kind {
+        OciKind::Index => OciImage {
+            kind,
+            digest: Some(digest),
+            image_id: None,
+        },
+        OciKind::Manifest => OciImage {
+            kind,
+            digest: Some(digest),
+            image_id: value["config"]["digest"].as_str().map(normalize_digest),
+        },
+        OciKind::Config => OciImage {
+            kind,
+            digest: None,
+            image_id: Some(digest),
+        },
+    })
+}
+
+// ---------------------------------------------------------------------------
+// POLICY
+// ---------------------------------------------------------------------------
+
+/// Check the recorded container against the policy's container
+/// requirement.
+pub fn check_policy(
+    container: Option<&ContainerInfo>,
+    requirement: &ContainerRequirement,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(allowed) = &requirement.allowed_image_digests else {
+        return;
+    };
+    let allowed: Vec<String> = allowed.iter().map(|d| normalize_digest(d)).collect();
+    let Some(container) = container.filter(|c| c.container_type != "none") else {
+        errors.push(
+            "Policy requires a container image digest but the build did not run in a container"
+                .to_string(),
+        );
+        return;
+    };
+    let digest = normalize_digest(&container.image_digest);
+    if !allowed.contains(&digest) {
+        errors.push(format!(
+            "Container image digest {} is not in policy allowed_image_digests",
+            container.image_digest
+        ));
+        return;
+    }
+    match container.image_digest_source.as_deref() {
+        Some("oci-manifest+runtime") => {
+            eprintln!("[vbw] Container image digest: OK ({})", digest);
+        }
+        Some(source @ ("oci-index" | "oci-manifest")) => warnings.push(format!(
+            "Container image digest {} matches the policy but was computed from an OCI {} \
+             the builder supplied, which the container runtime did not confirm",
+            digest,
+            source.trim_start_matches("oci-")
+        )),
+        source => warnings.push(format!(
+            "Container image digest {} matches the policy but was claimed ({}), \
+             not computed from an OCI image file",
+            digest,
+            source.unwrap_or("no source recorded")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4e1b2a9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f";
+
+    #[test]
+    fn identifies_runtimes_from_cgroup_paths() {
+        let v2 = format!("0::/system.slice/docker-{}.scope\n", ID);
+        let found = parse_cgroup(&v2);
+        assert_eq!(found.runtime, Some("docker"));
+        assert_eq!(found.id.as_deref(), Some(ID));
+
+        let v1 = format!("12:memory:/docker/{}\n11:cpu:/docker/{}\n", ID, ID);
+        assert_eq!(parse_cgroup(&v1).runtime, Some("docker"));
+
+        let podman = format!(
+            "0::/user.slice/user-1000.slice/libpod-{}.scope/container\n",
+            ID
+        );
+        assert_eq!(parse_cgroup(&podman).runtime, Some("podman"));
+
+        let k8s = format!(
+            "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1234.slice/cri-containerd-{}.scope\n",
+            ID
+        );
+        let found = parse_cgroup(&k8s);
+        assert_eq!(found.runtime, Some("containerd"));
+        assert!(found.kubernetes);
+
+        // Private cgroup namespace: nothing to learn
+        assert_eq!(parse_cgroup("0::/\n"), Found::default());
+    }
+
+    #[test]
+    fn identifies_runtimes_from_mountinfo() {
+        let docker = format!(
+            "612 590 254:1 /var/lib/docker/containers/{}/hostname /etc/hostname rw,relatime - ext4 /dev/vda1 rw\n",
+            ID
+        );
+        let found = parse_mountinfo(&docker);
+        assert_eq!(found.runtime, Some("docker"));
+        assert_eq!(found.id.as_deref(), Some(ID));
+
+        let podman = format!(
+            "700 690 0:25 /containers/storage/overlay-containers/{}/userdata/resolv.conf /etc/resolv.conf rw - tmpfs tmpfs rw\n",
+            ID
+        );
+        assert_eq!(parse_mountinfo(&podman).runtime, Some("podman"));
+
+        // Other mounts naming ids are ignored
+        let other = format!("1 0 0:1 /data/{} /data rw - ext4 /dev/vda1 rw\n", ID);
+        assert_eq!(parse_mountinfo(&other), Found::default());
+    }
+
+    #[test]
+    fn prefers_markers_and_records_evidence() {
+        let sources = Sources {
+            containerenv: Some(format!(
+                "engine=\"podman-4.9.3\"\nname=\"builder\"\nid=\"{}\"\nimage=\"docker.io/library/rust:1.78\"\nimageid=\"{}\"\n",
+                ID, ID
+            )),
+            cgroup: Some("0::/\n".to_string()),
+            ..Default::default()
+        };
+        let identity = identify(&sources);
+        assert_eq!(identity.runtime.as_deref(), Some("podman"));
+        assert_eq!(identity.id.as_deref(), Some(ID));
+        assert_eq!(
+            identity.image.as_deref(),
+            Some("docker.io/library/rust:1.78")
+        );
+        assert_eq!(identity.image_id, Some(format!("sha256:{}", ID)));
+        assert_eq!(identity.evidence, vec!["/run/.containerenv"]);
+
+        let nspawn = Sources {
+            systemd_container: Some("systemd-nspawn\n".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(identify(&nspawn).runtime.as_deref(), Some("systemd-nspawn"));
+
+        assert_eq!(identify(&Sources::default()), Identity::default());
+    }
+
+    #[test]
+    fn digests_oci_image_files() {
+        let manifest = br#"{"schemaVersion":2,"mediaType":"application/vnd.oci.image.manifest.v1+json","config":{"mediaType":"application/vnd.oci.image.config.v1+json","digest":"sha256:AB12","size":1},"layers":[]}"#;
+        let image = parse_oci_image(manifest).unwrap();
+        assert_eq!(image.kind, OciKind::Manifest);
+        assert_eq!(
+            image.digest,
+            Some(format!("sha256:{}", hash::sha256_hex(manifest)))
+        );
+        assert_eq!(image.image_id.as_deref(), Some("sha256:ab12"));
+
+        let index = br#"{"schemaVersion":2,"manifests":[]}"#;
+        assert_eq!(parse_oci_image(index).unwrap().kind, OciKind::Index);
+
+        let config =
+            br#"{"architecture":"amd64","os":"linux","rootfs":{"type":"layers","diff_ids":[]}}"#;
+        let image = parse_oci_image(config).unwrap();
+        assert_eq!(image.kind, OciKind::Config);
+        assert_eq!(image.digest, None);
+        assert_eq!(
+            image.image_id,
+            Some(format!("sha256:{}", hash::sha256_hex(config)))
+        );
+
+        assert!(parse_oci_image(b"{}").is_err());
+    }
+
+    #[test]
+    fn policy_requires_a_computed_allowed_digest() {
+        let digest = format!("sha256:{}", ID);
+        let requirement = ContainerRequirement {
+            allowed_image_digests: Some(vec![digest.clone()]),
+        };
+        let container = |source: &str| ContainerInfo {
+            container_type: "docker".to_string(),
+            id: None,
+            orchestrator: None,
+            image: None,
+            image_digest: digest.clone(),
+            image_digest_source: Some(source.to_string()),
+            image_id: None,
+            evidence: None,
+        };
+        let check = |container: Option<&ContainerInfo>| {
+            let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+            check_policy(container, &requirement, &mut errors, &mut warnings);
+            (errors, warnings)
+        };
+
+        assert_eq!(
+            check(Some(&container("oci-manifest+runtime"))),
+            (vec![], vec![])
+        );
+        let (errors, warnings) = check(Some(&container("oci-manifest")));
+        assert!(errors.is_empty());
+        assert!(warnings[0].contains("did not confirm"), "{:?}", warnings);
+        let (errors, warnings) = check(Some(&container("env")));
+        assert!(errors.is_empty());
+        assert!(warnings[0].contains("claimed (env)"), "{:?}", warnings);
+
+        let mut other = container("oci-manifest+runtime");
+        other.image_digest = format!("sha256:{}", "0".repeat(64));
+        assert!(check(Some(&other)).0[0].contains("not in policy allowed_image_digests"));
+        assert!(check(None).0[0].contains("did not run in a container"));
+    }
+}
@@ -0,0 +1,186 @@
+// inputs.rs — Classification of the files a build read (--trace-inputs)
+//
+// The tracer (trace.rs) reports every regular file the build's process
+// tree opened for reading, as an absolute path. Each file is classified:
+//
+//   - tracked-in-git:     in the repository and tracked by git (or part of
+//                         .git itself), so the source hashes cover it
+//   - untracked-in-repo:  in the repository but not tracked: a provenance
+//                         gap. Its SHA-256 is recorded, and build.rs lists
+//                         it in enforcement.untracked_inputs
+//   - toolchain:          under a system directory (/usr, /lib*, /bin,
+//                         /sbin) or a detected tool's installation
+//   - outside-repo:       anything else (dependency caches, /etc, $HOME)
+//
+// A tool's installation is the directory above its bin/, so a rustc at
+// ~/.rustup/toolchains/stable/bin/rustc covers the toolchain's lib/ too.
+// A tool not in a bin/ directory only covers its own directory.
+//
+// Gitignored build caches (target/, node_modules/) read by the build show
+// up as untracked inputs. That is intended: a cache left by an earlier
+// build is an input the source hashes do not cover.
+//
+// Untracked files are hashed after the build finishes, so a file the
+// build changed is recorded as it was left, and one it deleted has no
+// hash.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::hash;
+use crate::vbw::model::{InputClass, InputFile, InputsLog, ToolInfo};
+
+/// System directories whose files count as toolchain.
+const SYSTEM_DIRS: &[&str] = &[
+    "/usr", "/lib", "/lib32", "/lib64", "/libx32", "/bin", "/sbin",
+];
+
+/// Classify the files the build read against the current repository's
+/// tracked files and the detected tools.
+pub fn classify_inputs(files_read: BTreeMap<String, u64>, tools: &[ToolInfo]) -> Result<InputsLog> {
+    let repo_root = crate::git::repo_root()?;
+    // Traced paths are canonical; so must the root be to match them
+    let repo_root = repo_root.canonicalize().unwrap_or(repo_root);
+    let tracked = crate::git::tracked_files().context("listing tracked files")?;
+    let toolchain_dirs = toolchain_dirs(tools);
+    Ok(classify(files_read, &repo_root, &tracked, &toolchain_dirs))
+}
+
+/// Installation directories of the detected tools.
+fn toolchain_dirs(tools: &[ToolInfo]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for path in tools.iter().filter_map(|t| t.path.as_deref()) {
+        let path = Path::new(path);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let Some(dir) = path.parent() else {
+            continue;
+        };
+        let dir = match dir.file_name() {
+            Some(name) if name == "bin" => dir.parent().unwrap_or(dir),
+            _ => dir,
+        };
+        // A tool directly in / would make everything toolchain
+        if dir.parent().is_some() && !dirs.iter().any(|d| d == dir) {
+            dirs.push(dir.to_path_buf());
+        }
+    }
+    dirs
+}
+
+fn classify(
+    files_read: BTreeMap<String, u64>,
+    repo_root: &Path,
+    tracked: &BTreeSet<String>,
+    toolchain_dirs: &[PathBuf],
+) -> InputsLog {
+    let mut files = Vec::new();
+    for (absolute, count) in files_read {
+        let path = Path::new(&absolute);
+        let (path, class) = match path.strip_prefix(repo_root) {
+            Ok(relative) => {
+                let relative = relative.to_string_lossy().into_owned();
+                let class =
+                    if tracked.contains(&relative) || Path::new(&relative).starts_with(".git") {
+                        InputClass::TrackedInGit
+                    } else {
+                        InputClass::UntrackedInRepo
+                    };
+                (relative, class)
+            }
+            Err(_) => {
+                let class = if SYSTEM_DIRS.iter().any(|dir| path.starts_with(dir))
+                    || toolchain_dirs.iter().any(|dir| path.starts_with(dir))
+                {
+                    InputClass::Toolchain
+                } else {
+                    InputClass::OutsideRepo
+                };
+                (absolute.clone(), class)
+            }
+        };
+        let sha256 = if class == InputClass::UntrackedInRepo {
+            hash::hash_file(Path::new(&absolute)).ok()
+        } else {
+            None
+        };
+        files.push(InputFile {
+            path,
+            class,
+            count,
+            sha256,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    InputsLog { files }
+}
+
+/// Paths of the untracked files in the repository the build read.
+pub fn untracked(log: &InputsLog) -> Vec<String> {
+    log.files
+        .iter()
+        .filter(|f| f.class == InputClass::UntrackedInRepo)
+        .map(|f| f.path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tool(path: &str) -> ToolInfo {
+        ToolInfo {
+            name: "tool".to_string(),
+            version: "1.0".to_string(),
+            path: Some(path.to_string()),
+            sha256: None,
+            invocation: None,
+            details: None,
+        }
+    }
+
+    #[test]
+    fn classifies_inputs_by_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::write(repo.join("config.local.json"), "{}").unwrap();
+        let in_repo = |p: &str| repo.join(p).to_string_lossy().into_owned();
+
+        let files_read = BTreeMap::from([
+            (in_repo("src/main.rs"), 2),
+            (in_repo(".git/HEAD"), 1),
+            (in_repo("config.local.json"), 1),
+            ("/usr/lib/libc.so.6".to_string(), 5),
+            ("/opt/rust/lib/libstd.rlib".to_string(), 1),
+            ("/etc/passwd".to_string(), 1),
+        ]);
+        let tracked = BTreeSet::from(["src/main.rs".to_string()]);
+        let dirs = toolchain_dirs(&[tool("/opt/rust/bin/rustc")]);
+        let log = classify(files_read, &repo, &tracked, &dirs);
+
+        let class_of = |path: &str| log.files.iter().find(|f| f.path == path).unwrap().class;
+        assert_eq!(class_of("src/main.rs"), InputClass::TrackedInGit);
+        assert_eq!(class_of(".git/HEAD"), InputClass::TrackedInGit);
+        assert_eq!(class_of("config.local.json"), InputClass::UntrackedInRepo);
+        assert_eq!(class_of("/usr/lib/libc.so.6"), InputClass::Toolchain);
+        assert_eq!(class_of("/opt/rust/lib/libstd.rlib"), InputClass::Toolchain);
+        assert_eq!(class_of("/etc/passwd"), InputClass::OutsideRepo);
+
+        assert_eq!(untracked(&log), vec!["config.local.json".to_string()]);
+        let config = log
+            .files
+            .iter()
+            .find(|f| f.path == "config.local.json")
+            .unwrap();
+        assert_eq!(
+            config.sha256.as_deref(),
+            Some(hash::sha256_hex(b"{}").as_str())
+        );
+        assert!(log
+            .files
+            .iter()
+            .all(|f| f.path == "config.local.json" || f.sha256.is_none()));
+    }
+}
@@ -3,8 +3,30 @@
 // model.rs  — Data structures (serde) matching the JSON schemas
 // build.rs  — Build command: run build, capture environment, generate bundle
 // verify.rs — Verify command: check hashes, signature, policy compliance
+// chain.rs  — Verify-chain command: gaps, forks and broken links in a release history
+// ci.rs     — CI provider context: run, workflow, event, actor, ref
+// container.rs — Container runtime, id and image digest of the build
+// envvars.rs — Environment variable capture with secret redaction
+// inputs.rs — Classification of the files a build read (inputs.json)
+// proxy.rs  — Mode B network allowlist: local recording HTTP(S) proxy
+// sandbox.rs — Mode A build sandbox: network, mount and filesystem isolation
+// tools.rs  — Toolchain detector registry: versions and hashes of resolved binaries
+// trace.rs  — Syscall tracing of the build's process tree (network access, executed programs, files read)
+// transcript.rs — Byte-exact capture of the build's stdout and stderr (transcript.jsonl)
+// upstream.rs — Artifacts consumed from other projects' bundles (vbw-artifact materials)
 
 pub mod build;
 pub mod canonical;
+pub mod chain;
+pub mod ci;
+pub mod container;
+pub mod envvars;
+pub mod inputs;
 pub mod model;
+pub mod proxy;
+pub mod sandbox;
+pub mod tools;
+pub mod trace;
+pub mod transcript;
+pub mod upstream;
 pub mod verify;
@@ -12,14 +12,21 @@
 // responsibility of external tooling, not this code.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::sign::Algorithm;
 
 // ── Manifest ────────────────────────────────────────────────────────────────
 // The root document of a witness bundle. Contains hashes of all other files,
 // git state, builder identity, and the policy reference. This is the file
 // that gets signed.
 //
-// SIGNING: The Ed25519 signature covers canonical_manifest_bytes(&manifest),
+// SIGNING: The builder signature covers canonical_manifest_bytes(&manifest),
 // NOT the pretty-printed JSON on disk. See canonical.rs for the canonical form.
+//
+// CHAINING: `sequence` and `previous_manifest_hash` link a bundle to the
+// release before it, so a skipped or replaced release shows up as a gap or
+// broken link in `vbw verify-chain` (see chain.rs). Both are signed.
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Manifest {
@@ -41,6 +48,37 @@ pub struct Manifest {
     pub outputs_hash: String,
     pub builder_identity: BuilderIdentity,
     pub policy_ref: PolicyRef,
+    /// Position in a release chain: the previous bundle's sequence plus one.
+    /// Absent for unchained bundles, which count as sequence 0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// SHA-256 of the previous bundle's canonical manifest bytes (its
+    /// hashes/manifest.sha256). Set together with `sequence`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_manifest_hash: Option<String>,
+    /// Pipeline steps composed into this build, in execution order. Each
+    /// is a signed sub-witness under steps/<name>/ (see STEPS below).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<Vec<StepRef>>,
+    /// SHA-256 of network.json, the connection log of the Mode B recording
+    /// proxy (see proxy.rs). Absent when the build ran without the proxy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_log_hash: Option<String>,
+    /// SHA-256 of network-access.json, the connections and DNS queries
+    /// traced with `vbw build --trace-network` (see trace.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_access_hash: Option<String>,
+    /// SHA-256 of inputs.json, the files traced being read with
+    /// `vbw build --trace-inputs` (see inputs.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs_hash: Option<String>,
+    /// SHA-256 of transcript.jsonl (transcript.txt in older bundles).
+    /// Absent in bundles from before the transcript was signed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_hash: Option<String>,
+    /// How the build command ended and what it used (see BUILD RESULT).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_result: Option<BuildResult>,
     /// Records what the build tool actually enforced vs. what was requested.
     /// Always present in bundles produced by VBW v1.0+.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -71,14 +109,177 @@ pub struct GitRef {
     pub dirty: bool,
 }
 
+// KEY TYPES: Ed25519 keys live in `public_key_ed25519`, exactly as in VBW 1.0
+// bundles (which have no `key_type` and are always Ed25519). Keys for other
+// algorithms set `key_type` and live in `public_key`. This keeps 1.0 bundles
+// byte-identical under canonicalization, so their signatures still verify.
+//
+// HYBRID MODE: a builder identity may also carry a post-quantum key in
+// `hybrid`. The builder then signs the same canonical bytes with both keys,
+// writing one `builder.<alg>.sig` per key, and verify requires both. Because
+// the hybrid key is inside the signed manifest, stripping it (or the second
+// signature) from a bundle invalidates the classical signature too.
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuilderIdentity {
     /// Human-readable identifier (e.g. "builder@ci", "alice@example.com").
     pub key_id: String,
+    /// Signature algorithm of the builder key. Absent means Ed25519.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<Algorithm>,
     /// Base64-encoded Ed25519 public key (44 characters with padding).
+    /// Empty for non-Ed25519 keys.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub public_key_ed25519: String,
+    /// Base64-encoded public key for non-Ed25519 algorithms
+    /// (SEC1 compressed point for ECDSA P-256).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Issuer of the OIDC token in `oidc`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub issuer: Option<String>,
+    /// CI identity the key is bound to through an OIDC ID token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oidc: Option<OidcBinding>,
+    /// Post-quantum half of a hybrid identity. Absent for classical-only keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hybrid: Option<HybridKey>,
+    /// OpenSSH fingerprint ("SHA256:...") when the key was held in ssh-agent.
+    /// Lets reviewers match the builder key against `ssh-add -l` output or
+    /// git signing keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_fingerprint: Option<String>,
+}
+
+// OIDC BINDING: with `vbw build --oidc`, the builder key is bound to a CI
+// identity by an OIDC ID token whose audience is derived from the public key
+// (see oidc.rs). The token is stored as signatures/builder.oidc.jwt and its
+// claims are copied here, inside the signed manifest; verify checks that the
+// two agree, that the claims match `git`, and (given the issuer's JWKS) the
+// token's signature. With `--ephemeral-key` the token is the only identity
+// behind the key.
+
+/// Claims of the OIDC ID token the builder key is bound to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OidcBinding {
+    /// SHA-256 of signatures/builder.oidc.jwt.
+    pub token_sha256: String,
+    pub subject: String,
+    /// "vbw:sha256:<hex>" of the builder public key.
+    pub audience: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha: Option<String>,
+    pub issued_at: String,
+    pub expires_at: String,
+}
+
+impl OidcBinding {
+    /// The binding recorded for `token` (the raw JWT), whose `claims` have
+    /// been checked to include `audience`.
+    pub fn from_token(token: &str, claims: &crate::oidc::Claims, audience: &str) -> Self {
+        let time = |secs: i64| {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .unwrap_or_default()
+        };
+        OidcBinding {
+            token_sha256: crate::hash::sha256_hex(token.as_bytes()),
+            subject: claims.sub.clone(),
+            audience: audience.to_string(),
+            repository: claims.repository.clone(),
+            git_ref: claims.git_ref.clone(),
+            workflow: claims.workflow.clone(),
+            sha: claims.sha.clone(),
+            issued_at: time(claims.iat),
+            expires_at: time(claims.exp),
+        }
+    }
+}
+
+/// Filename (under signatures/) of the OIDC ID token binding the builder key.
+pub const OIDC_TOKEN_FILE: &str = "builder.oidc.jwt";
+
+/// Second (post-quantum) builder key in hybrid mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridKey {
+    /// Always a post-quantum algorithm ("ml-dsa-65").
+    pub key_type: Algorithm,
+    /// Base64-encoded public key (1952 bytes for ML-DSA-65).
+    pub public_key: String,
+}
+
+impl BuilderIdentity {
+    /// The builder key's algorithm (Ed25519 when `key_type` is absent).
+    pub fn algorithm(&self) -> Algorithm {
+        self.key_type.unwrap_or(Algorithm::Ed25519)
+    }
+
+    /// The base64 public key for `algorithm()`.
+    pub fn public_key_b64(&self) -> &str {
+        select_public_key(self.algorithm(), &self.public_key_ed25519, &self.public_key)
+    }
+
+    /// Filename (under signatures/) of the builder signature for this key.
+    pub fn signature_filename(&self) -> String {
+        builder_signature_filename(self.algorithm())
+    }
+
+    /// Every builder signature this identity requires, as
+    /// (algorithm, public key, filename under signatures/). One entry for a
+    /// classical key, two in hybrid mode.
+    pub fn required_signatures(&self) -> Vec<(Algorithm, &str, String)> {
+        let mut sigs = vec![(
+            self.algorithm(),
+            self.public_key_b64(),
+            self.signature_filename(),
+        )];
+        if let Some(hybrid) = &self.hybrid {
+            sigs.push((
+                hybrid.key_type,
+                hybrid.public_key.as_str(),
+                builder_signature_filename(hybrid.key_type),
+            ));
+        }
+        sigs
+    }
+}
+
+/// Filename (under signatures/) of a builder signature made with `algorithm`.
+pub fn builder_signature_filename(algorithm: Algorithm) -> String {
+    format!("builder.{}.sig", algorithm.as_str())
+}
+
+/// Filename (under signatures/) of the RFC 3161 timestamp token over the
+/// builder signature made with `algorithm`.
+pub fn builder_timestamp_filename(algorithm: Algorithm) -> String {
+    format!("builder.{}.tst", algorithm.as_str())
+}
+
+/// Pick the public key field matching `algorithm` (see KEY TYPES above).
+fn select_public_key<'a>(
+    algorithm: Algorithm,
+    public_key_ed25519: &'a str,
+    public_key: &'a Option<String>,
+) -> &'a str {
+    match algorithm {
+        Algorithm::Ed25519 if !public_key_ed25519.is_empty() => public_key_ed25519,
+        _ => public_key.as_deref().unwrap_or(""),
+    }
+}
+
+/// Split a public key into the (`public_key_ed25519`, `public_key`) field
+/// pair used on the wire (see KEY TYPES above).
+pub fn public_key_fields(algorithm: Algorithm, public_key_b64: String) -> (String, Option<String>) {
+    match algorithm {
+        Algorithm::Ed25519 => (public_key_b64, None),
+        _ => (String::new(), Some(public_key_b64)),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +288,164 @@ pub struct PolicyRef {
     pub hash_sha256: String,
 }
 
+// ── Pipeline steps ──────────────────────────────────────────────────────────
+// STEPS: A multi-step pipeline (codegen → compile → test → package, possibly
+// on different runners) runs `vbw build --step <name>` once per step. Each
+// step writes a signed sub-witness to vbw/steps/<name>/ with its own
+// step.json, environment.json, materials.lock.json, outputs.json and
+// transcript.jsonl. A step declares earlier steps' outputs as inputs
+// (`--input <step>[:<path>]`); they are recorded as materials of kind
+// "step-output" with `source` set to the producing step.
+//
+// The final `vbw build` (without --step) lists every step in
+// `Manifest.steps` with the SHA-256 of its canonical step.json, so the
+// builder signature covers the whole pipeline. Verify checks each step's
+// hashes and signature, and that every step-output material matches an
+// output of an earlier step.
+
+/// Material kind for an input taken from an earlier pipeline step.
+pub const STEP_OUTPUT_KIND: &str = "step-output";
+
+/// Files every step sub-witness contains (besides its signatures).
+pub const STEP_FILES: &[&str] = &[
+    "step.json",
+    "environment.json",
+    "materials.lock.json",
+    "outputs.json",
+];
+
+/// True for step names usable as a directory name: 1-64 of [a-z0-9_-].
+pub fn is_valid_step_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRef {
+    pub name: String,
+    /// SHA-256 of the step's canonical step.json bytes.
+    pub step_hash: String,
+}
+
+/// The signed statement of one pipeline step (steps/<name>/step.json).
+/// Signed like a manifest: over its canonical bytes, by the key of the
+/// runner that executed the step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepManifest {
+    pub vbw_version: String,
+    /// Step name, matching its directory under steps/.
+    pub step: String,
+    pub build_id: String,
+    pub created_at: String,
+    pub git: GitRef,
+    pub source_commit_tree_hash: String,
+    /// The step's command line.
+    pub command: Vec<String>,
+    /// SHA-256 of the step's environment.json.
+    pub environment_hash: String,
+    /// SHA-256 of the step's materials.lock.json.
+    pub materials_lock_hash: String,
+    /// SHA-256 of the step's outputs.json.
+    pub outputs_hash: String,
+    /// SHA-256 of the step's transcript.jsonl (transcript.txt in older
+    /// bundles).
+    pub transcript_hash: String,
+    /// SHA-256 of the policy.json the step ran under.
+    pub policy_hash: String,
+    pub builder_identity: BuilderIdentity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_result: Option<BuildResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enforcement: Option<Enforcement>,
+}
+
+// ── Transcript ──────────────────────────────────────────────────────────────
+// transcript.jsonl holds the build's stdout and stderr byte for byte, one
+// TranscriptChunk per line in arrival order (see transcript.rs). Bundles
+// from before it have a line-oriented transcript.txt instead.
+
+/// Byte-exact build transcript, in the bundle and in each step.
+pub const TRANSCRIPT_FILE: &str = "transcript.jsonl";
+
+/// Line-oriented transcript of older bundles, replaced by TRANSCRIPT_FILE.
+pub const LEGACY_TRANSCRIPT_FILE: &str = "transcript.txt";
+
+/// Bytes the build wrote to one stream in a single read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptChunk {
+    /// When the bytes were read (RFC 3339, milliseconds, UTC).
+    pub time: String,
+    /// "stdout" or "stderr"; "vbw" for notes from vbw itself.
+    pub stream: String,
+    /// The bytes, when they are valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The bytes, base64-encoded, when they are not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+}
+
+// ── Build result ────────────────────────────────────────────────────────────
+// How the build command ended, how long it ran and what it used. CPU time
+// and peak RSS come from the wait4() rusage of the build command, which
+// covers every descendant it waited for; they are absent where wait4 is
+// unavailable.
+//
+// FAILED BUILDS: `vbw build --record-failure` writes a signed bundle for a
+// build command that failed, with `success: false`, so the transcript is
+// kept as evidence. Verify reports such a bundle as UNVERIFIED.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildResult {
+    /// Whether the build command exited with status 0.
+    pub success: bool,
+    /// Exit code; absent when the command was killed by a signal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Signal that killed the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Wall-clock time from spawn to exit, in milliseconds.
+    pub wall_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_cpu_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_cpu_ms: Option<u64>,
+    /// Largest resident set size of the command or any descendant, in KiB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rss_kib: Option<u64>,
+}
+
+impl BuildResult {
+    /// "exit code N" or "killed by signal N".
+    pub fn describe_exit(&self) -> String {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("exit code {}", code),
+            (None, Some(signal)) => format!("killed by signal {}", signal),
+            (None, None) => "unknown exit status".to_string(),
+        }
+    }
+
+    /// One line for build and verify output, e.g.
+    /// "exit code 0, 12.3s wall, 9.8s CPU, 512.0 MiB peak RSS".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            self.describe_exit(),
+            format!("{:.1}s wall", self.wall_time_ms as f64 / 1000.0),
+        ];
+        if let (Some(user), Some(system)) = (self.user_cpu_ms, self.system_cpu_ms) {
+            parts.push(format!("{:.1}s CPU", (user + system) as f64 / 1000.0));
+        }
+        if let Some(rss) = self.max_rss_kib {
+            parts.push(format!("{:.1} MiB peak RSS", rss as f64 / 1024.0));
+        }
+        parts.join(", ")
+    }
+}
+
 // ── Enforcement ─────────────────────────────────────────────────────────────
 // Records what the build tool actually enforced at build time.
 // This is critical for honesty: if Mode A was requested but the tool
@@ -103,11 +462,123 @@ pub struct Enforcement {
     pub network_blocked: bool,
     /// Whether SOURCE_DATE_EPOCH was set in the build environment.
     pub source_date_epoch_set: bool,
+    /// Mode B: "host:port" destinations the build tried to reach outside
+    /// the policy's network allowlist. The proxy refused them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_violations: Option<Vec<String>>,
+    /// With --trace-inputs: files in the repository the build read that
+    /// git does not track, so the source hashes do not cover them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub untracked_inputs: Option<Vec<String>>,
     /// Human-readable explanation of enforcement gaps.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub notes: Option<String>,
 }
 
+// ── Network log ─────────────────────────────────────────────────────────────
+// Mode B builds run with HTTP(S)_PROXY pointed at a local recording proxy
+// that only lets allowlisted hosts through. Every attempted connection is
+// written to network.json, hashed in the manifest as network_log_hash.
+
+/// Bundle file holding the recording proxy's connection log.
+pub const NETWORK_LOG_FILE: &str = "network.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkLog {
+    /// Address the proxy listened on, e.g. "127.0.0.1:40713".
+    pub proxy: String,
+    /// The policy allowlist in effect. Empty means every host was allowed.
+    pub allowlist: Vec<String>,
+    pub connections: Vec<NetworkConnection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConnection {
+    /// When the request reached the proxy (RFC 3339).
+    pub time: String,
+    /// "CONNECT" for HTTPS tunnels, else the plain HTTP method.
+    pub method: String,
+    pub host: String,
+    pub port: u16,
+    /// Whether the proxy let the connection through.
+    pub allowed: bool,
+}
+
+// ── Network access trace ────────────────────────────────────────────────────
+// With --trace-network, the build's process tree runs under a syscall
+// tracer that records every outbound connection and DNS query, whether or
+// not the build honours the proxy variables. Written to
+// network-access.json, hashed in the manifest as network_access_hash.
+
+/// Bundle file holding the traced network access of the build.
+pub const NETWORK_ACCESS_FILE: &str = "network-access.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkAccessLog {
+    /// Distinct destinations of connect() and datagram sends, sorted.
+    pub connections: Vec<TracedConnection>,
+    /// Names looked up by DNS queries sent to port 53, sorted.
+    pub dns_queries: Vec<DnsQuery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedConnection {
+    /// IPv4 or IPv6 address, e.g. "140.82.112.3".
+    pub address: String,
+    pub port: u16,
+    /// Number of attempts, including failed ones.
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsQuery {
+    /// Queried name, lowercased.
+    pub name: String,
+    /// Number of queries (an A and an AAAA lookup count as two).
+    pub count: u64,
+}
+
+// ── Build inputs trace ──────────────────────────────────────────────────────
+// With --trace-inputs, the tracer records every file the build's process
+// tree opens for reading. Each is classified by where it lives, so files
+// the source hashes do not cover stand out. Written to inputs.json, hashed
+// in the manifest as inputs_hash.
+
+/// Bundle file holding the files the build read.
+pub const INPUTS_FILE: &str = "inputs.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InputsLog {
+    /// Distinct files read, sorted by path.
+    pub files: Vec<InputFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputFile {
+    /// Relative to the repository root for files in the repository,
+    /// otherwise absolute.
+    pub path: String,
+    pub class: InputClass,
+    /// Number of times the file was opened.
+    pub count: u64,
+    /// SHA-256 of untracked files, which nothing else in the bundle pins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputClass {
+    /// Tracked by git at the recorded commit (or git's own metadata).
+    TrackedInGit,
+    /// In the repository but not tracked: a provenance gap.
+    UntrackedInRepo,
+    /// Part of the system or of a detected tool's installation.
+    Toolchain,
+    /// Anywhere else (dependency caches, /etc, the home directory).
+    OutsideRepo,
+}
+
 // ── Environment ─────────────────────────────────────────────────────────────
 // Captures the build machine state: OS, tools, container info, and
 // reproducibility settings.
@@ -117,9 +588,19 @@ pub struct Environment {
     pub os: OsInfo,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub container: Option<ContainerInfo>,
+    /// The CI run that produced the build (see ci.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiContext>,
     pub tools: Vec<ToolInfo>,
+    /// Environment variables captured per the policy's
+    /// `requirements.environment`, secret values redacted or hashed.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub env: Option<serde_json::Value>,
+    pub env: Option<BTreeMap<String, String>>,
+    /// The complete environment of a clean build (policy
+    /// `environment.clean`), secret values redacted or hashed. Absent when
+    /// the build inherited vbw's environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_env: Option<BTreeMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub locale: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -138,23 +619,98 @@ pub struct OsInfo {
     pub arch: Option<String>,
 }
 
+/// The CI run that produced the build, from the provider's variables.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CiContext {
+    /// "github-actions", "gitlab-ci", "buildkite" or "jenkins".
+    pub provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_attempt: Option<String>,
+    /// Workflow, pipeline or job name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<String>,
+    /// Job or step within the workflow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    /// Triggering event, in the provider's terms ("push", "schedule").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Full git ref built: refs/heads/<branch> or refs/tags/<tag>.
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    /// Commit the CI system checked out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runner: Option<String>,
+    /// Hosted runner image (GitHub: ImageOS/ImageVersion).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runner_image: Option<String>,
+    /// Link to the run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// The container the build ran in (see container.rs).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ContainerInfo {
+    /// Runtime: "docker", "podman", "containerd", "cri-o", "lxc",
+    /// "systemd-nspawn", "oci" (a container of unknown runtime), or "none".
     #[serde(rename = "type")]
     pub container_type: String,
+    /// Full container id from runtime metadata, cgroup or mountinfo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// "kubernetes" when the container runs in a pod.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orchestrator: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<String>,
+    /// "sha256:<hex>" image digest, or "unknown".
     pub image_digest: String,
+    /// Where image_digest comes from: "oci-index" or "oci-manifest" when
+    /// computed from the file passed to --container-image, with "+runtime"
+    /// when the runtime's image id confirmed that file, "env" when claimed
+    /// through CONTAINER_IMAGE_DIGEST.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_digest_source: Option<String>,
+    /// Image id: digest of the image config ("sha256:<hex>").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_id: Option<String>,
+    /// What the detection was based on ("/.dockerenv", "cgroup", ...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolInfo {
     pub name: String,
     pub version: String,
+    /// The binary the tool resolves to (version manager shims followed).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
+    /// SHA-256 of the binary at `path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// How often the build executed this binary (vbw build --trace-exec).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub invocation: Option<String>,
+    pub invocation: Option<ToolInvocation>,
+    /// Detector-specific facts: version manager, shim, rustup toolchain
+    /// and components, JAVA_HOME (see tools.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    /// Successful execs of the binary during the build.
+    pub count: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -227,6 +783,18 @@ pub struct PolicyRequirements {
     pub materials: MaterialsRequirement,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signing: Option<SigningRequirement>,
+    /// Environment variables to record in environment.json.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentRequirement>,
+    /// Which tools environment.json records (see tools.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<ToolchainRequirement>,
+    /// Which build containers verify accepts (see container.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerRequirement>,
+    /// Which CI runs verify accepts (see ci.rs).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiRequirement>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -259,14 +827,182 @@ pub struct SigningRequirement {
     /// against the matching key_id in this list.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub trusted_cosigner_keys: Option<Vec<TrustedCosignerKey>>,
+    /// Statement types (e.g. "approved-for-release") that must each be
+    /// covered by at least one valid co-signature from a trusted key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_statements: Option<Vec<String>>,
+    /// RFC 3161 timestamping of the builder signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<TimestampRequirement>,
+    /// Builder keys allowed to sign bundles, with validity periods and
+    /// revocation times. Absent means any builder key is accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_builder_keys: Option<Vec<TrustedBuilderKey>>,
+    /// Fail verification unless the builder key is bound to an OIDC token
+    /// whose signature was checked against the issuer's keys (--oidc-jwks).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_verified_oidc: Option<bool>,
+}
+
+/// Which environment variables the build records, and how secret-looking
+/// values are hidden (see envvars.rs).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EnvironmentRequirement {
+    /// Variables to record: exact names or `*` wildcards ("CARGO_*").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<Vec<String>>,
+    /// Extra name patterns (`*` wildcards, case-insensitive) whose values
+    /// are always treated as secret.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_patterns: Option<Vec<String>>,
+    /// Record secret values as "sha256:<hex>" instead of "[REDACTED]", so
+    /// bundles can be compared without revealing the value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash_secrets: Option<bool>,
+    /// Launch the build with a cleared environment, passing through only
+    /// the `passthrough` variables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clean: Option<bool>,
+    /// Variables a clean build inherits: exact names or `*` wildcards.
+    /// Defaults to PATH, HOME, USER, LOGNAME, LANG, LC_*, TERM, TZ, TMPDIR.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passthrough: Option<Vec<String>>,
+}
+
+/// Which tool detectors run, plus project-specific tools to record.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ToolchainRequirement {
+    /// Built-in detectors to run, by name ("rustc", "node", "gradle").
+    /// Defaults to all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detectors: Option<Vec<String>>,
+    /// Additional tools to detect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom: Option<Vec<CustomTool>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomTool {
+    pub name: String,
+    /// Executable name looked up on PATH, or a path.
+    pub command: String,
+    /// Arguments that print the version (default: --version).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_args: Option<Vec<String>>,
+}
+
+/// Accepted CI context values, `*` wildcards allowed. Each listed field
+/// must be recorded and match one of its patterns.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CiRequirement {
+    /// Require a CI context even when no field is constrained.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub providers: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repositories: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflows: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actors: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContainerRequirement {
+    /// Image digests ("sha256:<hex>") the build container must have, as
+    /// computed from the OCI file given to `vbw build --container-image`
+    /// and confirmed by the runtime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_image_digests: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimestampRequirement {
+    /// TSA endpoint used by build (overridden by --tsa-url).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tsa_url: Option<String>,
+    /// Fail verification unless the bundle carries a timestamp token from
+    /// a trusted TSA.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    /// Hex SHA-256 of the DER TSA signing certificates whose time is
+    /// trusted. Tokens from other TSAs are rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_tsa_cert_sha256: Option<Vec<String>>,
+}
+
+// KEY VALIDITY: validity and revocation are checked at the build time. That
+// is the TSA's genTime when the bundle carries a trusted timestamp token,
+// otherwise the self-reported manifest created_at. Because anyone holding a
+// revoked key can backdate created_at, a key with `revoked_at` is only
+// accepted with a trusted timestamp proving it signed before revocation.
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrustedBuilderKey {
+    /// Human-readable identifier, for messages only.
+    pub key_id: String,
+    /// Signature algorithm of the key. Absent means Ed25519.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<Algorithm>,
+    /// Base64-encoded Ed25519 public key. Empty for non-Ed25519 keys.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub public_key_ed25519: String,
+    /// Base64-encoded public key for non-Ed25519 algorithms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// RFC 3339 time before which the key must not have been used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<String>,
+    /// RFC 3339 time after which the key must not have been used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+    /// RFC 3339 time the key was revoked (e.g. after a compromise).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+impl TrustedBuilderKey {
+    /// The key's algorithm (Ed25519 when `key_type` is absent).
+    pub fn algorithm(&self) -> Algorithm {
+        self.key_type.unwrap_or(Algorithm::Ed25519)
+    }
+
+    /// The base64 public key for `algorithm()`.
+    pub fn public_key_b64(&self) -> &str {
+        select_public_key(self.algorithm(), &self.public_key_ed25519, &self.public_key)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrustedCosignerKey {
     /// Human-readable identifier matching the co-signature filename.
     pub key_id: String,
-    /// Base64-encoded Ed25519 public key.
+    /// Signature algorithm of the key. Absent means Ed25519.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<Algorithm>,
+    /// Base64-encoded Ed25519 public key. Empty for non-Ed25519 keys.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub public_key_ed25519: String,
+    /// Base64-encoded public key for non-Ed25519 algorithms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl TrustedCosignerKey {
+    /// The key's algorithm (Ed25519 when `key_type` is absent).
+    pub fn algorithm(&self) -> Algorithm {
+        self.key_type.unwrap_or(Algorithm::Ed25519)
+    }
+
+    /// The base64 public key for `algorithm()`.
+    pub fn public_key_b64(&self) -> &str {
+        select_public_key(self.algorithm(), &self.public_key_ed25519, &self.public_key)
+    }
 }
 
 impl Policy {
@@ -288,15 +1024,75 @@ impl Policy {
                     require_lockfile_hashes: true,
                     require_vendor_archive_and_tree: Some(false),
                 },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
                 signing: Some(SigningRequirement {
                     require_maintainer_cosign_for_release: Some(false),
                     trusted_cosigner_keys: None,
+                    required_statements: None,
+                    timestamp: None,
+                    trusted_builder_keys: None,
+                    require_verified_oidc: None,
                 }),
             },
         }
     }
 }
 
+// ── Cosignature ─────────────────────────────────────────────────────────────
+// A structured co-signature statement written by `attest` to
+// signatures/<key_id>.<statement>.cosig.json. Unlike a bare signature file, it records
+// who signed, when, and what they are vouching for.
+//
+// SIGNING: The signature covers cosignature_signing_bytes() — the canonical
+// manifest bytes followed by the canonical JSON of every field below except
+// `signature`. See canonical.rs.
+
+/// Statement types with a defined meaning. Other values are accepted for
+/// forward-compatibility but carry no meaning beyond what policy assigns.
+pub const KNOWN_STATEMENTS: &[&str] = &["reviewed", "approved-for-release", "deployed-to-prod"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cosignature {
+    pub cosignature_version: String,
+    /// Human-readable identifier of the co-signer (e.g. "maintainer@org").
+    pub key_id: String,
+    /// Signature algorithm of the co-signer key. Absent means Ed25519.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<Algorithm>,
+    /// Base64-encoded Ed25519 public key of the co-signer.
+    /// Empty for non-Ed25519 keys.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub public_key_ed25519: String,
+    /// Base64-encoded public key for non-Ed25519 algorithms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// RFC 3339 timestamp of when the statement was signed (self-reported).
+    pub signed_at: String,
+    /// What the co-signer attests to, e.g. "reviewed" or "approved-for-release".
+    pub statement: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// SHA-256 of the canonical manifest bytes this statement covers.
+    pub manifest_hash: String,
+    /// Base64-encoded signature over cosignature_signing_bytes().
+    pub signature: String,
+}
+
+impl Cosignature {
+    /// The co-signer key's algorithm (Ed25519 when `key_type` is absent).
+    pub fn algorithm(&self) -> Algorithm {
+        self.key_type.unwrap_or(Algorithm::Ed25519)
+    }
+
+    /// The base64 public key for `algorithm()`.
+    pub fn public_key_b64(&self) -> &str {
+        select_public_key(self.algorithm(), &self.public_key_ed25519, &self.public_key)
+    }
+}
+
 // ── Materials Lock ──────────────────────────────────────────────────────────
 // Records which lockfiles were present and their hashes.
 //
@@ -316,15 +1112,28 @@ pub struct LockfileEntry {
     pub sha256: String,
 }
 
+/// Material kind for an artifact taken from another project's VBW bundle.
+/// See upstream.rs.
+pub const VBW_ARTIFACT_KIND: &str = "vbw-artifact";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MaterialEntry {
     pub name: String,
-    /// One of: "npm", "git", "tarball", "file" (per schema).
-    /// Currently only "npm" and "file" are used by auto-detection.
+    /// One of: "npm", "git", "tarball", "file", "step-output",
+    /// "vbw-artifact" (per schema). Currently only "npm" and "file" are
+    /// used by auto-detection; "step-output" marks an input produced by an
+    /// earlier pipeline step (named in `source`), "vbw-artifact" an
+    /// artifact of another project's VBW bundle (project in `source`).
     pub kind: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
     pub sha256: String,
+    /// vbw-artifact only: canonical manifest hash of the upstream bundle.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_manifest_hash: Option<String>,
+    /// vbw-artifact only: the artifact's path in the upstream outputs.json.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_path: Option<String>,
     /// SHA-256 of vendor archive as-downloaded. TODO: Not yet populated.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub archive_sha256: Option<String>,
@@ -0,0 +1,491 @@
+// proxy.rs — Mode B network allowlist: a local recording HTTP(S) proxy
+//
+// Mode B builds run with HTTP_PROXY / HTTPS_PROXY (and the lowercase
+// variants) pointed at a proxy on 127.0.0.1. For each request it:
+//
+//   - parses the destination: the CONNECT target for HTTPS tunnels, or the
+//     absolute-form URI of a plain HTTP request
+//   - checks the host against the policy's network allowlist
+//   - logs the attempt (time, method, host, port, allowed)
+//   - relays the connection if allowed, or answers 403 if not
+//
+// ALLOWLIST: entries are host names ("registry.npmjs.org"), wildcard
+// suffixes ("*.crates.io" matches subdomains, not crates.io itself), or
+// either with a port ("example.com:8443"). Without a port any port
+// matches. Matching is case-insensitive. An empty allowlist allows every
+// host (the proxy only records); `network.allowed = false` allows none.
+//
+// HONESTY: the proxy only sees clients that honour the proxy variables.
+// A build that opens sockets directly is not stopped (Mode A's network
+// namespace is the hard block); the log records what went through the
+// proxy.
+
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::vbw::model::{NetworkConnection, NetworkLog};
+
+/// Maximum size of a request head (request line + headers).
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// How long to wait for an upstream server to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Clients served at once; further connections wait in the listen queue.
+const MAX_CONNECTIONS: usize = 128;
+
+/// How long `finish` waits for connections still being handled.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which destinations the proxy lets through.
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    allow_all: bool,
+    entries: Vec<String>,
+}
+
+impl Allowlist {
+    /// Build from the policy's network requirement.
+    pub fn from_policy(allowed: bool, entries: &[String]) -> Self {
+        Allowlist {
+            allow_all: allowed && entries.is_empty(),
+            entries: if allowed {
+                entries.iter().map(|e| e.to_ascii_lowercase()).collect()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    pub fn permits(&self, host: &str, port: u16) -> bool {
+        if self.allow_all {
+            return true;
+        }
+        let host = host.to_ascii_lowercase();
+        self.entries.iter().any(|entry| {
+            let (pattern, entry_port) = match entry.rsplit_once(':') {
+                Some((h, p)) => match p.parse::<u16>() {
+                    Ok(p) => (h, Some(p)),
+                    Err(_) => (entry.as_str(), None),
+                },
+                None => (entry.as_str(), None),
+            };
+            if entry_port.is_some_and(|p| p != port) {
+                return false;
+            }
+            match pattern.strip_prefix("*.") {
+                Some(suffix) => host
+                    .strip_suffix(suffix)
+                    .is_some_and(|rest| rest.len() > 1 && rest.ends_with('.')),
+                None => host == pattern,
+            }
+        })
+    }
+}
+
+/// A running recording proxy. Call `finish()` once the build is done.
+pub struct RecordingProxy {
+    addr: String,
+    allowlist_entries: Vec<String>,
+    log: Arc<Mutex<Vec<NetworkConnection>>>,
+    stop: Arc<AtomicBool>,
+    /// Returns the handler threads still running when it stops.
+    acceptor: Option<thread::JoinHandle<Vec<thread::JoinHandle<()>>>>,
+}
+
+impl RecordingProxy {
+    /// Start the proxy on an ephemeral port on 127.0.0.1.
+    pub fn start(allowed: bool, allowlist: &[String]) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").context("binding the network proxy")?;
+        let addr = listener.local_addr()?.to_string();
+        let rules = Arc::new(Allowlist::from_policy(allowed, allowlist));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let (log, stop) = (log.clone(), stop.clone());
+            thread::spawn(move || {
+                let mut handlers: Vec<thread::JoinHandle<()>> = Vec::new();
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    handlers.retain(|h| !h.is_finished());
+                    while handlers.len() >= MAX_CONNECTIONS && !stop.load(Ordering::SeqCst) {
+                        thread::sleep(Duration::from_millis(10));
+                        handlers.retain(|h| !h.is_finished());
+                    }
+                    let (rules, log) = (rules.clone(), log.clone());
+                    handlers.push(thread::spawn(move || {
+                        let _ = handle_client(stream, &rules, &log);
+                    }));
+                }
+                handlers
+            })
+        };
+
+        Ok(RecordingProxy {
+            addr,
+            allowlist_entries: allowlist.to_vec(),
+            log,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Environment variables that route a build's HTTP(S) traffic through
+    /// the proxy.
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let url = format!("http://{}", self.addr);
+        let mut env: Vec<(&'static str, String)> =
+            ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"]
+                .into_iter()
+                .map(|k| (k, url.clone()))
+                .collect();
+        // A NO_PROXY inherited from the host would let hosts bypass the log
+        env.push(("NO_PROXY", String::new()));
+        env.push(("no_proxy", String::new()));
+        env
+    }
+
+    /// Stop accepting connections and return the log, once the
+    /// connections in progress are done (or DRAIN_TIMEOUT has passed).
+    pub fn finish(mut self) -> NetworkLog {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the acceptor so it sees the stop flag
+        let _ = TcpStream::connect(&self.addr);
+        let handlers = match self.acceptor.take() {
+            Some(acceptor) => acceptor.join().unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let deadline = std::time::Instant::now() + DRAIN_TIMEOUT;
+        while handlers.iter().any(|h| !h.is_finished()) && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let (finished, running): (Vec<_>, Vec<_>) =
+            handlers.into_iter().partition(|h| h.is_finished());
+        for handler in finished {
+            let _ = handler.join();
+        }
+        if !running.is_empty() {
+            eprintln!(
+                "[vbw] WARNING: {} proxy connection(s) still open after the build \
+                 are no longer waited for",
+                running.len()
+            );
+        }
+        let connections = self.log.lock().map(|l| l.clone()).unwrap_or_default();
+        NetworkLog {
+            proxy: self.addr.clone(),
+            allowlist: self.allowlist_entries.clone(),
+            connections,
+        }
+    }
+}
+
+/// Handle one proxy client: parse the request head, check and log the
+/// destination, then tunnel or forward it.
+fn handle_client(
+    client: TcpStream,
+    rules: &Allowlist,
+    log: &Mutex<Vec<NetworkConnection>>,
+) -> Result<()> {
+    client.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut reader = BufReader::new(client.try_clone()?);
+    let head = read_head(&mut reader)?;
+    let mut client_out = client;
+
+    let request_line = head.lines().next().unwrap_or("");
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) => (m.to_string(), t.to_string()),
+        _ => return respond(&mut client_out, "400 Bad Request", "malformed request"),
+    };
+
+    let destination = if method.eq_ignore_ascii_case("CONNECT") {
+        parse_authority(&target, 443)
+    } else {
+        target
+            .strip_prefix("http://")
+            .map(|rest| rest.split('/').next().unwrap_or(""))
+            .and_then(|authority| parse_authority(authority, 80))
+    };
+    let Some((host, port)) = destination else {
+        return respond(
+            &mut client_out,
+            "400 Bad Request",
+            "vbw proxy: expected CONNECT host:port or an absolute http:// URI",
+        );
+    };
+
+    let allowed = rules.permits(&host, port);
+    if let Ok(mut log) = log.lock() {
+        log.push(NetworkConnection {
+            time: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            method: method.clone(),
+            host: host.clone(),
+            port,
+            allowed,
+        });
+    }
+    if !allowed {
+        return respond(
+            &mut client_out,
+            "403 Forbidden",
+            &format!(
+                "vbw proxy: {}:{} is not in the network allowlist",
+                host, port
+            ),
+        );
+    }
+
+    let Some(addr) = (host.as_str(), port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut a| a.next())
+    else {
+        return respond(
+            &mut client_out,
+            "502 Bad Gateway",
+            "vbw proxy: cannot resolve host",
+        );
+    };
+    let mut upstream = match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+        Ok(s) => s,
+        Err(e) => {
+            return respond(
+                &mut client_out,
+                "502 Bad Gateway",
+                &format!("vbw proxy: {}", e),
+            )
+        }
+    };
+    client_out.set_read_timeout(None)?;
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        client_out.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")?;
+    } else {
+        // Forward in origin-form; the upstream connection is fixed to this
+        // host, so a reused client connection cannot reach another one.
+        let path = target
+            .strip_prefix("http://")
+            .and_then(|rest| rest.find('/').map(|i| &rest[i..]))
+            .unwrap_or("/");
+        let rest_of_line = request_line.split_whitespace().nth(2).unwrap_or("HTTP/1.1");
+        let mut forwarded = format!("{} {} {}\r\n", method, path, rest_of_line);
+        for line in head.lines().skip(1) {
+            if line.is_empty() {
+                break;
+            }
+            if !line.to_ascii_lowercase().starts_with("proxy-") {
+                forwarded.push_str(line);
+                forwarded.push_str("\r\n");
+            }
+        }
+        forwarded.push_str("\r\n");
+        upstream.write_all(forwarded.as_bytes())?;
+    }
+
+    // Bytes the client sent after the head belong to the tunnel
+    let buffered = reader.buffer().to_vec();
+    upstream.write_all(&buffered)?;
+    relay(reader.into_inner(), client_out, upstream)
+}
+
+/// Read the request head up to the blank line, leaving any body in the
+/// reader's buffer.
+fn read_head(reader: &mut BufReader<TcpStream>) -> Result<String> {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("client closed the connection");
+        }
+        head.push_str(&line);
+        if head.len() > MAX_HEAD_BYTES {
+            anyhow::bail!("request head too large");
+        }
+        if line == "\r\n" || line == "\n" {
+            return Ok(head);
+        }
+    }
+}
+
+/// Split "host:port" (or "[v6]:port"), using `default_port` if none.
+fn parse_authority(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, after) = rest.split_once(']')?;
+        let port = match after.strip_prefix(':') {
+            Some(p) => p.parse().ok()?,
+            None => default_port,
+        };
+        (host, port)
+    } else {
+        match authority.rsplit_once(':') {
+            Some((h, p)) => (h, p.parse().ok()?),
+            None => (authority, default_port),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_ascii_lowercase(), port))
+}
+
+fn respond(client: &mut TcpStream, status: &str, message: &str) -> Result<()> {
+    let body = format!("{}\n", message);
+    write!(
+        client,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Copy bytes both ways until either side closes.
+fn relay(client_in: TcpStream, mut client_out: TcpStream, upstream: TcpStream) -> Result<()> {
+    let mut upstream_in = upstream.try_clone()?;
+    let mut upstream_out = upstream;
+    let mut client_in = client_in;
+    let to_upstream = thread::spawn(move || {
+        let _ = io::copy(&mut client_in, &mut upstream_out);
+        let _ = upstream_out.shutdown(Shutdown::Write);
+    });
+    let _ = copy_until_closed(&mut upstream_in, &mut client_out);
+    let _ = client_out.shutdown(Shutdown::Write);
+    let _ = to_upstream.join();
+    Ok(())
+}
+
+fn copy_until_closed(from: &mut TcpStream, to: &mut TcpStream) -> io::Result<u64> {
+    let mut buf = [0u8; 16 * 1024];
+    let mut total = 0;
+    loop {
+        let n = from.read(&mut buf)?;
+        if n == 0 {
+            return Ok(total);
+        }
+        to.write_all(&buf[..n])?;
+        total += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowlist_matches_hosts_wildcards_and_ports() {
+        let rules = Allowlist::from_policy(
+            true,
+            &[
+                "registry.npmjs.org".to_string(),
+                "*.crates.io".to_string(),
+                "Example.com:8443".to_string(),
+            ],
+        );
+        assert!(rules.permits("registry.npmjs.org", 443));
+        assert!(rules.permits("REGISTRY.npmjs.org", 80));
+        assert!(rules.permits("static.crates.io", 443));
+        assert!(!rules.permits("crates.io", 443));
+        assert!(!rules.permits("evilcrates.io", 443));
+        assert!(rules.permits("example.com", 8443));
+        assert!(!rules.permits("example.com", 443));
+        assert!(!rules.permits("npmjs.org", 443));
+
+        assert!(Allowlist::from_policy(true, &[]).permits("anything.test", 1));
+        assert!(!Allowlist::from_policy(false, &[]).permits("anything.test", 1));
+        assert!(!Allowlist::from_policy(false, &["a.test".to_string()]).permits("a.test", 1));
+    }
+
+    #[test]
+    fn proxy_tunnels_allowed_hosts_and_logs_refusals() {
+        // A local "upstream" server that echoes one line back
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (stream, _) = upstream.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(stream.try_clone().unwrap())
+                .read_line(&mut line)
+                .unwrap();
+            (&stream).write_all(line.as_bytes()).unwrap();
+        });
+
+        let proxy = RecordingProxy::start(true, &["localhost".to_string()]).unwrap();
+        let proxy_addr = proxy.addr.clone();
+        let connect = |target: &str| {
+            let mut stream = TcpStream::connect(&proxy_addr).unwrap();
+            write!(
+                stream,
+                "CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n",
+                target, target
+            )
+            .unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut status = String::new();
+            reader.read_line(&mut status).unwrap();
+            (stream, reader, status)
+        };
+
+        let (mut stream, mut reader, status) = connect(&format!("localhost:{}", upstream_port));
+        assert!(status.contains("200"), "{}", status);
+        let mut blank = String::new();
+        reader.read_line(&mut blank).unwrap();
+        stream.write_all(b"ping\n").unwrap();
+        let mut echoed = String::new();
+        reader.read_line(&mut echoed).unwrap();
+        assert_eq!(echoed, "ping\n");
+
+        let (_, _, status) = connect("example.com:443");
+        assert!(status.contains("403"), "{}", status);
+
+        // The build is done: its connections are closed
+        drop((stream, reader));
+        let log = proxy.finish();
+        assert_eq!(log.allowlist, vec!["localhost"]);
+        let summary: Vec<(String, bool)> = log
+            .connections
+            .iter()
+            .map(|c| (format!("{}:{}", c.host, c.port), c.allowed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (format!("localhost:{}", upstream_port), true),
+                ("example.com:443".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_waits_for_connections_in_progress() {
+        let proxy = RecordingProxy::start(true, &["localhost".to_string()]).unwrap();
+        let mut stream = TcpStream::connect(&proxy.addr).unwrap();
+        // The request arrives only after the build is done
+        let client = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            stream
+                .write_all(b"CONNECT late.example:443 HTTP/1.1\r\n\r\n")
+                .unwrap();
+            let mut status = String::new();
+            BufReader::new(stream).read_line(&mut status).unwrap();
+            status
+        });
+        thread::sleep(Duration::from_millis(50));
+
+        let log = proxy.finish();
+        assert!(client.join().unwrap().contains("403"));
+        assert_eq!(log.connections.len(), 1);
+        assert_eq!(log.connections[0].host, "late.example");
+    }
+}
@@ -0,0 +1,463 @@
+// sandbox.rs — Mode A build sandbox (Linux namespaces)
+//
+// Mode A builds run inside a sandbox assembled with unshare(1):
+//
+//   - network:          new network namespace (loopback only)
+//   - mount-namespace:  private mount namespace with a fresh tmpfs root
+//   - readonly-system:  /usr, /bin, /lib*, /opt, ... bound read-only
+//   - readonly-source:  the source tree bound read-only at its own path
+//   - writable-output:  the output directory bound read-write on top
+//   - private-tmp:      an empty /tmp (and TMPDIR) owned by the build
+//   - minimal-etc:      /etc holds only identity, resolver config, the
+//                       linker cache and alternatives — no credentials
+//   - no-home:          the user's home is not mounted; HOME is an empty
+//                       directory under the private /tmp
+//
+// The mounts are set up by a shell script running as root in a new user
+// namespace; the build itself then runs in a nested user + mount namespace
+// (unshare --root) as an unprivileged user, so it cannot undo the
+// read-only mounts. Every layer that fails is reported, and the build
+// still runs with whatever layers succeeded; compute_enforcement() records
+// them in the manifest and only counts Mode A as enforced when all hold.
+//
+// If mount namespaces are unavailable, the build falls back to network
+// isolation alone (`unshare --user --net`).
+//
+// Either way the unshare that runs the build is exec'd through a link to
+// it named trace::START_MARKER, so that a traced build records only what
+// runs after the sandbox setup (see trace.rs).
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::vbw::trace;
+
+/// Every isolation layer the Mode A sandbox attempts, in setup order.
+pub const LAYERS: &[&str] = &[
+    "network",
+    "mount-namespace",
+    "readonly-system",
+    "readonly-source",
+    "writable-output",
+    "private-tmp",
+    "minimal-etc",
+    "no-home",
+];
+
+/// Mount setup, run as root of a new user namespace:
+///   sh -c SETUP sh <root> <status> <start> <source> <output> <build command...>
+/// Writes the layers that succeeded to <status>, then execs the build
+/// through <start>, the START_MARKER link to unshare.
+/// On a fatal mount error it runs the build in place (network isolation
+/// only) rather than not at all.
+const SETUP_SCRIPT: &str = r#"
+set -u
+root=$1 status=$2 start=$3 src=$4 out=$5
+shift 5
+ok="network"
+report() { printf '%s\n' $ok > "$status"; }
+fallback() { report; exec "$start" -- "$@"; }
+
+mount -t tmpfs -o mode=0755 vbw-sandbox "$root" 2>/dev/null || fallback "$@"
+ok="$ok mount-namespace"
+
+sys_ro=1
+for d in usr bin sbin lib lib32 lib64 libx32 opt; do
+    if [ -L "/$d" ]; then
+        ln -s "$(readlink "/$d")" "$root/$d"
+    elif [ -d "/$d" ]; then
+        mkdir "$root/$d"
+        mount --rbind "/$d" "$root/$d" || fallback "$@"
+        mount -o remount,bind,ro "$root/$d" 2>/dev/null || sys_ro=0
+    fi
+done
+mkdir "$root/dev" "$root/proc" "$root/tmp" "$root/etc"
+mount --rbind /dev "$root/dev" || fallback "$@"
+mount -t proc proc "$root/proc" 2>/dev/null || mount --rbind /proc "$root/proc"
+
+mkdir -p "$root$src"
+mount --bind "$src" "$root$src" || fallback "$@"
+[ "$sys_ro" = 1 ] && ok="$ok readonly-system"
+# An output directory that is the source tree itself makes it writable again
+mount -o remount,bind,ro "$root$src" 2>/dev/null && [ "$out" != "$src" ] &&
+    ok="$ok readonly-source"
+mkdir -p "$root$out" 2>/dev/null
+mount --bind "$out" "$root$out" 2>/dev/null && ok="$ok writable-output"
+
+chmod 1777 "$root/tmp" && mkdir -m 0700 "$root/tmp/home" && ok="$ok private-tmp"
+etc_ok=1
+for f in passwd group hosts nsswitch.conf ld.so.cache ld.so.conf localtime alternatives; do
+    if [ -e "/etc/$f" ] || [ -L "/etc/$f" ]; then
+        cp -a "/etc/$f" "$root/etc/" || etc_ok=0
+    fi
+done
+[ "$etc_ok" = 1 ] && ok="$ok minimal-etc"
+# The home directory stays hidden unless it lies inside the source tree
+case "${HOME:-/nonexistent}/" in
+    "$src"/*) ;;
+    *) ok="$ok no-home" ;;
+esac
+
+report
+export HOME=/tmp/home TMPDIR=/tmp
+exec "$start" --user --mount --root "$root" --wd "$src" -- "$@"
+"#;
+
+/// Which sandbox layers were in effect for a build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxReport {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl SandboxReport {
+    fn from_layers(succeeded: &[&str]) -> Self {
+        SandboxReport {
+            succeeded: LAYERS
+                .iter()
+                .filter(|l| succeeded.contains(l))
+                .map(|l| l.to_string())
+                .collect(),
+            failed: LAYERS
+                .iter()
+                .filter(|l| !succeeded.contains(l))
+                .map(|l| l.to_string())
+                .collect(),
+        }
+    }
+
+    /// True if every layer of the sandbox was in effect.
+    pub fn complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A sandboxed build command. Run `command` (traced with
+/// `from_start_marker`), then call `report()`.
+pub struct Sandbox {
+    pub command: Command,
+    /// Temporary directory holding the START_MARKER link and, unless only
+    /// network isolation is available, the sandbox root mount point and
+    /// the setup script's status file.
+    state_dir: PathBuf,
+    network_only: bool,
+}
+
+impl Sandbox {
+    /// Which layers were in effect. Call after the build has run.
+    pub fn report(self) -> SandboxReport {
+        if self.network_only {
+            return SandboxReport::from_layers(&["network"]);
+        }
+        let layers = fs::read_to_string(self.state_dir.join("layers")).unwrap_or_default();
+        SandboxReport::from_layers(&layers.split_whitespace().collect::<Vec<_>>())
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.state_dir);
+    }
+}
+
+/// Prepare `build_cmd` to run in the Mode A sandbox, with `source_dir` as
+/// the read-only source tree (and working directory) and `output_dir` as
+/// the only writable path in it.
+///
+/// Fails if not even network isolation is available (not Linux, user
+/// namespaces disabled, or no unshare binary).
+pub fn sandboxed_command(
+    build_cmd: &[String],
+    source_dir: &Path,
+    output_dir: &Path,
+) -> Result<Sandbox> {
+    if build_cmd.is_empty() {
+        anyhow::bail!("No build command specified");
+    }
+
+    let namespaces = [
+        "--user",
+        "--map-root-user",
+        "--mount",
+        "--net",
+        "--pid",
+        "--fork",
+    ];
+    if !probe(&namespaces) {
+        if !probe(&["--user", "--net"]) {
+            anyhow::bail!("unshare -rn not available or user namespaces disabled");
+        }
+        eprintln!("[vbw] Mode A: mount namespaces unavailable; network isolation only");
+        let (state_dir, start) = state_dir()?;
+        let mut command = Command::new(start);
+        command
+            .args(["--user", "--net", "--"])
+            .args(build_cmd)
+            .current_dir(source_dir);
+        return Ok(Sandbox {
+            command,
+            state_dir,
+            network_only: true,
+        });
+    }
+
+    let source = source_dir
+        .canonicalize()
+        .with_context(|| format!("resolving source directory {}", source_dir.display()))?;
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("creating output directory {}", output_dir.display()))?;
+    let output = output_dir
+        .canonicalize()
+        .with_context(|| format!("resolving output directory {}", output_dir.display()))?;
+
+    let (state_dir, start) = state_dir()?;
+    let root = state_dir.join("root");
+    fs::create_dir(&root).with_context(|| format!("creating {}", root.display()))?;
+
+    let mut command = Command::new("unshare");
+    command
+        .args(namespaces)
+        .args(["--", "sh", "-c", SETUP_SCRIPT, "sh"])
+        .arg(&root)
+        .arg(state_dir.join("layers"))
+        .arg(&start)
+        .arg(&source)
+        .arg(&output)
+        .args(build_cmd)
+        .current_dir(&source);
+    Ok(Sandbox {
+        command,
+        state_dir,
+        network_only: false,
+    })
+}
+
+/// Create the sandbox's temporary directory, with a START_MARKER link to
+/// the unshare binary in it. Returns both paths.
+fn state_dir() -> Result<(PathBuf, PathBuf)> {
+    let unshare = std::env::var_os("PATH")
+        .and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join("unshare"))
+                .find(|candidate| candidate.is_file())
+        })
+        .context("unshare not found on PATH")?;
+    let unshare = unshare
+        .canonicalize()
+        .with_context(|| format!("resolving {}", unshare.display()))?;
+    let state_dir = std::env::temp_dir().join(format!("vbw-sandbox-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&state_dir).with_context(|| format!("creating {}", state_dir.display()))?;
+    let start = state_dir.join(trace::START_MARKER);
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&unshare, &start);
+    #[cfg(not(unix))]
+    let linked = Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+    linked.with_context(|| format!("creating {}", start.display()))?;
+    Ok((state_dir, start))
+}
+
+/// True if unshare can create the given namespaces here.
+fn probe(args: &[&str]) -> bool {
+    Command::new("unshare")
+        .args(args)
+        .args(["--", "true"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_missing_layers_as_failed() {
+        let report = SandboxReport::from_layers(&["network", "private-tmp", "bogus"]);
+        assert_eq!(report.succeeded, vec!["network", "private-tmp"]);
+        assert!(report.failed.contains(&"readonly-source".to_string()));
+        assert_eq!(report.failed.len(), LAYERS.len() - 2);
+        assert!(!report.complete());
+    }
+
+    #[test]
+    fn sandbox_confines_writes_to_output_dir() {
+        if !probe(&[
+            "--user",
+            "--map-root-user",
+            "--mount",
+            "--net",
+            "--pid",
+            "--fork",
+        ]) {
+            eprintln!("skipping: mount namespaces unavailable");
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("dist")).unwrap();
+        fs::write(source.join("input.txt"), "source").unwrap();
+
+        let script = "cat input.txt > dist/copy; echo $HOME > dist/home; ls /etc > dist/etc; \
+                      touch /tmp/scratch; echo tampered > input.txt";
+        let build_cmd: Vec<String> = ["sh", "-c", script].iter().map(|s| s.to_string()).collect();
+        let mut sandbox = sandboxed_command(&build_cmd, &source, &source.join("dist")).unwrap();
+        let status = sandbox.command.status().unwrap();
+        assert!(!status.success(), "writing to the source tree must fail");
+        let report = sandbox.report();
+
+        assert_eq!(
+            fs::read_to_string(source.join("input.txt")).unwrap(),
+            "source"
+        );
+        assert_eq!(
+            fs::read_to_string(source.join("dist/copy")).unwrap(),
+            "source"
+        );
+        assert_eq!(
+            fs::read_to_string(source.join("dist/home")).unwrap(),
+            "/tmp/home\n"
+        );
+        assert!(!fs::read_to_string(source.join("dist/etc"))
+            .unwrap()
+            .contains("shadow"));
+        for layer in [
+            "network",
+            "mount-namespace",
+            "readonly-source",
+            "writable-output",
+        ] {
+            assert!(report.succeeded.iter().any(|l| l == layer), "{:?}", report);
+        }
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn traced_sandbox_records_only_the_build() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("dist")).unwrap();
+        fs::write(source.join("input.txt"), "source").unwrap();
+        let build_cmd: Vec<String> = ["sh", "-c", "cat input.txt > dist/copy"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let Ok(mut sandbox) = sandboxed_command(&build_cmd, &source, &source.join("dist")) else {
+            eprintln!("skipping: user namespaces unavailable");
+            return;
+        };
+        sandbox
+            .command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let traced = trace::run_traced(
+            &mut sandbox.command,
+            trace::TraceOptions {
+                exec: true,
+                inputs: true,
+                from_start_marker: true,
+                ..Default::default()
+            },
+            |mut stdout, mut stderr| {
+                stdout.read_to_end(&mut Vec::new()).unwrap();
+                stderr.read_to_end(&mut Vec::new()).unwrap();
+            },
+        );
+        let ((), status, report) = match traced {
+            Ok(traced) => traced,
+            Err(e) => {
+                eprintln!("skipping: ptrace unavailable: {:#}", e);
+                return;
+            }
+        };
+        assert!(status.success());
+
+        let executables: Vec<String> = report
+            .executables
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert!(
+            executables.iter().any(|p| p.ends_with("/cat")),
+            "{:?}",
+            executables
+        );
+        for setup in [
+            "unshare",
+            "mount",
+            "cp",
+            "mkdir",
+            "env",
+            trace::START_MARKER,
+        ] {
+            assert!(
+                !executables
+                    .iter()
+                    .any(|p| p.ends_with(&format!("/{}", setup))),
+                "{} in {:?}",
+                setup,
+                executables
+            );
+        }
+        let files_read = report.files_read.unwrap();
+        let input = source.join("input.txt").canonicalize().unwrap();
+        assert!(
+            files_read.contains_key(&input.display().to_string()),
+            "{:?}",
+            files_read
+        );
+        assert!(
+            !files_read
+                .keys()
+                .any(|p| p == "/etc/fstab" || p.contains("libmount")),
+            "{:?}",
+            files_read
+        );
+    }
+
+    #[test]
+    fn failed_etc_copy_is_reported() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join("dist")).unwrap();
+        // A cp that always fails, ahead of the real one on PATH
+        let shims = dir.path().join("shims");
+        fs::create_dir(&shims).unwrap();
+        fs::write(shims.join("cp"), "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(shims.join("cp"), fs::Permissions::from_mode(0o755)).unwrap();
+        let path = format!("{}:{}", shims.display(), std::env::var("PATH").unwrap());
+
+        let build_cmd = vec!["true".to_string()];
+        let Ok(mut sandbox) = sandboxed_command(&build_cmd, &source, &source.join("dist")) else {
+            eprintln!("skipping: user namespaces unavailable");
+            return;
+        };
+        if sandbox.network_only {
+            eprintln!("skipping: mount namespaces unavailable");
+            return;
+        }
+        let status = sandbox.command.env("PATH", path).status().unwrap();
+        assert!(status.success());
+        let report = sandbox.report();
+        assert!(
+            report.failed.iter().any(|l| l == "minimal-etc"),
+            "{:?}",
+            report
+        );
+        assert!(
+            report.succeeded.iter().any(|l| l == "private-tmp"),
+            "{:?}",
+            report
+        );
+    }
+}
@@ -0,0 +1,550 @@
+// tools.rs — Toolchain detection: which compilers and build tools ran
+//
+// environment.json lists the tools found on the build's PATH. A version
+// string alone does not prove which compiler ran (two "rustc 1.78.0"
+// binaries can differ), so each tool also records the SHA-256 of the
+// binary it resolves to.
+//
+// DETECTORS: a registry of known tools, each with the executable to look
+// up and the arguments that print its version. Some add details: the
+// rustup toolchain and installed components, or JAVA_HOME for the JDK.
+// The policy can narrow the registry and add project-specific tools:
+//
+//   "toolchain": {
+//     "detectors": ["rustc", "cargo", "clang"],
+//     "custom": [{ "name": "protoc", "command": "protoc" }]
+//   }
+//
+// SHIMS: version managers put shims on PATH (~/.cargo/bin/rustc is a
+// link to rustup, ~/.pyenv/shims/python3 is a shell script). Hashing the
+// shim proves nothing, so a shim is resolved with the manager's own
+// `which` command (rustup, pyenv, volta, asdf, rbenv, nodenv, jenv) and
+// the real binary is hashed. `path` records the resolved binary, and
+// `details.shim` the PATH entry it came from. Tools installed by nvm,
+// sdkman and similar managers are plain binaries; the manager is noted in
+// `details.manager` from the install path.
+//
+// Detection uses the environment the build command sees: with a clean
+// build environment (envvars.rs) the tools are looked up on its PATH.
+//
+// EXECUTED TOOLS: detection only shows what is installed. With
+// --trace-exec the tracer (trace.rs) reports every program the build ran;
+// a detected tool it ran gets `invocation.count`, and every other program
+// is added with its path and hash (version "unknown": running an arbitrary
+// binary with --version is not safe).
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Result;
+
+use crate::hash;
+use crate::sign::SECRET_ENV_VARS;
+use crate::vbw::model::{ToolInfo, ToolInvocation, ToolchainRequirement};
+use crate::vbw::trace::ExecutedFile;
+
+// ---------------------------------------------------------------------------
+// DETECTOR REGISTRY
+// ---------------------------------------------------------------------------
+
+/// Adds tool-specific details, given the resolved binary.
+type DetailsFn = fn(&Path, &Probe) -> BTreeMap<String, String>;
+
+/// A built-in tool detector.
+pub struct Detector {
+    /// Name recorded in environment.json and used in the policy.
+    pub name: &'static str,
+    /// Executable looked up on PATH.
+    pub binary: &'static str,
+    /// Arguments that print the version.
+    pub version_args: &'static [&'static str],
+    details: Option<DetailsFn>,
+}
+
+const fn detector(
+    name: &'static str,
+    version_args: &'static [&'static str],
+    details: Option<DetailsFn>,
+) -> Detector {
+    Detector {
+        name,
+        binary: name,
+        version_args,
+        details,
+    }
+}
+
+/// Every built-in detector, in the order tools are listed.
+pub const DETECTORS: &[Detector] = &[
+    detector("rustc", &["--version"], Some(rustc_details)),
+    detector("cargo", &["--version"], Some(rust_toolchain_details)),
+    detector("node", &["--version"], None),
+    detector("npm", &["--version"], None),
+    detector("yarn", &["--version"], None),
+    detector("pnpm", &["--version"], None),
+    detector("deno", &["--version"], None),
+    detector("bun", &["--version"], None),
+    detector("python3", &["--version"], None),
+    detector("go", &["version"], None),
+    detector("gcc", &["--version"], None),
+    detector("g++", &["--version"], None),
+    detector("clang", &["--version"], None),
+    detector("cmake", &["--version"], None),
+    detector("make", &["--version"], None),
+    detector("ninja", &["--version"], None),
+    detector("java", &["-version"], Some(java_details)),
+    detector("javac", &["-version"], Some(java_details)),
+    detector("mvn", &["--version"], None),
+    detector("gradle", &["--version"], None),
+    detector("dotnet", &["--version"], None),
+    detector("ruby", &["--version"], None),
+];
+
+/// Version managers whose PATH entries are shims: (directory, manager).
+/// The real binary is `<manager> which <binary>`.
+const SHIM_DIRS: &[(&str, &str)] = &[
+    ("/.cargo/bin/", "rustup"),
+    ("/.pyenv/shims/", "pyenv"),
+    ("/.volta/bin/", "volta"),
+    ("/.asdf/shims/", "asdf"),
+    ("/.rbenv/shims/", "rbenv"),
+    ("/.nodenv/shims/", "nodenv"),
+    ("/.jenv/shims/", "jenv"),
+];
+
+/// Version managers recognized from where a binary is installed.
+const MANAGER_DIRS: &[(&str, &str)] = &[
+    ("/.rustup/toolchains/", "rustup"),
+    ("/.nvm/", "nvm"),
+    ("/.volta/", "volta"),
+    ("/.fnm/", "fnm"),
+    ("/fnm/node-versions/", "fnm"),
+    ("/.pyenv/", "pyenv"),
+    ("/.asdf/", "asdf"),
+    ("/.rbenv/", "rbenv"),
+    ("/.nodenv/", "nodenv"),
+    ("/.sdkman/", "sdkman"),
+    ("/.jenv/", "jenv"),
+];
+
+/// Detect the tools the policy asks for (all built-ins by default) and
+/// hash the binaries they resolve to. `env` is the build command's
+/// environment when it does not inherit ours (a clean build).
+pub fn detect_tools(
+    requirement: Option<&ToolchainRequirement>,
+    env: Option<&BTreeMap<String, String>>,
+) -> Result<Vec<ToolInfo>> {
+    let selected = requirement.and_then(|r| r.detectors.as_ref());
+    if let Some(names) = selected {
+        for name in names {
+            if !DETECTORS.iter().any(|d| d.name == name) {
+                let known: Vec<&str> = DETECTORS.iter().map(|d| d.name).collect();
+                anyhow::bail!(
+                    "Unknown toolchain detector '{}' in policy (known: {})",
+                    name,
+                    known.join(", ")
+                );
+            }
+        }
+    }
+
+    let probe = Probe { env };
+    let mut tools = Vec::new();
+    for detector in DETECTORS {
+        if selected.is_some_and(|names| !names.iter().any(|n| n == detector.name)) {
+            continue;
+        }
+        if let Some(tool) = probe.detect(
+            detector.name,
+            detector.binary,
+            detector.version_args,
+            detector.details,
+        ) {
+            tools.push(tool);
+        }
+    }
+
+    for custom in requirement
+        .and_then(|r| r.custom.as_ref())
+        .into_iter()
+        .flatten()
+    {
+        let args: Vec<&str> = match &custom.version_args {
+            Some(args) => args.iter().map(String::as_str).collect(),
+            None => vec!["--version"],
+        };
+        match probe.detect(&custom.name, &custom.command, &args, None) {
+            Some(tool) => tools.push(tool),
+            None => eprintln!(
+                "[vbw] WARNING: policy tool '{}' ({}) not found or failed to run",
+                custom.name, custom.command
+            ),
+        }
+    }
+    Ok(tools)
+}
+
+/// Merge the programs the build executed into the detected tools.
+pub fn record_invocations(tools: &mut Vec<ToolInfo>, executed: Vec<ExecutedFile>) {
+    for exec in executed {
+        let invocation = Some(ToolInvocation { count: exec.count });
+        match tools
+            .iter_mut()
+            .find(|t| t.path.as_deref() == Some(&exec.path))
+        {
+            Some(tool) => {
+                if tool.sha256.is_some() && exec.sha256.is_some() && tool.sha256 != exec.sha256 {
+                    eprintln!(
+                        "[vbw] WARNING: {} changed between detection and execution",
+                        exec.path
+                    );
+                }
+                tool.invocation = invocation;
+            }
+            None => tools.push(ToolInfo {
+                name: Path::new(&exec.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| exec.path.clone()),
+                version: "unknown".to_string(),
+                path: Some(exec.path),
+                sha256: exec.sha256,
+                invocation,
+                details: None,
+            }),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PROBING
+// ---------------------------------------------------------------------------
+
+/// Runs tool commands in the build command's environment.
+struct Probe<'a> {
+    env: Option<&'a BTreeMap<String, String>>,
+}
+
+impl Probe<'_> {
+    fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        if let Some(env) = self.env {
+            command.env_clear().envs(env);
+        }
+        for name in SECRET_ENV_VARS {
+            command.env_remove(name);
+        }
+        command
+    }
+
+    /// Run a command and return its stdout, or stderr when stdout is empty
+    /// (`java -version` prints to stderr).
+    fn output(&self, program: impl AsRef<OsStr>, args: &[&str]) -> Option<String> {
+        let output = self.command(program).args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !stdout.is_empty() {
+            return Some(stdout);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        (!stderr.is_empty()).then_some(stderr)
+    }
+
+    fn detect(
+        &self,
+        name: &str,
+        binary: &str,
+        version_args: &[&str],
+        details: Option<DetailsFn>,
+    ) -> Option<ToolInfo> {
+        let found = self.find_executable(binary)?;
+        let version = version_line(&self.output(&found, version_args)?);
+
+        let mut info = BTreeMap::new();
+        let resolved = match self.resolve_shim(&found, binary) {
+            Some((real, manager)) => {
+                info.insert("shim".to_string(), found.display().to_string());
+                info.insert("manager".to_string(), manager.to_string());
+                real
+            }
+            None => found,
+        };
+        let resolved = resolved.canonicalize().unwrap_or(resolved);
+        if !info.contains_key("manager") {
+            if let Some(manager) = manager_for(&resolved) {
+                info.insert("manager".to_string(), manager.to_string());
+            }
+        }
+        if let Some(details) = details {
+            info.extend(details(&resolved, self));
+        }
+
+        Some(ToolInfo {
+            name: name.to_string(),
+            version,
+            sha256: hash::hash_file(&resolved).ok(),
+            path: Some(resolved.display().to_string()),
+            invocation: None,
+            details: (!info.is_empty()).then_some(info),
+        })
+    }
+
+    /// Look a command up like the shell would: a name containing `/` is a
+    /// path, anything else is searched on PATH.
+    fn find_executable(&self, command: &str) -> Option<PathBuf> {
+        if command.contains('/') {
+            let path = PathBuf::from(command);
+            return is_executable(&path).then_some(path);
+        }
+        let path_var = match self.env {
+            Some(env) => env.get("PATH").map(Into::into),
+            None => std::env::var_os("PATH"),
+        }?;
+        std::env::split_paths(&path_var)
+            .map(|dir| dir.join(command))
+            .find(|candidate| is_executable(candidate))
+    }
+
+    /// Resolve a version manager shim to the binary it runs.
+    fn resolve_shim(&self, found: &Path, binary: &str) -> Option<(PathBuf, &'static str)> {
+        let found_str = found.to_string_lossy();
+        let (_, manager) = SHIM_DIRS.iter().find(|(dir, _)| found_str.contains(dir))?;
+        let manager_path = self.find_executable(manager)?;
+        let real = PathBuf::from(self.output(manager_path, &["which", binary])?);
+        is_executable(&real).then_some((real, manager))
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(meta) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        meta.is_file()
+    }
+}
+
+fn manager_for(resolved: &Path) -> Option<&'static str> {
+    let path = resolved.to_string_lossy();
+    MANAGER_DIRS
+        .iter()
+        .find(|(dir, _)| path.contains(dir))
+        .map(|(_, manager)| *manager)
+}
+
+/// The line of version output worth recording: the first one with a digit
+/// (`gradle --version` opens with a rule), minus terminal color codes.
+fn version_line(output: &str) -> String {
+    let lines: Vec<String> = output
+        .lines()
+        .map(strip_ansi)
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines
+        .iter()
+        .find(|l| l.chars().any(|c| c.is_ascii_digit()))
+        .or(lines.first())
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequence: ESC [ parameters final-byte
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// TOOL DETAILS
+// ---------------------------------------------------------------------------
+
+/// The rustup toolchain a binary belongs to, from its install path
+/// (`<RUSTUP_HOME>/toolchains/<toolchain>/bin/rustc`).
+fn rustup_toolchain(resolved: &Path) -> Option<String> {
+    let mut components = resolved.components().map(|c| c.as_os_str());
+    components.find(|c| *c == "toolchains")?;
+    components.next().map(|c| c.to_string_lossy().into_owned())
+}
+
+fn rust_toolchain_details(resolved: &Path, _probe: &Probe) -> BTreeMap<String, String> {
+    let mut details = BTreeMap::new();
+    if let Some(toolchain) = rustup_toolchain(resolved) {
+        details.insert("toolchain".to_string(), toolchain);
+    }
+    details
+}
+
+fn rustc_details(resolved: &Path, probe: &Probe) -> BTreeMap<String, String> {
+    let mut details = rust_toolchain_details(resolved, probe);
+    let installed = details.get("toolchain").and_then(|toolchain| {
+        let rustup = probe.find_executable("rustup")?;
+        probe.output(
+            rustup,
+            &["component", "list", "--installed", "--toolchain", toolchain],
+        )
+    });
+    if let Some(installed) = installed {
+        let components: Vec<&str> = installed.lines().map(str::trim).collect();
+        details.insert("components".to_string(), components.join(", "));
+    }
+    details
+}
+
+/// JAVA_HOME of the JDK a `java`/`javac` binary belongs to (`<home>/bin/java`).
+fn java_details(resolved: &Path, _probe: &Probe) -> BTreeMap<String, String> {
+    let mut details = BTreeMap::new();
+    if let Some(home) = resolved.parent().and_then(Path::parent) {
+        details.insert("java_home".to_string(), home.display().to_string());
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vbw::model::CustomTool;
+
+    #[test]
+    fn picks_version_line_from_tool_output() {
+        assert_eq!(
+            version_line("rustc 1.78.0 (9b00956e5 2024-04-29)"),
+            "rustc 1.78.0 (9b00956e5 2024-04-29)"
+        );
+        assert_eq!(
+            version_line(
+                "\n------------------------------------------------------------\nGradle 8.5\n"
+            ),
+            "Gradle 8.5"
+        );
+        assert_eq!(
+            version_line("\u{1b}[1mApache Maven 3.9.6\u{1b}[m (bc0240f3c)\nMaven home: /opt/maven"),
+            "Apache Maven 3.9.6 (bc0240f3c)"
+        );
+        assert_eq!(version_line("no digits here"), "no digits here");
+    }
+
+    #[test]
+    fn recognizes_toolchains_and_managers_from_paths() {
+        let rustc =
+            Path::new("/home/ci/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/bin/rustc");
+        assert_eq!(
+            rustup_toolchain(rustc).as_deref(),
+            Some("stable-x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(manager_for(rustc), Some("rustup"));
+        assert_eq!(rustup_toolchain(Path::new("/usr/bin/rustc")), None);
+
+        let node = Path::new("/home/ci/.nvm/versions/node/v20.11.0/bin/node");
+        assert_eq!(manager_for(node), Some("nvm"));
+        assert_eq!(manager_for(Path::new("/usr/bin/node")), None);
+
+        let java = java_details(
+            Path::new("/usr/lib/jvm/java-17-openjdk-amd64/bin/java"),
+            &Probe { env: None },
+        );
+        assert_eq!(java["java_home"], "/usr/lib/jvm/java-17-openjdk-amd64");
+    }
+
+    #[test]
+    fn merges_executed_programs_into_tools() {
+        let mut tools = vec![ToolInfo {
+            name: "rustc".to_string(),
+            version: "rustc 1.78.0".to_string(),
+            path: Some("/opt/rust/bin/rustc".to_string()),
+            sha256: Some("a".repeat(64)),
+            invocation: None,
+            details: None,
+        }];
+        record_invocations(
+            &mut tools,
+            vec![
+                ExecutedFile {
+                    path: "/opt/rust/bin/rustc".to_string(),
+                    sha256: Some("a".repeat(64)),
+                    count: 7,
+                },
+                ExecutedFile {
+                    path: "/usr/bin/cc".to_string(),
+                    sha256: Some("b".repeat(64)),
+                    count: 3,
+                },
+            ],
+        );
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0].invocation.as_ref().unwrap().count, 7);
+        assert_eq!(tools[1].name, "cc");
+        assert_eq!(tools[1].version, "unknown");
+        assert_eq!(tools[1].sha256.as_deref(), Some("b".repeat(64).as_str()));
+        assert_eq!(tools[1].invocation.as_ref().unwrap().count, 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detects_policy_tools_on_the_build_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let script = tmp.path().join("mytool");
+        std::fs::write(&script, "#!/bin/sh\necho 'mytool version 2.4.1'\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let expected_hash = hash::hash_file(&script).unwrap();
+
+        let env = BTreeMap::from([("PATH".to_string(), tmp.path().display().to_string())]);
+        let requirement = ToolchainRequirement {
+            detectors: Some(vec![]),
+            custom: Some(vec![
+                CustomTool {
+                    name: "mytool".to_string(),
+                    command: "mytool".to_string(),
+                    version_args: None,
+                },
+                CustomTool {
+                    name: "missing".to_string(),
+                    command: "not-installed".to_string(),
+                    version_args: None,
+                },
+            ]),
+        };
+        let tools = detect_tools(Some(&requirement), Some(&env)).unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "mytool");
+        assert_eq!(tools[0].version, "mytool version 2.4.1");
+        assert_eq!(tools[0].sha256.as_deref(), Some(expected_hash.as_str()));
+        let canonical = script.canonicalize().unwrap();
+        assert_eq!(tools[0].path.as_deref(), Some(canonical.to_str().unwrap()));
+
+        let unknown = ToolchainRequirement {
+            detectors: Some(vec!["rustcc".to_string()]),
+            custom: None,
+        };
+        let err = detect_tools(Some(&unknown), Some(&env)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown toolchain detector 'rustcc'"));
+    }
+}
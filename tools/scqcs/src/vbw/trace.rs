@@ -0,0 +1,1033 @@
+// trace.rs — Syscall tracing of the build's process tree (Linux ptrace)
+//
+// With `vbw build --trace-network`, the build command runs under a small
+// ptrace tracer. The tracer follows every fork, vfork and clone, and stops
+// each process at syscall entry and exit (PTRACE_SYSCALL). That way it sees
+// the whole process tree, including tools that ignore proxy variables.
+//
+// Network tracing records:
+//   - connect():  every IPv4/IPv6 destination (address, port, count)
+//   - sendto(), sendmsg(), sendmmsg():  datagram destinations, and the
+//     query name of every DNS request sent to port 53
+//
+// Attempts are recorded at syscall entry, so refused and timed-out
+// connections count too. Unix-domain sockets are not recorded.
+//
+// Exec tracing (`--trace-exec`) records every program the build runs:
+//   - execve(), execveat():  the file name, made absolute against the
+//     process's working directory, is noted at syscall entry
+//   - PTRACE_EVENT_EXEC:  the exec succeeded; the loaded binary
+//     (/proc/<pid>/exe) is recorded, and the executed file too when it
+//     differs (a script run by its interpreter)
+// Each distinct path is hashed once, when first executed, so a binary the
+// build later deletes is still identified. build.rs merges the result
+// into environment.json's tools (see tools.rs).
+//
+// The build command is stopped before its own exec so that the first
+// program is recorded as well.
+//
+// Input tracing (`--trace-inputs`) records every regular file the build
+// opens for reading (open(), openat(), openat2() with O_RDONLY or O_RDWR).
+// The file is recorded at syscall exit, only when the open succeeded, by
+// reading the /proc/<pid>/fd link of the new descriptor, so paths are
+// canonical. /proc and /sys are skipped. inputs.rs classifies the files.
+//
+// A command that sets something up before running the build (the Mode A
+// sandbox, see sandbox.rs) execs its last step through a link named
+// START_MARKER and sets `from_start_marker`: the tracer then records
+// nothing until the process that exec'd the marker execs again, so the
+// setup's own programs and files are not attributed to the build. Only
+// the setup runs before that exec, and it has finished by then.
+//
+// Tracing ends when the build command itself exits; processes it leaves
+// behind are detached and keep running untraced.
+//
+// Stopping at every syscall slows syscall-heavy builds down noticeably,
+// which is why tracing is opt-in. Tracing is only available on Linux
+// (x86_64 and aarch64); elsewhere --trace-network, --trace-exec and
+// --trace-inputs fail the build.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::process::{ChildStderr, ChildStdout, Command, ExitStatus};
+use std::time::Duration;
+
+use crate::hash;
+use crate::vbw::model::*;
+
+/// What the tracer should record.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceOptions {
+    /// Record network connections and DNS queries (network-access.json).
+    pub network: bool,
+    /// Record the programs executed by the build (environment.json tools).
+    pub exec: bool,
+    /// Record the files the build reads (inputs.json).
+    pub inputs: bool,
+    /// Record nothing before the exec that follows an exec of START_MARKER.
+    pub from_start_marker: bool,
+}
+
+/// File name of the link a setup step execs its last program through.
+pub const START_MARKER: &str = "vbw-build-start";
+
+impl TraceOptions {
+    /// Whether the build needs to run under the tracer at all.
+    pub fn any(&self) -> bool {
+        self.network || self.exec || self.inputs
+    }
+}
+
+/// What the tracer observed, one entry per enabled kind of tracing.
+#[derive(Debug, Default)]
+pub struct TraceReport {
+    pub network: Option<NetworkAccessLog>,
+    /// Executed programs, by path.
+    pub executables: Option<Vec<ExecutedFile>>,
+    /// Files opened for reading: absolute path -> number of opens.
+    pub files_read: Option<BTreeMap<String, u64>>,
+    /// Resource usage of the build command, from the tracer's wait4().
+    pub resources: Option<ResourceUsage>,
+}
+
+/// CPU time and peak memory of a finished process and of the descendants
+/// it waited for, as reported by wait4().
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceUsage {
+    pub user_cpu: Duration,
+    pub system_cpu: Duration,
+    /// Largest resident set size, in KiB.
+    pub max_rss_kib: u64,
+}
+
+impl ResourceUsage {
+    #[cfg(unix)]
+    pub fn from_rusage(usage: &libc::rusage) -> Self {
+        let duration = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec.max(0) as u64)
+                + Duration::from_micros(tv.tv_usec.max(0) as u64)
+        };
+        ResourceUsage {
+            user_cpu: duration(usage.ru_utime),
+            system_cpu: duration(usage.ru_stime),
+            // Linux reports KiB; macOS reports bytes
+            max_rss_kib: if cfg!(target_os = "macos") {
+                usage.ru_maxrss.max(0) as u64 / 1024
+            } else {
+                usage.ru_maxrss.max(0) as u64
+            },
+        }
+    }
+}
+
+/// A program the build executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutedFile {
+    pub path: String,
+    /// SHA-256 when first executed (None if it could not be read).
+    pub sha256: Option<String>,
+    /// Number of successful execs.
+    pub count: u64,
+}
+
+// ── Recording ──────────────────────────────────────────────────────────────
+
+/// Accumulates observations while the tracer runs.
+#[derive(Default)]
+struct Recorder {
+    options: TraceOptions,
+    /// (address, port) -> number of attempts
+    destinations: BTreeMap<(String, u16), u64>,
+    /// DNS query name -> number of queries
+    dns_queries: BTreeMap<String, u64>,
+    /// Executed path -> (hash, number of execs)
+    executables: BTreeMap<String, (Option<String>, u64)>,
+    /// Path opened for reading -> number of opens
+    files_read: BTreeMap<String, u64>,
+}
+
+impl Recorder {
+    fn record_destination(&mut self, address: String, port: u16) {
+        *self.destinations.entry((address, port)).or_default() += 1;
+    }
+
+    fn record_dns_query(&mut self, name: String) {
+        *self.dns_queries.entry(name).or_default() += 1;
+    }
+
+    /// Count an exec of `path`; `contents` is where to read the file the
+    /// first time (it may differ from `path`, e.g. /proc/<pid>/exe).
+    fn record_exec(&mut self, path: String, contents: &std::path::Path) {
+        let entry = self
+            .executables
+            .entry(path)
+            .or_insert_with(|| (hash::hash_file(contents).ok(), 0));
+        entry.1 += 1;
+    }
+
+    fn finish(self) -> TraceReport {
+        let network = self.options.network.then(|| NetworkAccessLog {
+            connections: self
+                .destinations
+                .into_iter()
+                .map(|((address, port), count)| TracedConnection {
+                    address,
+                    port,
+                    count,
+                })
+                .collect(),
+            dns_queries: self
+                .dns_queries
+                .into_iter()
+                .map(|(name, count)| DnsQuery { name, count })
+                .collect(),
+        });
+        let executables = self.options.exec.then(|| {
+            self.executables
+                .into_iter()
+                .map(|(path, (sha256, count))| ExecutedFile {
+                    path,
+                    sha256,
+                    count,
+                })
+                .collect()
+        });
+        let files_read = self.options.inputs.then_some(self.files_read);
+        TraceReport {
+            network,
+            executables,
+            files_read,
+            resources: None,
+        }
+    }
+}
+
+/// Decode an IPv4 or IPv6 socket address into (address, port). Other
+/// families (Unix sockets, AF_UNSPEC disconnects) yield None.
+fn decode_sockaddr(bytes: &[u8]) -> Option<(String, u16)> {
+    const AF_INET: u16 = 2;
+    const AF_INET6: u16 = 10;
+    let family = u16::from_ne_bytes(bytes.get(0..2)?.try_into().ok()?);
+    let port = u16::from_be_bytes(bytes.get(2..4)?.try_into().ok()?);
+    match family {
+        AF_INET => {
+            let octets: [u8; 4] = bytes.get(4..8)?.try_into().ok()?;
+            Some((std::net::Ipv4Addr::from(octets).to_string(), port))
+        }
+        AF_INET6 => {
+            let octets: [u8; 16] = bytes.get(8..24)?.try_into().ok()?;
+            Some((std::net::Ipv6Addr::from(octets).to_string(), port))
+        }
+        _ => None,
+    }
+}
+
+/// The query name of a DNS request packet, or None if `packet` is not a
+/// standard query for exactly one name in class IN.
+fn parse_dns_query(packet: &[u8]) -> Option<String> {
+    let be16 = |at: usize| -> Option<u16> {
+        Some(u16::from_be_bytes(packet.get(at..at + 2)?.try_into().ok()?))
+    };
+    let flags = be16(2)?;
+    // QR=0 (query), opcode 0 (standard query)
+    if flags & 0xf800 != 0 || be16(4)? != 1 || be16(6)? != 0 || be16(8)? != 0 {
+        return None;
+    }
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        let label = packet.get(pos..pos + len).filter(|_| len <= 63)?;
+        if !label
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_')
+        {
+            return None;
+        }
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+    // QTYPE, then QCLASS IN
+    if be16(pos + 2)? != 1 {
+        return None;
+    }
+    if labels.is_empty() {
+        return Some(".".to_string());
+    }
+    Some(labels.join("."))
+}
+
+/// Run `command` (stdout and stderr must be piped) under the tracer.
+/// `capture` consumes the output streams while the tracer runs; its
+/// result is returned with the exit status and what was traced.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn run_traced<T>(
+    command: &mut Command,
+    options: TraceOptions,
+    capture: impl FnOnce(ChildStdout, ChildStderr) -> T,
+) -> Result<(T, ExitStatus, TraceReport)> {
+    ptrace::run(command, options, capture)
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub fn run_traced<T>(
+    _command: &mut Command,
+    _options: TraceOptions,
+    _capture: impl FnOnce(ChildStdout, ChildStderr) -> T,
+) -> Result<(T, ExitStatus, TraceReport)> {
+    anyhow::bail!("build tracing requires Linux on x86_64 or aarch64")
+}
+
+// ── ptrace ─────────────────────────────────────────────────────────────────
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod ptrace {
+    use super::*;
+    use anyhow::Context;
+    use std::collections::{HashMap, HashSet};
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::path::{Path, PathBuf};
+    use std::ptr;
+    use std::sync::mpsc;
+
+    use libc::{c_long, c_void, pid_t};
+
+    /// Largest sockaddr and DNS packet read from a tracee.
+    const MAX_SOCKADDR: usize = 128;
+    const MAX_DNS_PACKET: usize = 512;
+    /// Messages of one sendmmsg() call inspected.
+    const MAX_MMSG: usize = 8;
+    const DNS_PORT: u16 = 53;
+    /// Longest file name read from an execve() argument.
+    const MAX_PATH: usize = 4096;
+
+    /// A syscall as seen at a syscall stop.
+    struct Syscall {
+        nr: c_long,
+        args: [u64; 6],
+        /// Return value (meaningful at syscall exit only)
+        ret: i64,
+    }
+
+    pub fn run<T>(
+        command: &mut Command,
+        options: TraceOptions,
+        capture: impl FnOnce(ChildStdout, ChildStderr) -> T,
+    ) -> Result<(T, ExitStatus, TraceReport)> {
+        // The tracee's tracer is the thread that forked it, and only that
+        // thread may wait for it, so spawning and tracing share a thread.
+        let (tx, rx) = mpsc::channel();
+        let program = program_path(command);
+        std::thread::scope(|scope| {
+            let tracer = scope.spawn(move || {
+                // SAFETY: PTRACE_TRACEME is async-signal-safe; the child
+                // stops at its execve until the tracer resumes it.
+                unsafe {
+                    command.pre_exec(|| {
+                        if libc::ptrace(libc::PTRACE_TRACEME, 0, ptr::null_mut::<c_void>(), 0) == -1
+                        {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+                let mut child = command.spawn().with_context(|| {
+                    format!("spawning {}", command.get_program().to_string_lossy())
+                })?;
+                let _ = tx.send((
+                    child.stdout.take().expect("stdout was piped"),
+                    child.stderr.take().expect("stderr was piped"),
+                ));
+                trace(child.id() as pid_t, program, options)
+            });
+            let captured = rx
+                .recv()
+                .ok()
+                .map(|(stdout, stderr)| capture(stdout, stderr));
+            let (status, report) = tracer
+                .join()
+                .map_err(|_| anyhow::anyhow!("tracer thread panicked"))??;
+            let captured = captured.expect("pipes are sent before tracing starts");
+            Ok((captured, status, report))
+        })
+    }
+
+    /// The file the build command's own execve runs, found the way
+    /// Command does: a name containing `/` is a path, anything else is
+    /// looked up on the command's PATH.
+    fn program_path(command: &Command) -> Option<PathBuf> {
+        let base = match command.get_current_dir() {
+            Some(dir) => dir.to_path_buf(),
+            None => std::env::current_dir().ok()?,
+        };
+        let program = command.get_program();
+        if program.to_string_lossy().contains('/') {
+            return Some(base.join(program));
+        }
+        let path_var = match command.get_envs().find(|(key, _)| *key == "PATH") {
+            Some((_, value)) => value?.to_os_string(),
+            None => std::env::var_os("PATH")?,
+        };
+        std::env::split_paths(&path_var)
+            .map(|dir| base.join(dir).join(program))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Trace `root` and its descendants until `root` exits. `program` is
+    /// the file `root` exec'd before tracing began.
+    fn trace(
+        root: pid_t,
+        program: Option<PathBuf>,
+        options: TraceOptions,
+    ) -> Result<(ExitStatus, TraceReport)> {
+        let mut status = 0;
+        // The first stop is the SIGTRAP after the traced execve
+        if unsafe { libc::waitpid(root, &mut status, libc::__WALL) } != root
+            || !libc::WIFSTOPPED(status)
+        {
+            anyhow::bail!("traced build command did not stop at exec");
+        }
+        let ptrace_options = libc::PTRACE_O_TRACESYSGOOD
+            | libc::PTRACE_O_TRACEFORK
+            | libc::PTRACE_O_TRACEVFORK
+            | libc::PTRACE_O_TRACECLONE
+            | libc::PTRACE_O_TRACEEXEC;
+        if request(libc::PTRACE_SETOPTIONS, root, ptrace_options as usize) == -1 {
+            let error = std::io::Error::last_os_error();
+            unsafe { libc::kill(root, libc::SIGKILL) };
+            return Err(error).context("setting ptrace options");
+        }
+
+        let mut recorder = Recorder {
+            options,
+            ..Default::default()
+        };
+        let mut tracer = Tracer {
+            recording: !options.from_start_marker,
+            ..Default::default()
+        };
+        if let Some(program) = program {
+            tracer.pending_exec.insert(root, program);
+        }
+        tracer.on_exec(root, root, &mut recorder);
+        request(libc::PTRACE_SYSCALL, root, 0);
+        let mut in_syscall: HashSet<pid_t> = HashSet::new();
+        // Tracees whose initial SIGSTOP has been consumed
+        let mut started: HashSet<pid_t> = HashSet::from([root]);
+        // SAFETY: rusage is plain data; wait4 fills it in
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            let pid = unsafe {
+                libc::wait4(-1, &mut status, libc::__WALL | libc::__WNOTHREAD, &mut usage)
+            };
+            if pid == -1 {
+                let error = std::io::Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    _ => return Err(error).context("waiting for traced build"),
+                }
+            }
+            if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+                in_syscall.remove(&pid);
+                started.remove(&pid);
+                tracer.pending_exec.remove(&pid);
+                tracer.marked.remove(&pid);
+                tracer.pending_open.remove(&pid);
+                if pid == root {
+                    break;
+                }
+                continue;
+            }
+            if !libc::WIFSTOPPED(status) {
+                continue;
+            }
+            let signal = libc::WSTOPSIG(status);
+            let inject = if signal == libc::SIGTRAP | 0x80 {
+                if !in_syscall.remove(&pid) {
+                    in_syscall.insert(pid);
+                    if let Some(syscall) = read_syscall(pid) {
+                        tracer.on_entry(pid, &syscall, &mut recorder);
+                    }
+                } else if tracer.pending_open.remove(&pid) {
+                    if let Some(syscall) = read_syscall(pid) {
+                        tracer.on_open_exit(pid, &syscall, &mut recorder);
+                    }
+                }
+                0
+            } else if signal == libc::SIGTRAP && status >> 16 == libc::PTRACE_EVENT_EXEC {
+                let former = event_message(pid).unwrap_or(pid);
+                if former != pid && in_syscall.remove(&former) {
+                    // A non-leader thread exec'd and took over the leader's pid
+                    in_syscall.insert(pid);
+                }
+                tracer.on_exec(pid, former, &mut recorder);
+                0
+            } else if signal == libc::SIGTRAP && status >> 16 != 0 {
+                // fork/vfork/clone event stop
+                0
+            } else if signal == libc::SIGSTOP && started.insert(pid) {
+                // A new tracee's initial stop
+                0
+            } else {
+                signal
+            };
+            request(libc::PTRACE_SYSCALL, pid, inject as usize);
+        }
+        // Returning ends this thread, which detaches any remaining tracees.
+        // The last wait4 reaped the build command, so `usage` is its own.
+        let mut report = recorder.finish();
+        report.resources = Some(ResourceUsage::from_rusage(&usage));
+        Ok((ExitStatus::from_raw(status), report))
+    }
+
+    /// Per-trace state needed to interpret syscalls.
+    #[derive(Default)]
+    struct Tracer {
+        /// Sockets connected to a DNS server, by (tid, fd)
+        dns_sockets: HashSet<(pid_t, u64)>,
+        /// File being exec'd, by tid, until the exec succeeds
+        pending_exec: HashMap<pid_t, PathBuf>,
+        /// Threads inside an open for reading, until it returns
+        pending_open: HashSet<pid_t>,
+        /// False until the build proper starts (see `from_start_marker`)
+        recording: bool,
+        /// Processes that exec'd START_MARKER, until their next exec
+        marked: HashSet<pid_t>,
+    }
+
+    impl Tracer {
+        fn on_entry(&mut self, pid: pid_t, syscall: &Syscall, recorder: &mut Recorder) {
+            if recorder.options.exec || !self.recording {
+                self.on_exec_entry(pid, syscall);
+            }
+            if !self.recording {
+                return;
+            }
+            if recorder.options.inputs && opens_for_reading(pid, syscall) {
+                self.pending_open.insert(pid);
+            }
+            if !recorder.options.network {
+                return;
+            }
+            let [fd, a1, a2, _, a4, a5] = syscall.args;
+            match syscall.nr {
+                libc::SYS_connect => {
+                    if let Some((address, port)) = read_sockaddr(pid, a1, a2) {
+                        if port == DNS_PORT {
+                            self.dns_sockets.insert((pid, fd));
+                        } else {
+                            self.dns_sockets.remove(&(pid, fd));
+                        }
+                        recorder.record_destination(address, port);
+                    }
+                }
+                libc::SYS_sendto => self.on_send(pid, fd, (a4, a5), (a1, a2), recorder),
+                libc::SYS_sendmsg => self.on_sendmsg(pid, fd, a1, recorder),
+                libc::SYS_sendmmsg => {
+                    for i in 0..(a2 as usize).min(MAX_MMSG) {
+                        // struct mmsghdr: a msghdr plus msg_len, 64 bytes
+                        self.on_sendmsg(pid, fd, a1 + 64 * i as u64, recorder);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        /// Note the absolute name of the file an execve()/execveat() runs.
+        fn on_exec_entry(&mut self, pid: pid_t, syscall: &Syscall) {
+            let [a0, a1, _, _, a4, _] = syscall.args;
+            let (dirfd, name, flags) = match syscall.nr {
+                libc::SYS_execve => (libc::AT_FDCWD, a0, 0),
+                libc::SYS_execveat => (a0 as i32, a1, a4 as i32),
+                _ => return,
+            };
+            let Some(name) = read_cstring(pid, name) else {
+                return;
+            };
+            let dir = || {
+                let link = match dirfd {
+                    libc::AT_FDCWD => format!("/proc/{}/cwd", pid),
+                    fd => format!("/proc/{}/fd/{}", pid, fd),
+                };
+                std::fs::read_link(link).ok()
+            };
+            let path = if name.is_empty() && flags & libc::AT_EMPTY_PATH != 0 {
+                dir()
+            } else if name.starts_with('/') {
+                Some(PathBuf::from(name))
+            } else {
+                dir().map(|dir| dir.join(name))
+            };
+            if let Some(path) = path {
+                self.pending_exec.insert(pid, path);
+            }
+        }
+
+        /// An exec succeeded: `pid` now runs a new program. `former` is
+        /// the thread that called exec (a different tid when a non-leader
+        /// thread exec'd).
+        fn on_exec(&mut self, pid: pid_t, former: pid_t, recorder: &mut Recorder) {
+            let pending = self.pending_exec.remove(&former);
+            if !self.recording {
+                if !self.marked.remove(&former) {
+                    if pending.is_some_and(|p| p.file_name() == Some(START_MARKER.as_ref())) {
+                        self.marked.insert(pid);
+                    }
+                    return;
+                }
+                self.recording = true;
+            }
+            if !recorder.options.exec {
+                return;
+            }
+            let exe_link = PathBuf::from(format!("/proc/{}/exe", pid));
+            let exe = read_proc_link(pid, &exe_link);
+            if let Some(exe) = &exe {
+                recorder.record_exec(exe.display().to_string(), &exe_link);
+            }
+            // A script: record the file itself besides its interpreter
+            if let Some(file) = pending {
+                let file = resolve_in_root(pid, &file);
+                if exe.as_ref() != Some(&file) {
+                    let contents = in_root(pid, &file);
+                    recorder.record_exec(file.display().to_string(), &contents);
+                }
+            }
+        }
+
+        /// An open for reading returned: record the file if it succeeded.
+        fn on_open_exit(&self, pid: pid_t, syscall: &Syscall, recorder: &mut Recorder) {
+            if syscall.ret < 0 {
+                return;
+            }
+            let fd_link = PathBuf::from(format!("/proc/{}/fd/{}", pid, syscall.ret));
+            let Some(path) = read_proc_link(pid, &fd_link) else {
+                return;
+            };
+            let path = path.display().to_string();
+            if let Some(count) = recorder.files_read.get_mut(&path) {
+                *count += 1;
+            } else if !path.starts_with("/proc/")
+                && !path.starts_with("/sys/")
+                && std::fs::metadata(&fd_link).is_ok_and(|m| m.is_file())
+            {
+                recorder.files_read.insert(path, 1);
+            }
+        }
+
+        /// struct msghdr: msg_name at 0, msg_namelen at 8, msg_iov at 16.
+        fn on_sendmsg(&self, pid: pid_t, fd: u64, msghdr: u64, recorder: &mut Recorder) {
+            let Some(header) = read_memory(pid, msghdr, 32) else {
+                return;
+            };
+            let word = |at: usize| u64::from_ne_bytes(header[at..at + 8].try_into().unwrap());
+            let name = (word(0), word(8) & 0xffff_ffff);
+            let (iov, iovlen) = (word(16), word(24));
+            let payload = if iovlen > 0 {
+                read_memory(pid, iov, 16).map(|iov| {
+                    (
+                        u64::from_ne_bytes(iov[0..8].try_into().unwrap()),
+                        u64::from_ne_bytes(iov[8..16].try_into().unwrap()),
+                    )
+                })
+            } else {
+                None
+            };
+            self.on_send(pid, fd, name, payload.unwrap_or((0, 0)), recorder);
+        }
+
+        /// A datagram to `name` (sockaddr pointer and length, or null for
+        /// a connected socket) with payload at (pointer, length).
+        fn on_send(
+            &self,
+            pid: pid_t,
+            fd: u64,
+            name: (u64, u64),
+            payload: (u64, u64),
+            recorder: &mut Recorder,
+        ) {
+            let mut to_dns = self.dns_sockets.contains(&(pid, fd));
+            if name.0 != 0 {
+                if let Some((address, port)) = read_sockaddr(pid, name.0, name.1) {
+                    to_dns = port == DNS_PORT;
+                    recorder.record_destination(address, port);
+                }
+            }
+            if to_dns && payload.0 != 0 {
+                let len = (payload.1 as usize).min(MAX_DNS_PACKET);
+                if let Some(query) = read_memory(pid, payload.0, len)
+                    .as_deref()
+                    .and_then(parse_dns_query)
+                {
+                    recorder.record_dns_query(query);
+                }
+            }
+        }
+    }
+
+    fn request(request: libc::c_uint, pid: pid_t, data: usize) -> c_long {
+        unsafe { libc::ptrace(request, pid, ptr::null_mut::<c_void>(), data as *mut c_void) }
+    }
+
+    /// The event message of a ptrace event stop (the former tid for
+    /// PTRACE_EVENT_EXEC).
+    fn event_message(pid: pid_t) -> Option<pid_t> {
+        let mut message: libc::c_ulong = 0;
+        let rc = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETEVENTMSG,
+                pid,
+                ptr::null_mut::<c_void>(),
+                &mut message as *mut _ as *mut c_void,
+            )
+        };
+        (rc != -1).then_some(message as pid_t)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn read_syscall(pid: pid_t) -> Option<Syscall> {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        let rc = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETREGS,
+                pid,
+                ptr::null_mut::<c_void>(),
+                &mut regs as *mut _ as *mut c_void,
+            )
+        };
+        (rc != -1).then_some(Syscall {
+            nr: regs.orig_rax as c_long,
+            args: [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9],
+            ret: regs.rax as i64,
+        })
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn read_syscall(pid: pid_t) -> Option<Syscall> {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        let mut iov = libc::iovec {
+            iov_base: &mut regs as *mut _ as *mut c_void,
+            iov_len: std::mem::size_of::<libc::user_regs_struct>(),
+        };
+        let rc = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETREGSET,
+                pid,
+                libc::NT_PRSTATUS as usize as *mut c_void,
+                &mut iov as *mut _ as *mut c_void,
+            )
+        };
+        (rc != -1).then_some(Syscall {
+            nr: regs.regs[8] as c_long,
+            args: [
+                regs.regs[0],
+                regs.regs[1],
+                regs.regs[2],
+                regs.regs[3],
+                regs.regs[4],
+                regs.regs[5],
+            ],
+            ret: regs.regs[0] as i64,
+        })
+    }
+
+    fn read_sockaddr(pid: pid_t, addr: u64, len: u64) -> Option<(String, u16)> {
+        if addr == 0 {
+            return None;
+        }
+        decode_sockaddr(&read_memory(pid, addr, (len as usize).min(MAX_SOCKADDR))?)
+    }
+
+    /// Whether the syscall at entry opens a file for reading.
+    fn opens_for_reading(pid: pid_t, syscall: &Syscall) -> bool {
+        let [_, a1, a2, _, _, _] = syscall.args;
+        let flags = match syscall.nr {
+            #[cfg(target_arch = "x86_64")]
+            libc::SYS_open => a1 as i32,
+            libc::SYS_openat => a2 as i32,
+            // struct open_how: flags is the first u64
+            libc::SYS_openat2 => match read_memory(pid, a2, 8) {
+                Some(how) => u64::from_ne_bytes(how[..8].try_into().unwrap()) as i32,
+                None => return false,
+            },
+            _ => return false,
+        };
+        flags & libc::O_ACCMODE != libc::O_WRONLY && flags & (libc::O_DIRECTORY | libc::O_PATH) == 0
+    }
+
+    /// Read a /proc/<pid>/{exe,fd/N} link as a path the tracee would use:
+    /// inside the Mode A sandbox the kernel reports it from our root.
+    fn read_proc_link(pid: pid_t, link: &Path) -> Option<PathBuf> {
+        let path = std::fs::read_link(link).ok()?;
+        let root = std::fs::read_link(format!("/proc/{}/root", pid))
+            .unwrap_or_else(|_| PathBuf::from("/"));
+        Some(match path.strip_prefix(&root) {
+            Ok(rest) => Path::new("/").join(rest),
+            Err(_) => path,
+        })
+    }
+
+    /// `path` (absolute, as the tracee sees it) reached through
+    /// /proc/<pid>/root, which works across mount namespaces.
+    fn in_root(pid: pid_t, path: &Path) -> PathBuf {
+        PathBuf::from(format!("/proc/{}/root", pid)).join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Resolve the symlinks in `path` (absolute, as the tracee sees it)
+    /// inside the tracee's root. fs::canonicalize cannot: it would follow
+    /// absolute links in our root.
+    fn resolve_in_root(pid: pid_t, path: &Path) -> PathBuf {
+        const MAX_LINKS: usize = 40;
+        let mut resolved = PathBuf::from("/");
+        let mut queue: Vec<std::ffi::OsString> = path.iter().rev().map(Into::into).collect();
+        let mut links = 0;
+        while let Some(part) = queue.pop() {
+            if part == "/" {
+                resolved = PathBuf::from("/");
+            } else if part == ".." {
+                resolved.pop();
+            } else if part != "." {
+                let candidate = resolved.join(&part);
+                match std::fs::read_link(in_root(pid, &candidate)) {
+                    Ok(target) if links < MAX_LINKS => {
+                        links += 1;
+                        queue.extend(target.iter().rev().map(Into::into));
+                    }
+                    _ => resolved = candidate,
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Read a NUL-terminated string at `addr`, a page at a time so that no
+    /// read crosses into an unmapped page.
+    fn read_cstring(pid: pid_t, addr: u64) -> Option<String> {
+        const PAGE: u64 = 4096;
+        if addr == 0 {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        let mut at = addr;
+        while bytes.len() < MAX_PATH {
+            let chunk = read_memory(pid, at, (PAGE - at % PAGE) as usize)?;
+            if let Some(end) = chunk.iter().position(|b| *b == 0) {
+                bytes.extend_from_slice(&chunk[..end]);
+                return Some(String::from_utf8_lossy(&bytes).into_owned());
+            }
+            at += chunk.len() as u64;
+            bytes.extend_from_slice(&chunk);
+        }
+        None
+    }
+
+    /// Read `len` bytes at `addr` in the tracee's address space.
+    fn read_memory(pid: pid_t, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        let local = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut c_void,
+            iov_len: len,
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut c_void,
+            iov_len: len,
+        };
+        let read = unsafe { libc::process_vm_readv(pid, &local, 1, &remote, 1, 0) };
+        if read == len as isize {
+            return Some(buf);
+        }
+        // Fall back to word-by-word reads (process_vm_readv may be denied)
+        for (i, chunk) in buf.chunks_mut(8).enumerate() {
+            let at = addr + 8 * i as u64;
+            unsafe { *libc::__errno_location() = 0 };
+            let word = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_PEEKDATA,
+                    pid,
+                    at as *mut c_void,
+                    ptr::null_mut::<c_void>(),
+                )
+            };
+            if word == -1 && std::io::Error::last_os_error().raw_os_error() != Some(0) {
+                return None;
+            }
+            chunk.copy_from_slice(&word.to_ne_bytes()[..chunk.len()]);
+        }
+        Some(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DNS query for `name` (type A, class IN) as a resolver sends it.
+    fn dns_query(name: &str) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.extend_from_slice(&[0, 0, 1, 0, 1]);
+        packet
+    }
+
+    #[test]
+    fn decodes_sockaddrs_and_dns_queries() {
+        let mut v4 = vec![2, 0, 0x01, 0xbb, 93, 184, 216, 34];
+        v4[0..2].copy_from_slice(&2u16.to_ne_bytes());
+        assert_eq!(
+            decode_sockaddr(&v4),
+            Some(("93.184.216.34".to_string(), 443))
+        );
+        let mut v6 = vec![0u8; 28];
+        v6[0..2].copy_from_slice(&10u16.to_ne_bytes());
+        v6[2..4].copy_from_slice(&53u16.to_be_bytes());
+        v6[23] = 1;
+        assert_eq!(decode_sockaddr(&v6), Some(("::1".to_string(), 53)));
+        let mut unix = vec![0u8; 16];
+        unix[0..2].copy_from_slice(&1u16.to_ne_bytes());
+        assert_eq!(decode_sockaddr(&unix), None);
+
+        assert_eq!(
+            parse_dns_query(&dns_query("Registry.npmjs.org")).as_deref(),
+            Some("registry.npmjs.org")
+        );
+        let mut response = dns_query("example.com");
+        response[2] |= 0x80;
+        assert_eq!(parse_dns_query(&response), None);
+        assert_eq!(parse_dns_query(b"GET / HTTP/1.1\r\n"), None);
+        assert_eq!(parse_dns_query(&dns_query("example.com")[..20]), None);
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn tracer_records_connections_of_child_processes() {
+        use std::io::Read;
+        use std::process::Stdio;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // A child process connects; the tracer must follow the fork.
+        let script = format!("bash -c 'exec 3<>/dev/tcp/127.0.0.1/{port}' && echo done");
+        let mut command = Command::new("bash");
+        command
+            .args(["-c", &script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let traced = run_traced(
+            &mut command,
+            TraceOptions {
+                network: true,
+                ..Default::default()
+            },
+            |mut stdout, mut stderr| {
+                let mut out = String::new();
+                stdout.read_to_string(&mut out).unwrap();
+                stderr.read_to_end(&mut Vec::new()).unwrap();
+                out
+            },
+        );
+        let (stdout, status, report) = match traced {
+            Ok(traced) => traced,
+            Err(e) => {
+                eprintln!("skipping: ptrace unavailable: {:#}", e);
+                return;
+            }
+        };
+
+        assert!(status.success());
+        assert_eq!(stdout, "done\n");
+        let network = report.network.unwrap();
+        let connection = network
+            .connections
+            .iter()
+            .find(|c| c.address == "127.0.0.1" && c.port == port)
+            .unwrap_or_else(|| panic!("{:?}", network));
+        assert!(connection.count >= 1);
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn tracer_records_executed_programs() {
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Stdio;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let script = tmp.path().join("build.sh");
+        std::fs::write(&script, "#!/bin/sh\n/bin/true\n/bin/true\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let true_bin = std::fs::canonicalize("/bin/true").unwrap();
+        let sh = std::fs::canonicalize("/bin/sh").unwrap();
+
+        // Relative exec from the script's directory: the tracer resolves it
+        let mut command = Command::new("/bin/sh");
+        command
+            .args(["-c", "./build.sh && /bin/true"])
+            .current_dir(tmp.path())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let traced = run_traced(
+            &mut command,
+            TraceOptions {
+                exec: true,
+                ..Default::default()
+            },
+            |mut stdout, mut stderr| {
+                stdout.read_to_end(&mut Vec::new()).unwrap();
+                stderr.read_to_end(&mut Vec::new()).unwrap();
+            },
+        );
+        let ((), status, report) = match traced {
+            Ok(traced) => traced,
+            Err(e) => {
+                eprintln!("skipping: ptrace unavailable: {:#}", e);
+                return;
+            }
+        };
+
+        assert!(status.success());
+        assert!(report.network.is_none());
+        let executables = report.executables.unwrap();
+        let find = |path: &std::path::Path| {
+            executables
+                .iter()
+                .find(|e| e.path == path.display().to_string())
+                .unwrap_or_else(|| panic!("{} not in {:?}", path.display(), executables))
+        };
+        // sh -c runs: the script (via sh), then true three times
+        assert!(find(&sh).count >= 2);
+        let script_exec = find(&script.canonicalize().unwrap());
+        assert_eq!(script_exec.count, 1);
+        assert_eq!(
+            script_exec.sha256.as_deref(),
+            Some(hash::hash_file(&script).unwrap().as_str())
+        );
+        let true_exec = find(&true_bin);
+        assert_eq!(true_exec.count, 3);
+        assert_eq!(
+            true_exec.sha256.as_deref(),
+            Some(hash::hash_file(&true_bin).unwrap().as_str())
+        );
+    }
+}
@@ -0,0 +1,369 @@
+// transcript.rs — Byte-exact capture of the build's stdout and stderr
+//
+// The build command's output is recorded in transcript.jsonl, one JSON
+// object per chunk read from either pipe, in arrival order:
+//
+//   {"time":"2026-01-01T00:00:00.123Z","stream":"stdout","text":"compiling\n"}
+//   {"time":"2026-01-01T00:00:00.456Z","stream":"stderr","base64":"/w=="}
+//
+// Chunks are the bytes each read() returned, unchanged: line endings,
+// carriage-return progress output and partial lines are kept as written,
+// so concatenating a stream's chunks gives back exactly what the build
+// wrote to it. A chunk that is valid UTF-8 is stored as `text`, anything
+// else as `base64`. A code point split across two reads is held back and
+// recorded with the next read, so UTF-8 output stays readable.
+//
+// The transcript is written to a temporary file outside the source tree
+// while the build runs, not held in memory, and moved into the bundle.
+// Recording stops at MAX_TRANSCRIPT_BYTES of output (the pipes are still
+// drained and echoed), with a final chunk on the "vbw" stream saying so.
+//
+// `vbw transcript` writes the recorded bytes back out, both streams
+// interleaved or one of them, e.g. to diff the output of two builds.
+//
+// Bundles from before transcript.jsonl have a line-oriented transcript.txt
+// instead; verify accepts either, never both.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::vbw::model::{TranscriptChunk, LEGACY_TRANSCRIPT_FILE, TRANSCRIPT_FILE};
+
+/// Maximum build output recorded, in bytes (128 MiB). Prevents a build
+/// that produces excessive output from filling the disk.
+pub const MAX_TRANSCRIPT_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Largest single read from a pipe, and so the largest chunk.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Chunks in flight between the pipe readers and the writer. A slow disk
+/// blocks the readers, and through the pipes the build, rather than
+/// growing memory.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// A transcript file written in a private temporary directory while the
+/// build runs, outside the source tree the build can see. Dropped without
+/// `persist`, it is removed.
+pub struct TranscriptFile {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl TranscriptFile {
+    pub fn new() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("vbw-transcript-{}", uuid::Uuid::new_v4()));
+        let mut builder = fs::DirBuilder::new();
+        // Owner-only: the build's output may include secrets
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&dir)
+            .with_context(|| format!("creating {}", dir.display()))?;
+        let path = dir.join(TRANSCRIPT_FILE);
+        Ok(TranscriptFile { dir, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the transcript to `to`, copying it if `to` is on another file
+    /// system.
+    pub fn persist(self, to: &Path) -> Result<()> {
+        if fs::rename(&self.path, to).is_err() {
+            fs::copy(&self.path, to)
+                .with_context(|| format!("moving transcript to {}", to.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TranscriptFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Bytes read from one of the build's output streams.
+struct Chunk {
+    time: String,
+    stream: &'static str,
+    bytes: Vec<u8>,
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// The transcript record for `bytes`: text if they are UTF-8, else base64.
+fn encode_chunk(time: String, stream: &str, bytes: &[u8]) -> TranscriptChunk {
+    let (text, base64) = match std::str::from_utf8(bytes) {
+        Ok(text) => (Some(text.to_string()), None),
+        Err(_) => (None, Some(B64.encode(bytes))),
+    };
+    TranscriptChunk {
+        time,
+        stream: stream.to_string(),
+        text,
+        base64,
+    }
+}
+
+/// Read `reader` until it closes, sending each read as a chunk. A UTF-8
+/// sequence cut off at the end of a read is carried into the next chunk.
+fn read_chunks<R: Read>(mut reader: R, stream: &'static str, tx: mpsc::SyncSender<Chunk>) {
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    let mut carried: Vec<u8> = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let mut bytes = std::mem::take(&mut carried);
+        bytes.extend_from_slice(&buf[..n]);
+        if let Err(e) = std::str::from_utf8(&bytes) {
+            // error_len() is None only for an incomplete sequence at the end
+            if e.error_len().is_none() {
+                carried = bytes.split_off(e.valid_up_to());
+            }
+        }
+        if bytes.is_empty() {
+            continue;
+        }
+        let chunk = Chunk {
+            time: now(),
+            stream,
+            bytes,
+        };
+        if tx.send(chunk).is_err() {
+            return;
+        }
+    }
+    if !carried.is_empty() {
+        let _ = tx.send(Chunk {
+            time: now(),
+            stream,
+            bytes: carried,
+        });
+    }
+}
+
+/// Capture the build's stdout and stderr into `out` as JSON Lines until
+/// both close, echoing the raw bytes to vbw's stderr as they arrive.
+/// Output beyond `limit` bytes is echoed but not recorded.
+pub fn capture<O, E, W>(stdout: O, stderr: E, mut out: W, limit: u64) -> Result<()>
+where
+    O: Read + Send,
+    E: Read + Send,
+    W: Write,
+{
+    let (tx, rx) = mpsc::sync_channel::<Chunk>(CHANNEL_CAPACITY);
+    thread::scope(|scope| {
+        let tx_err = tx.clone();
+        scope.spawn(move || read_chunks(stdout, "stdout", tx));
+        scope.spawn(move || read_chunks(stderr, "stderr", tx_err));
+
+        // Keep draining after a write error, so the build is not blocked
+        // on a full pipe; the error is reported once both streams close.
+        let mut echo = std::io::stderr();
+        let mut recorded: u64 = 0;
+        let mut truncated = false;
+        let mut failure: Option<std::io::Error> = None;
+        for chunk in rx {
+            let _ = echo.write_all(&chunk.bytes);
+            if truncated || failure.is_some() {
+                continue;
+            }
+            let room = (limit - recorded).min(chunk.bytes.len() as u64) as usize;
+            let mut records = Vec::new();
+            if room > 0 {
+                records.push(encode_chunk(chunk.time, chunk.stream, &chunk.bytes[..room]));
+                recorded += room as u64;
+            }
+            if room < chunk.bytes.len() {
+                truncated = true;
+                let note = format!(
+                    "transcript truncated at {} bytes (limit: {} bytes)",
+                    recorded, limit
+                );
+                eprintln!("\n[vbw] WARNING: {}", note);
+                records.push(TranscriptChunk {
+                    time: now(),
+                    stream: "vbw".to_string(),
+                    text: Some(note),
+                    base64: None,
+                });
+            }
+            for record in records {
+                let line = serde_json::to_string(&record).expect("chunk serializes");
+                if let Err(e) = writeln!(out, "{}", line) {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => out.flush(),
+        }
+        .context("writing the build transcript")
+    })
+}
+
+/// The bytes a transcript record holds.
+pub fn chunk_bytes(chunk: &TranscriptChunk) -> Result<Vec<u8>> {
+    match (&chunk.text, &chunk.base64) {
+        (Some(text), None) => Ok(text.as_bytes().to_vec()),
+        (None, Some(b64)) => B64.decode(b64).context("decoding base64 chunk"),
+        _ => anyhow::bail!("chunk must have exactly one of text and base64"),
+    }
+}
+
+/// Write the build output recorded in `bundle_dir` (a bundle or a step)
+/// to `out` byte for byte: stdout and stderr interleaved in arrival
+/// order, or only `stream`. Notes on the "vbw" stream are written only
+/// when asked for. The bundle is not verified.
+pub fn replay<W: Write>(bundle_dir: &Path, stream: Option<&str>, mut out: W) -> Result<()> {
+    let path = bundle_dir.join(TRANSCRIPT_FILE);
+    if !path.exists() && bundle_dir.join(LEGACY_TRANSCRIPT_FILE).exists() {
+        anyhow::bail!(
+            "{} has a line-oriented {} from an older vbw; read it directly",
+            bundle_dir.display(),
+            LEGACY_TRANSCRIPT_FILE
+        );
+    }
+    let file = fs::File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("reading {}", path.display()))?;
+        let chunk: TranscriptChunk = serde_json::from_str(&line)
+            .with_context(|| format!("{} line {}", path.display(), index + 1))?;
+        let wanted = match stream {
+            Some(stream) => chunk.stream == stream,
+            None => chunk.stream != "vbw",
+        };
+        if wanted {
+            let bytes = chunk_bytes(&chunk)
+                .with_context(|| format!("{} line {}", path.display(), index + 1))?;
+            out.write_all(&bytes)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(transcript: &[u8]) -> Vec<TranscriptChunk> {
+        std::str::from_utf8(transcript)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// The bytes recorded for `stream`, in order.
+    fn stream_bytes(chunks: &[TranscriptChunk], stream: &str) -> Vec<u8> {
+        chunks
+            .iter()
+            .filter(|c| c.stream == stream)
+            .flat_map(|c| chunk_bytes(c).unwrap())
+            .collect()
+    }
+
+    /// Yields its data in reads of at most `step` bytes.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn capture_is_byte_exact_per_stream() {
+        // CRLF, carriage-return progress, invalid UTF-8, no final newline
+        let stdout: &[u8] = b"line one\r\n 10%\r 50%\r100%\nbad \xff\xfe bytes\nno newline";
+        // A multi-byte code point split across reads
+        let stderr = "warning: caf\u{e9} \u{1f980}\n".as_bytes();
+        let mut out = Vec::new();
+        capture(
+            Trickle {
+                data: stdout,
+                step: 7,
+            },
+            Trickle {
+                data: stderr,
+                step: 3,
+            },
+            &mut out,
+            MAX_TRANSCRIPT_BYTES,
+        )
+        .unwrap();
+
+        let chunks = parse(&out);
+        assert_eq!(stream_bytes(&chunks, "stdout"), stdout);
+        assert_eq!(stream_bytes(&chunks, "stderr"), stderr);
+        // Split code points are carried over, so stderr stays text
+        assert!(chunks
+            .iter()
+            .filter(|c| c.stream == "stderr")
+            .all(|c| c.text.is_some()));
+        assert!(chunks.iter().any(|c| c.base64.is_some()));
+        assert!(chunks.iter().all(|c| c.time.ends_with('Z')));
+
+        // Replay gives the streams back
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join(TRANSCRIPT_FILE), &out).unwrap();
+        let mut replayed = Vec::new();
+        replay(tmp.path(), Some("stdout"), &mut replayed).unwrap();
+        assert_eq!(replayed, stdout);
+        let mut both = Vec::new();
+        replay(tmp.path(), None, &mut both).unwrap();
+        assert_eq!(both.len(), stdout.len() + stderr.len());
+    }
+
+    #[test]
+    fn capture_stops_recording_at_the_limit() {
+        let stdout = vec![b'x'; 100];
+        let mut out = Vec::new();
+        capture(stdout.as_slice(), std::io::empty(), &mut out, 60).unwrap();
+
+        let chunks = parse(&out);
+        assert_eq!(stream_bytes(&chunks, "stdout"), vec![b'x'; 60]);
+        let note = chunks.last().unwrap();
+        assert_eq!(note.stream, "vbw");
+        assert!(note
+            .text
+            .as_deref()
+            .unwrap()
+            .contains("truncated at 60 bytes"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transcript_directory_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let transcript = TranscriptFile::new().unwrap();
+        let dir = transcript.path().parent().unwrap().to_path_buf();
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        drop(transcript);
+        assert!(!dir.exists());
+    }
+}
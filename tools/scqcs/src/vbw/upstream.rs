@@ -0,0 +1,229 @@
+// upstream.rs — Provenance across projects: artifacts from upstream bundles
+//
+// A library's outputs are often a downstream product's inputs. The
+// downstream build declares each consumed file with
+// `vbw build --upstream <bundle>:<file>`: the file is hashed and must match
+// an artifact in the upstream bundle's outputs.json. It is recorded as a
+// material of kind "vbw-artifact" carrying the upstream bundle's canonical
+// manifest hash and the artifact's path there, so the builder signature
+// covers the link.
+//
+// `vbw verify --upstream <bundle>` checks each vbw-artifact material
+// against the supplied upstream bundles: the bundle with that manifest
+// hash must check out (manifest hash, builder signatures, outputs.json
+// hash) and list the artifact with the same SHA-256.
+//
+// As with verify-chain, only the upstream manifest and outputs.json are
+// checked, not the upstream artifacts on disk; verify the upstream bundle
+// itself for that. Which upstream builder keys to trust is up to the
+// verifier; the key id is printed for review.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::hash;
+use crate::vbw::canonical;
+use crate::vbw::model::*;
+use crate::vbw::verify::check_builder_signatures;
+
+/// An upstream bundle whose manifest, signatures and outputs checked out.
+pub struct UpstreamBundle {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+    pub manifest_hash: String,
+    pub outputs: Outputs,
+}
+
+/// Load an upstream bundle and check its manifest hash, builder
+/// signature(s) and outputs.json hash. Any problem is an error.
+pub fn load_upstream(dir: &Path) -> Result<UpstreamBundle> {
+    let manifest_path = dir.join("manifest.json");
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(&manifest_path)
+            .with_context(|| format!("reading {}", manifest_path.display()))?,
+    )
+    .context("parsing manifest.json")?;
+    let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+    let manifest_hash = hash::sha256_hex(&canonical_bytes);
+
+    let mut errors = Vec::new();
+    let stored_path = dir.join("hashes/manifest.sha256");
+    let stored = fs::read_to_string(&stored_path)
+        .with_context(|| format!("reading {}", stored_path.display()))?;
+    if stored.trim() != manifest_hash {
+        errors.push(format!(
+            "manifest hash mismatch (stored={}, computed={})",
+            stored.trim(),
+            manifest_hash
+        ));
+    }
+    check_builder_signatures(
+        &dir.join("signatures"),
+        &manifest.builder_identity,
+        &canonical_bytes,
+        "builder signature",
+        &mut errors,
+    );
+
+    let outputs_path = dir.join("outputs.json");
+    let outputs_json = fs::read_to_string(&outputs_path)
+        .with_context(|| format!("reading {}", outputs_path.display()))?;
+    let outputs_hash = hash::sha256_hex(outputs_json.as_bytes());
+    if outputs_hash != manifest.outputs_hash {
+        errors.push(format!(
+            "outputs.json hash mismatch: manifest={}, computed={}",
+            manifest.outputs_hash, outputs_hash
+        ));
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("{}", errors.join("; "));
+    }
+    let outputs: Outputs = serde_json::from_str(&outputs_json).context("parsing outputs.json")?;
+
+    Ok(UpstreamBundle {
+        dir: dir.to_path_buf(),
+        manifest,
+        manifest_hash,
+        outputs,
+    })
+}
+
+/// Resolve `--upstream <bundle>:<file>` for `vbw build`. Each local file is
+/// hashed and matched to an artifact of the upstream bundle (preferring
+/// one with the same file name when several share the hash). Returns them
+/// as vbw-artifact materials.
+pub fn resolve_upstream_inputs(specs: &[String]) -> Result<Vec<MaterialEntry>> {
+    let mut materials = Vec::new();
+    for spec in specs {
+        let Some((bundle, file)) = spec.split_once(':') else {
+            anyhow::bail!("--upstream '{}' must be <bundle>:<file>", spec);
+        };
+        let upstream = load_upstream(Path::new(bundle))
+            .with_context(|| format!("upstream bundle {}", bundle))?;
+        let sha256 = hash::hash_file(Path::new(file))
+            .with_context(|| format!("hashing upstream input {}", file))?;
+
+        let file_name = Path::new(file).file_name();
+        let candidates: Vec<&Artifact> = upstream
+            .outputs
+            .artifacts
+            .iter()
+            .filter(|a| a.sha256 == sha256)
+            .collect();
+        let Some(artifact) = candidates
+            .iter()
+            .find(|a| Path::new(&a.path).file_name() == file_name)
+            .or(candidates.first())
+        else {
+            anyhow::bail!(
+                "{} (sha256 {}) is not an artifact of upstream bundle {} ({})",
+                file,
+                sha256,
+                bundle,
+                upstream.manifest.project.name
+            );
+        };
+
+        eprintln!(
+            "[vbw] Upstream input {}: {} from {} (build {})",
+            file, artifact.path, upstream.manifest.project.name, upstream.manifest.build_id
+        );
+        materials.push(MaterialEntry {
+            name: file.to_string(),
+            kind: VBW_ARTIFACT_KIND.to_string(),
+            source: Some(upstream.manifest.project.name.clone()),
+            sha256,
+            upstream_manifest_hash: Some(upstream.manifest_hash.clone()),
+            upstream_path: Some(artifact.path.clone()),
+            archive_sha256: None,
+            extracted_tree_hash: None,
+        });
+    }
+    Ok(materials)
+}
+
+/// Check every vbw-artifact material against the upstream bundles in
+/// `upstream_dirs` (from `vbw verify --upstream`).
+///
+/// A material whose upstream bundle was not supplied is a warning (its
+/// provenance is unchecked); a supplied bundle that fails its own checks,
+/// or that does not list the artifact with the recorded SHA-256, is an
+/// error.
+pub fn check_upstream_inputs(
+    materials: &MaterialsLock,
+    upstream_dirs: &[PathBuf],
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let mut upstreams: Vec<UpstreamBundle> = Vec::new();
+    for dir in upstream_dirs {
+        match load_upstream(dir) {
+            Ok(upstream) => upstreams.push(upstream),
+            Err(e) => errors.push(format!("Upstream bundle {}: {:#}", dir.display(), e)),
+        }
+    }
+
+    let mut used = vec![false; upstreams.len()];
+    for material in materials
+        .materials
+        .iter()
+        .filter(|m| m.kind == VBW_ARTIFACT_KIND)
+    {
+        let (Some(manifest_hash), Some(path)) =
+            (&material.upstream_manifest_hash, &material.upstream_path)
+        else {
+            errors.push(format!(
+                "Upstream input {} lacks upstream_manifest_hash or upstream_path",
+                material.name
+            ));
+            continue;
+        };
+        let Some(index) = upstreams
+            .iter()
+            .position(|u| u.manifest_hash == *manifest_hash)
+        else {
+            warnings.push(format!(
+                "Upstream input {} comes from bundle {} (project {}), which was not supplied \
+                 with --upstream (provenance not checked)",
+                material.name,
+                manifest_hash,
+                material.source.as_deref().unwrap_or("unknown")
+            ));
+            continue;
+        };
+        used[index] = true;
+        let upstream = &upstreams[index];
+        if upstream
+            .outputs
+            .artifacts
+            .iter()
+            .any(|a| a.path == *path && a.sha256 == material.sha256)
+        {
+            eprintln!(
+                "[vbw] Upstream input {}: OK ({} in {}, builder key '{}')",
+                material.name,
+                path,
+                upstream.manifest.project.name,
+                upstream.manifest.builder_identity.key_id
+            );
+        } else {
+            errors.push(format!(
+                "Upstream input {} (sha256 {}) does not match artifact {} of upstream bundle {}",
+                material.name,
+                material.sha256,
+                path,
+                upstream.dir.display()
+            ));
+        }
+    }
+
+    for (upstream, used) in upstreams.iter().zip(used) {
+        if !used {
+            warnings.push(format!(
+                "Upstream bundle {} is not referenced by any material",
+                upstream.dir.display()
+            ));
+        }
+    }
+}
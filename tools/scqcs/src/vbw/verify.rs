@@ -1,7 +1,7 @@
 // verify.rs — VBW bundle verification (strict, fail-closed)
 //
 // Reads a witness bundle from disk, recomputes all hashes from canonical
-// bytes, verifies the Ed25519 signature, validates bundle completeness,
+// bytes, verifies the builder signature, validates bundle completeness,
 // and checks policy compliance.
 //
 // FAIL-CLOSED DESIGN:
@@ -11,6 +11,7 @@
 //   - Invalid or missing signature → UNVERIFIED
 //   - Path traversal attempts (.. or absolute paths) → UNVERIFIED
 //   - Symlinks that escape the bundle → UNVERIFIED
+//   - Builder key outside its policy validity period, or revoked → UNVERIFIED
 //   - All failure paths return non-zero exit code
 //
 // SIGNING: Signature and manifest hash are verified against canonical
@@ -42,14 +43,17 @@ pub enum Verdict {
 }
 
 /// The set of files that MUST exist in a valid VBW bundle.
+///
+/// The builder signature is also required, but its filename depends on the
+/// key algorithm declared in the manifest (`signatures/builder.<alg>.sig`),
+/// so it is checked after the manifest is parsed. So is the transcript,
+/// which has a different name in older bundles (see find_transcript).
 const REQUIRED_FILES: &[&str] = &[
     "manifest.json",
     "environment.json",
     "materials.lock.json",
     "outputs.json",
-    "transcript.txt",
     "policy.json",
-    "signatures/builder.ed25519.sig",
     "hashes/manifest.sha256",
 ];
 
@@ -61,8 +65,22 @@ struct ComponentData {
     policy: Option<Policy>,
 }
 
-/// Verify a VBW witness bundle (strict, fail-closed).
-pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
+/// Options for `run_verify` (from the `vbw verify` CLI flags).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VerifyOptions<'a> {
+    /// Bundles that vbw-artifact materials are checked against.
+    pub upstream_dirs: &'a [PathBuf],
+    /// JWKS to check the OIDC token binding the builder key against.
+    pub oidc_jwks: Option<&'a Path>,
+}
+
+/// Verify a VBW witness bundle (strict, fail-closed). vbw-artifact
+/// materials are checked against the bundles in `options.upstream_dirs`.
+pub fn run_verify(bundle_dir: &Path, options: &VerifyOptions) -> Result<Verdict> {
+    let VerifyOptions {
+        upstream_dirs,
+        oidc_jwks,
+    } = *options;
     let mut errors: Vec<String> = Vec::new();
     let mut warnings: Vec<String> = Vec::new();
 
@@ -92,9 +110,14 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
             errors.push(format!("Required file missing: {}", required));
         }
     }
-    if !errors.is_empty() {
-        return emit_verdict(errors, warnings);
-    }
+    let transcript_file = match find_transcript(&canonical_bundle, "") {
+        Ok(file) if errors.is_empty() => file,
+        Ok(_) => return emit_verdict(errors, warnings),
+        Err(e) => {
+            errors.push(e);
+            return emit_verdict(errors, warnings);
+        }
+    };
 
     // 3. Check for unexpected files (strict bundle policy)
     check_unexpected_files(&canonical_bundle, &mut errors)?;
@@ -118,6 +141,10 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
     eprintln!("[vbw] Verifying build: {}", manifest.build_id);
     eprintln!("[vbw] Project: {}", manifest.project.name);
     eprintln!("[vbw] Git commit: {}", manifest.git.commit);
+    if let (Some(sequence), Some(previous)) = (manifest.sequence, &manifest.previous_manifest_hash) {
+        eprintln!("[vbw] Chain: sequence {}, previous manifest {}", sequence, previous);
+    }
+    crate::vbw::chain::check_link_fields(&manifest, &mut errors);
 
     // 6. Recompute canonical manifest bytes from parsed manifest
     //    This is the critical step: we don't trust the bytes on disk,
@@ -141,23 +168,85 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
         eprintln!("[vbw] Manifest hash (canonical): OK");
     }
 
-    // 8. Verify builder signature against canonical manifest bytes
-    let sig_path = canonical_bundle.join("signatures/builder.ed25519.sig");
-    let signature = fs::read_to_string(&sig_path)
-        .with_context(|| format!("reading {}", sig_path.display()))?
-        .trim()
-        .to_string();
-
-    match sign::verify(
-        &manifest.builder_identity.public_key_ed25519,
-        &canonical_bytes,
-        &signature,
-    ) {
-        Ok(true) => eprintln!("[vbw] Builder signature (over canonical bytes): OK"),
-        Ok(false) => errors.push(
-            "Builder signature INVALID (verified against canonical manifest bytes)".to_string(),
-        ),
-        Err(e) => errors.push(format!("Signature verification error: {}", e)),
+    // 8. Verify builder signature(s) against canonical manifest bytes, using
+    //    the algorithm declared in builder_identity (Ed25519 if absent).
+    //    In hybrid mode the post-quantum signature is required as well;
+    //    both must verify.
+    let identity = &manifest.builder_identity;
+    if identity.algorithm().is_post_quantum() {
+        errors.push(format!(
+            "builder_identity.key_type {} is only valid as the hybrid half of a key",
+            identity.algorithm().as_str()
+        ));
+    }
+    if let Some(hybrid) = &identity.hybrid {
+        if !hybrid.key_type.is_post_quantum() {
+            errors.push(format!(
+                "builder_identity.hybrid.key_type must be post-quantum, found {}",
+                hybrid.key_type.as_str()
+            ));
+        }
+    }
+    // An ssh-agent fingerprint is informational, but a wrong one would
+    // mislead reviewers matching it against their SSH keys, so check it.
+    if let Some(fingerprint) = &identity.ssh_fingerprint {
+        let expected = match identity.algorithm() {
+            sign::Algorithm::Ed25519 => {
+                sign::ed25519_fingerprint(identity.public_key_b64()).ok()
+            }
+            _ => None,
+        };
+        if expected.as_deref() != Some(fingerprint.as_str()) {
+            errors.push(format!(
+                "builder_identity.ssh_fingerprint {} does not match the builder public key",
+                fingerprint
+            ));
+        }
+    }
+    let required_sigs = identity.required_signatures();
+    for alg in sign::Algorithm::ALL {
+        let name = builder_signature_filename(*alg);
+        if !required_sigs.iter().any(|(_, _, f)| *f == name)
+            && canonical_bundle.join("signatures").join(&name).exists()
+        {
+            errors.push(format!(
+                "Unexpected file in bundle: signatures/{} (manifest does not declare a {} key)",
+                name,
+                alg.as_str()
+            ));
+        }
+    }
+    let mut signature_missing = false;
+    for (alg, public_key, sig_filename) in &required_sigs {
+        let sig_path = canonical_bundle.join("signatures").join(sig_filename);
+        if !sig_path.exists() {
+            errors.push(format!("Required file missing: signatures/{}", sig_filename));
+            signature_missing = true;
+            continue;
+        }
+        let signature = fs::read_to_string(&sig_path)
+            .with_context(|| format!("reading {}", sig_path.display()))?
+            .trim()
+            .to_string();
+
+        match sign::verify_with(*alg, public_key, &canonical_bytes, &signature) {
+            Ok(true) => eprintln!(
+                "[vbw] Builder signature ({}, over canonical bytes): OK",
+                alg.as_str()
+            ),
+            Ok(false) => errors.push(format!(
+                "Builder signature INVALID ({}, verified against canonical manifest bytes)",
+                alg.as_str()
+            )),
+            Err(e) => errors.push(format!(
+                "Signature verification error ({}): {}",
+                alg.as_str(),
+                e
+            )),
+        }
+    }
+    if signature_missing {
+        return emit_verdict(errors, warnings);
     }
 
     // 9. Load and verify component files
@@ -178,6 +267,9 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
             components.environment = Some(v);
         }),
     );
+    if let Some(ci) = components.environment.as_ref().and_then(|e| e.ci.as_ref()) {
+        print_ci(ci);
+    }
     verify_and_parse_component(
         &canonical_bundle,
         "materials.lock.json",
@@ -199,7 +291,109 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
         }),
     );
 
-    // 10. Verify policy reference
+    // The transcript is signed in bundles that record its hash, which all
+    // bundles with a transcript.jsonl do
+    match &manifest.transcript_hash {
+        Some(expected) => verify_and_parse_component(
+            &canonical_bundle,
+            transcript_file,
+            expected,
+            &mut errors,
+            &mut warnings,
+            |data| match transcript_file {
+                TRANSCRIPT_FILE => data
+                    .lines()
+                    .try_for_each(|line| serde_json::from_str::<TranscriptChunk>(line).map(|_| ())),
+                _ => Ok(()),
+            },
+        ),
+        None if transcript_file == TRANSCRIPT_FILE => errors.push(format!(
+            "Unexpected file in bundle: {} (manifest has no transcript_hash)",
+            TRANSCRIPT_FILE
+        )),
+        None => {}
+    }
+    // A bundle can witness a failed build, but it vouches for nothing
+    if let Some(result) = &manifest.build_result {
+        eprintln!("[vbw] Build command: {}", result.summary());
+        if !result.success {
+            errors.push(format!(
+                "Build command failed ({}): this bundle witnesses a failed build",
+                result.describe_exit()
+            ));
+        }
+    }
+
+    // The Mode B proxy's connection log, if the build ran through it
+    match &manifest.network_log_hash {
+        Some(expected) => verify_and_parse_component(
+            &canonical_bundle,
+            NETWORK_LOG_FILE,
+            expected,
+            &mut errors,
+            &mut warnings,
+            |data| serde_json::from_str::<NetworkLog>(data).map(|_| ()),
+        ),
+        None if canonical_bundle.join(NETWORK_LOG_FILE).exists() => errors.push(format!(
+            "Unexpected file in bundle: {} (manifest has no network_log_hash)",
+            NETWORK_LOG_FILE
+        )),
+        None => {}
+    }
+
+    // Network access traced with --trace-network, shown for review
+    match &manifest.network_access_hash {
+        Some(expected) => verify_and_parse_component(
+            &canonical_bundle,
+            NETWORK_ACCESS_FILE,
+            expected,
+            &mut errors,
+            &mut warnings,
+            |data| serde_json::from_str::<NetworkAccessLog>(data).map(|v| print_network_access(&v)),
+        ),
+        None if canonical_bundle.join(NETWORK_ACCESS_FILE).exists() => errors.push(format!(
+            "Unexpected file in bundle: {} (manifest has no network_access_hash)",
+            NETWORK_ACCESS_FILE
+        )),
+        None => {}
+    }
+
+    // Files read by the build, traced with --trace-inputs
+    match &manifest.inputs_hash {
+        Some(expected) => verify_and_parse_component(
+            &canonical_bundle,
+            INPUTS_FILE,
+            expected,
+            &mut errors,
+            &mut warnings,
+            |data| serde_json::from_str::<InputsLog>(data).map(|v| print_inputs(&v)),
+        ),
+        None if canonical_bundle.join(INPUTS_FILE).exists() => errors.push(format!(
+            "Unexpected file in bundle: {} (manifest has no inputs_hash)",
+            INPUTS_FILE
+        )),
+        None => {}
+    }
+
+    // 10. Verify pipeline steps (sub-witnesses under steps/) and that every
+    //     step's inputs match an earlier step's outputs. Artifacts taken
+    //     from other projects must match their --upstream bundles.
+    verify_steps(
+        &canonical_bundle,
+        &manifest,
+        components.materials_lock.as_ref(),
+        &mut errors,
+    )?;
+    if let Some(ref materials) = components.materials_lock {
+        crate::vbw::upstream::check_upstream_inputs(
+            materials,
+            upstream_dirs,
+            &mut errors,
+            &mut warnings,
+        );
+    }
+
+    // 11. Verify policy reference
     let policy_in_bundle = canonical_bundle.join("policy.json");
     let policy_data = fs::read_to_string(&policy_in_bundle).context("reading policy.json")?;
     let policy_hash = hash::sha256_hex(policy_data.as_bytes());
@@ -219,7 +413,28 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
         )),
     }
 
-    // 11. Verify co-signatures against trusted cosigner keys from policy.
+    // 12. Verify the builder signature's timestamp token (if any) and check
+    //     builder key validity/revocation at the build time it establishes.
+    verify_build_time(
+        &canonical_bundle,
+        &manifest,
+        components.policy.as_ref(),
+        &mut errors,
+        &mut warnings,
+    )?;
+
+    // The CI identity the builder key is bound to, if any
+    verify_oidc_binding(
+        &canonical_bundle,
+        &manifest,
+        components.environment.as_ref(),
+        components.policy.as_ref(),
+        oidc_jwks,
+        &mut errors,
+        &mut warnings,
+    )?;
+
+    // 13. Verify co-signatures against trusted cosigner keys from policy.
     verify_cosignatures(
         &canonical_bundle,
         &canonical_bytes,
@@ -228,7 +443,7 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
         &mut warnings,
     );
 
-    // 12. Verify output artifacts exist and match
+    // 14. Verify output artifacts exist and match
     if let Some(ref outputs) = components.outputs {
         for artifact in &outputs.artifacts {
             let artifact_path = PathBuf::from(&artifact.path);
@@ -294,7 +509,7 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
         );
     }
 
-    // 13. Check enforcement consistency
+    // 15. Check enforcement consistency
     if let Some(ref enforcement) = manifest.enforcement {
         if let Some(ref policy) = components.policy {
             if enforcement.mode_requested != policy.requirements.reproducibility.mode {
@@ -310,9 +525,25 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
                 ));
             }
         }
+        if let Some(violations) = enforcement.network_violations.as_ref().filter(|v| !v.is_empty()) {
+            warnings.push(format!(
+                "Build tried to reach hosts outside the network allowlist (blocked by the proxy): {}",
+                violations.join(", ")
+            ));
+        }
+        if let Some(untracked) = enforcement
+            .untracked_inputs
+            .as_ref()
+            .filter(|u| !u.is_empty())
+        {
+            warnings.push(format!(
+                "Build read files not tracked by git (provenance gap): {}",
+                untracked.join(", ")
+            ));
+        }
     }
 
-    // 14. Check policy compliance
+    // 16. Check policy compliance
     if let Some(ref policy) = components.policy {
         check_policy_compliance(
             &manifest,
@@ -321,11 +552,44 @@ pub fn run_verify(bundle_dir: &Path) -> Result<Verdict> {
             components.materials_lock.as_ref(),
             &mut warnings,
         );
+        if let Some(requirement) = &policy.requirements.ci {
+            crate::vbw::ci::check_policy(
+                components.environment.as_ref().and_then(|e| e.ci.as_ref()),
+                requirement,
+                &mut errors,
+            );
+        }
+        if let Some(requirement) = &policy.requirements.container {
+            crate::vbw::container::check_policy(
+                components.environment.as_ref().and_then(|e| e.container.as_ref()),
+                requirement,
+                &mut errors,
+                &mut warnings,
+            );
+        }
     }
 
     emit_verdict(errors, warnings)
 }
 
+/// The transcript in `dir`: transcript.jsonl, or transcript.txt in bundles
+/// from before it. Exactly one must be present; `prefix` locates `dir` in
+/// the error.
+fn find_transcript(dir: &Path, prefix: &str) -> Result<&'static str, String> {
+    match (
+        dir.join(TRANSCRIPT_FILE).exists(),
+        dir.join(LEGACY_TRANSCRIPT_FILE).exists(),
+    ) {
+        (true, false) => Ok(TRANSCRIPT_FILE),
+        (false, true) => Ok(LEGACY_TRANSCRIPT_FILE),
+        (true, true) => Err(format!(
+            "Unexpected file in bundle: {}{} (bundle also has {}{})",
+            prefix, LEGACY_TRANSCRIPT_FILE, prefix, TRANSCRIPT_FILE
+        )),
+        (false, false) => Err(format!("Required file missing: {}{}", prefix, TRANSCRIPT_FILE)),
+    }
+}
+
 /// Enumerate all files in the bundle and reject unexpected ones.
 ///
 /// This is the strict bundle policy: only known files are allowed.
@@ -335,6 +599,32 @@ fn check_unexpected_files(bundle_dir: &Path, errors: &mut Vec<String>) -> Result
     for f in REQUIRED_FILES {
         allowed.insert(bundle_dir.join(f));
     }
+    // Builder signatures (and their timestamp tokens) for any supported
+    // algorithm; verify checks the ones matching the manifest's key_type and
+    // rejects the rest.
+    for alg in sign::Algorithm::ALL {
+        allowed.insert(
+            bundle_dir
+                .join("signatures")
+                .join(builder_signature_filename(*alg)),
+        );
+        allowed.insert(
+            bundle_dir
+                .join("signatures")
+                .join(builder_timestamp_filename(*alg)),
+        );
+    }
+    // The transcript under either name; find_transcript rejects both at once
+    allowed.insert(bundle_dir.join(TRANSCRIPT_FILE));
+    allowed.insert(bundle_dir.join(LEGACY_TRANSCRIPT_FILE));
+    // OIDC token; checked against builder_identity.oidc
+    allowed.insert(bundle_dir.join("signatures").join(OIDC_TOKEN_FILE));
+    // Mode B proxy log; verify checks it against manifest.network_log_hash
+    allowed.insert(bundle_dir.join(NETWORK_LOG_FILE));
+    // Traced network access; checked against manifest.network_access_hash
+    allowed.insert(bundle_dir.join(NETWORK_ACCESS_FILE));
+    // Traced file reads; checked against manifest.inputs_hash
+    allowed.insert(bundle_dir.join(INPUTS_FILE));
     // Allow the signatures/ and hashes/ directories themselves
     allowed.insert(bundle_dir.join("signatures"));
     allowed.insert(bundle_dir.join("hashes"));
@@ -344,7 +634,10 @@ fn check_unexpected_files(bundle_dir: &Path, errors: &mut Vec<String>) -> Result
     for path in &actual_files {
         if path.is_dir() {
             // Allow known subdirectories
-            if *path == bundle_dir.join("signatures") || *path == bundle_dir.join("hashes") {
+            if *path == bundle_dir.join("signatures")
+                || *path == bundle_dir.join("hashes")
+                || is_step_path(bundle_dir, path, true)
+            {
                 continue;
             }
             errors.push(format!(
@@ -353,14 +646,16 @@ fn check_unexpected_files(bundle_dir: &Path, errors: &mut Vec<String>) -> Result
             ));
         } else if !allowed.contains(path) {
             // Allow additional co-signature files in signatures/ (from attest command).
-            // Strictly require the *.ed25519.sig naming pattern to prevent arbitrary
-            // data from being smuggled into the bundle via a .sig extension.
+            // Strictly require the *.cosig.json (statement) or legacy *.ed25519.sig
+            // naming pattern to prevent arbitrary data from being smuggled into
+            // the bundle via a signature-like extension.
+            if is_step_path(bundle_dir, path, false) {
+                // Step sub-witness file; verified in verify_steps()
+                continue;
+            }
             if path.starts_with(bundle_dir.join("signatures")) {
                 if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if name.ends_with(".ed25519.sig")
-                        && name.len() > ".ed25519.sig".len()
-                        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
-                    {
+                    if is_cosignature_filename(name) {
                         // Co-signature file allowed; verified in verify_cosignatures()
                         continue;
                     }
@@ -375,116 +670,765 @@ fn check_unexpected_files(bundle_dir: &Path, errors: &mut Vec<String>) -> Result
     Ok(())
 }
 
-/// Check that no symlinks in the bundle escape the bundle directory.
-fn check_symlink_safety(bundle_dir: &Path, errors: &mut Vec<String>) -> Result<()> {
-    let entries = walk_dir(bundle_dir)?;
-    for entry in &entries {
-        // Check if entry is a symlink
-        let metadata = entry.symlink_metadata()?;
-        if metadata.file_type().is_symlink() {
-            let target = fs::read_link(entry)?;
-            let resolved = if target.is_absolute() {
-                target.clone()
-            } else {
-                entry.parent().unwrap_or(bundle_dir).join(&target)
-            };
-            let resolved_canonical = resolved.canonicalize().unwrap_or_else(|_| resolved.clone());
-            if !resolved_canonical.starts_with(bundle_dir) {
-                errors.push(format!(
-                    "Symlink escapes bundle: {} -> {} (resolves outside {})",
-                    entry.display(),
-                    target.display(),
-                    bundle_dir.display()
-                ));
+/// Tolerated disagreement between the TSA's genTime and created_at, for
+/// clock skew. created_at is set just before signing, so it may trail
+/// genTime but should never be later.
+const TIMESTAMP_SKEW_SECS: i64 = 300;
+
+/// Gap between created_at and genTime above which the timestamp is reported
+/// as late (the signature may have been made long after the claimed time).
+const TIMESTAMP_LATE_SECS: i64 = 3600;
+
+/// Check the OIDC ID token binding the builder key to a CI identity: it
+/// must match builder_identity.oidc, be bound to the builder key, name the
+/// manifest's commit, ref and repository, and cover created_at. Its
+/// signature is checked against `jwks` when given; otherwise that is a
+/// warning, or an error if the policy sets signing.require_verified_oidc.
+fn verify_oidc_binding(
+    bundle_dir: &Path,
+    manifest: &Manifest,
+    environment: Option<&Environment>,
+    policy: Option<&Policy>,
+    jwks: Option<&Path>,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    let required = policy
+        .and_then(|p| p.requirements.signing.as_ref())
+        .and_then(|s| s.require_verified_oidc)
+        .unwrap_or(false);
+    let identity = &manifest.builder_identity;
+    let token_path = bundle_dir.join("signatures").join(OIDC_TOKEN_FILE);
+    let Some(binding) = &identity.oidc else {
+        if token_path.exists() {
+            errors.push(format!(
+                "Unexpected file in bundle: signatures/{} (manifest has no builder_identity.oidc)",
+                OIDC_TOKEN_FILE
+            ));
+        }
+        if required {
+            errors.push(
+                "Policy requires a verified OIDC identity, but the builder key has none"
+                    .to_string(),
+            );
+        }
+        return Ok(());
+    };
+    if !token_path.exists() {
+        errors.push(format!("Required file missing: signatures/{}", OIDC_TOKEN_FILE));
+        return Ok(());
+    }
+    let token = fs::read_to_string(&token_path)
+        .with_context(|| format!("reading {}", token_path.display()))?;
+    let decoded = match crate::oidc::decode(&token) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            errors.push(format!("OIDC token is malformed: {:#}", e));
+            return Ok(());
+        }
+    };
+    let claims = &decoded.claims;
+    eprintln!("[vbw] OIDC identity: {} (issuer {})", claims.sub, claims.iss);
+
+    if OidcBinding::from_token(&token, claims, &binding.audience) != *binding {
+        errors.push(format!(
+            "builder_identity.oidc does not match the claims of signatures/{}",
+            OIDC_TOKEN_FILE
+        ));
+    }
+    if identity.issuer.as_deref() != Some(claims.iss.as_str()) {
+        errors.push(format!(
+            "builder_identity.issuer is not the OIDC token's issuer {}",
+            claims.iss
+        ));
+    }
+    match crate::oidc::key_audience(identity.public_key_b64()) {
+        Ok(audience) if audience == binding.audience && claims.has_audience(&audience) => {}
+        _ => errors.push("OIDC token audience is not bound to the builder key".to_string()),
+    }
+
+    // The claims must describe the commit and ref the manifest records
+    if let Some(sha) = claims.sha.as_deref().filter(|sha| *sha != manifest.git.commit) {
+        errors.push(format!(
+            "OIDC token is for commit {}, but the manifest records {}",
+            sha, manifest.git.commit
+        ));
+    }
+    // A detached checkout records no branch, so a branch is only compared
+    // when recorded; a tag build must record the tag.
+    if let Some(token_ref) = claims.git_ref.as_deref() {
+        let mismatch = if let Some(branch) = token_ref.strip_prefix("refs/heads/") {
+            manifest.git.branch.as_deref().is_some_and(|b| b != branch)
+        } else if let Some(tag) = token_ref.strip_prefix("refs/tags/") {
+            manifest.git.tag.as_deref() != Some(tag)
+        } else {
+            false
+        };
+        if mismatch {
+            errors.push(format!(
+                "OIDC token is for {}, which does not match the manifest's git branch/tag",
+                token_ref
+            ));
+        }
+    }
+    // The repository must be the one the CI context (or else the project)
+    // records
+    if let Some(repository) = claims.repository.as_deref() {
+        let ci_repository = environment
+            .and_then(|e| e.ci.as_ref())
+            .and_then(|ci| ci.repository.as_deref());
+        let mismatch = match (ci_repository, manifest.project.repo_url.as_deref()) {
+            (Some(recorded), _) => recorded != repository,
+            (None, Some(url)) => {
+                let url = url.trim_end_matches('/');
+                let url = url.strip_suffix(".git").unwrap_or(url);
+                !url.ends_with(&format!("/{}", repository))
+            }
+            (None, None) => false,
+        };
+        if mismatch {
+            errors.push(format!(
+                "OIDC token is for repository {}, which does not match the recorded CI repository or project",
+                repository
+            ));
+        }
+    }
+
+    if let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&manifest.created_at) {
+        let created_at = created_at.timestamp();
+        let not_before = claims.nbf.unwrap_or(claims.iat).min(claims.iat);
+        if created_at < not_before - TIMESTAMP_SKEW_SECS
+            || created_at > claims.exp + TIMESTAMP_SKEW_SECS
+        {
+            errors.push(format!(
+                "manifest created_at {} is outside the OIDC token's validity ({} to {})",
+                manifest.created_at, binding.issued_at, binding.expires_at
+            ));
+        }
+    }
+
+    match jwks {
+        Some(jwks) => {
+            let jwks_json = fs::read_to_string(jwks)
+                .with_context(|| format!("reading JWKS {}", jwks.display()))?;
+            match crate::oidc::verify(&decoded, &jwks_json) {
+                Ok(()) => eprintln!("[vbw] OIDC token signature: OK"),
+                Err(e) => errors.push(format!("OIDC token signature: {:#}", e)),
             }
         }
+        None if required => errors.push(format!(
+            "Policy requires a verified OIDC identity, but the token from {} was not checked against the issuer's keys (pass --oidc-jwks)",
+            claims.iss
+        )),
+        None => warnings.push(format!(
+            "OIDC token from {} was not checked against the issuer's keys (pass --oidc-jwks)",
+            claims.iss
+        )),
     }
     Ok(())
 }
 
-/// Verify co-signatures in the signatures/ directory.
-///
-/// For each `*.ed25519.sig` file (except `builder.ed25519.sig`):
-///   1. Extract the key_id from the filename (stem before .ed25519.sig)
-///   2. Look up the public key in the policy's trusted_cosigner_keys
-///   3. Verify the signature against canonical manifest bytes
+/// Establish when the bundle was signed and check builder key validity at
+/// that time.
 ///
-/// If `require_maintainer_cosign_for_release` is true in the policy,
-/// at least one valid co-signature must be present.
-fn verify_cosignatures(
+/// The reference time is the TSA's genTime if signatures/builder.<alg>.tst
+/// holds a valid token from a TSA pinned in policy
+/// (signing.timestamp.trusted_tsa_cert_sha256); otherwise it is the
+/// self-reported manifest created_at. If the policy lists
+/// trusted_builder_keys, the builder key must be one of them, inside its
+/// validity period at the reference time, and not revoked by then. A
+/// revoked key additionally requires a trusted timestamp (see KEY VALIDITY
+/// in model.rs).
+fn verify_build_time(
     bundle_dir: &Path,
-    canonical_bytes: &[u8],
+    manifest: &Manifest,
     policy: Option<&Policy>,
     errors: &mut Vec<String>,
     warnings: &mut Vec<String>,
-) {
-    let sig_dir = bundle_dir.join("signatures");
-    if !sig_dir.is_dir() {
-        return;
-    }
-
-    // Collect trusted keys from policy
-    let trusted_keys: Vec<&TrustedCosignerKey> = policy
-        .and_then(|p| p.requirements.signing.as_ref())
-        .and_then(|s| s.trusted_cosigner_keys.as_ref())
-        .map(|keys| keys.iter().collect())
+) -> Result<()> {
+    let signing = policy.and_then(|p| p.requirements.signing.as_ref());
+    let timestamp_policy = signing.and_then(|s| s.timestamp.as_ref());
+    let pins: Vec<String> = timestamp_policy
+        .and_then(|t| t.trusted_tsa_cert_sha256.as_ref())
+        .map(|pins| pins.iter().map(|p| p.to_ascii_lowercase()).collect())
         .unwrap_or_default();
+    let required = timestamp_policy.and_then(|t| t.required).unwrap_or(false);
 
-    let require_cosign = policy
-        .and_then(|p| p.requirements.signing.as_ref())
-        .and_then(|s| s.require_maintainer_cosign_for_release)
-        .unwrap_or(false);
+    let identity = &manifest.builder_identity;
+    let primary = identity.algorithm();
+    for alg in sign::Algorithm::ALL {
+        let name = builder_timestamp_filename(*alg);
+        if *alg != primary && bundle_dir.join("signatures").join(&name).exists() {
+            errors.push(format!(
+                "Unexpected file in bundle: signatures/{} (only the {} builder signature is timestamped)",
+                name,
+                primary.as_str()
+            ));
+        }
+    }
 
-    // Find all co-signature files
-    let cosig_files: Vec<_> = match fs::read_dir(&sig_dir) {
-        Ok(entries) => entries
-            .filter_map(|e| e.ok())
-            .map(|e| e.path())
-            .filter(|p| {
-                p.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.ends_with(".ed25519.sig") && n != "builder.ed25519.sig")
-                    .unwrap_or(false)
-            })
-            .collect(),
-        Err(_) => Vec::new(),
+    let created_at = match chrono::DateTime::parse_from_rfc3339(&manifest.created_at) {
+        Ok(t) => Some(t.with_timezone(&chrono::Utc)),
+        Err(e) => {
+            warnings.push(format!(
+                "manifest created_at '{}' is not RFC 3339: {}",
+                manifest.created_at, e
+            ));
+            None
+        }
     };
 
-    let mut valid_cosig_count = 0;
+    // Timestamp token over the primary builder signature
+    let mut trusted_time = None;
+    let token_path = bundle_dir
+        .join("signatures")
+        .join(builder_timestamp_filename(primary));
+    if token_path.exists() {
+        let token = fs::read(&token_path)
+            .with_context(|| format!("reading {}", token_path.display()))?;
+        let signature_path = bundle_dir.join("signatures").join(identity.signature_filename());
+        let signature = sign::decode_signature(&fs::read_to_string(&signature_path)?)?;
+        match crate::tsa::verify_token(&token, &signature) {
+            Ok(ts) if pins.contains(&ts.tsa_cert_sha256) => {
+                eprintln!(
+                    "[vbw] Timestamp token: OK ({} from {})",
+                    ts.gen_time.to_rfc3339(),
+                    ts.tsa_subject
+                );
+                trusted_time = Some(ts.gen_time);
+            }
+            Ok(ts) if pins.is_empty() => warnings.push(format!(
+                "Timestamp token from '{}' (certificate sha256 {}) is valid but policy \
+                 pins no trusted_tsa_cert_sha256; its time is not trusted",
+                ts.tsa_subject, ts.tsa_cert_sha256
+            )),
+            Ok(ts) => errors.push(format!(
+                "Timestamp token signed by untrusted TSA '{}' (certificate sha256 {})",
+                ts.tsa_subject, ts.tsa_cert_sha256
+            )),
+            Err(e) => errors.push(format!("Timestamp token INVALID: {}", e)),
+        }
+    }
+    if required && trusted_time.is_none() {
+        errors.push(
+            "Policy requires a trusted timestamp but the bundle has none from a pinned TSA"
+                .to_string(),
+        );
+    }
 
-    for cosig_path in &cosig_files {
-        let filename = match cosig_path.file_name().and_then(|n| n.to_str()) {
-            Some(n) => n,
-            None => continue,
-        };
+    if let (Some(gen_time), Some(created_at)) = (trusted_time, created_at) {
+        let gap = (gen_time - created_at).num_seconds();
+        if gap < -TIMESTAMP_SKEW_SECS {
+            errors.push(format!(
+                "manifest created_at {} is after the trusted timestamp {}",
+                manifest.created_at,
+                gen_time.to_rfc3339()
+            ));
+        } else if gap > TIMESTAMP_LATE_SECS {
+            warnings.push(format!(
+                "Builder signature was timestamped {}s after manifest created_at",
+                gap
+            ));
+        }
+    }
 
-        // Extract key_id: strip the ".ed25519.sig" suffix
-        let key_id = &filename[..filename.len() - ".ed25519.sig".len()];
+    let Some(trusted_keys) = signing.and_then(|s| s.trusted_builder_keys.as_ref()) else {
+        return Ok(());
+    };
+    let Some(key) = trusted_keys.iter().find(|k| {
+        k.algorithm() == primary && k.public_key_b64() == identity.public_key_b64()
+    }) else {
+        errors.push(format!(
+            "Builder key '{}' is not in policy trusted_builder_keys",
+            identity.key_id
+        ));
+        return Ok(());
+    };
 
-        // Look up trusted key by key_id
-        let trusted_key = trusted_keys.iter().find(|k| {
-            // Match against sanitized key_id (same sanitization as cmd_attest)
-            let sanitized: String = k
-                .key_id
-                .chars()
-                .map(|c| {
-                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
-                        c
-                    } else {
-                        '_'
-                    }
-                })
-                .collect();
-            sanitized == key_id
-        });
+    let Some(reference) = trusted_time.or(created_at) else {
+        errors.push("Cannot check builder key validity: no usable build time".to_string());
+        return Ok(());
+    };
+    let source = if trusted_time.is_some() {
+        "trusted timestamp"
+    } else {
+        warnings.push(
+            "Builder key validity checked against self-reported created_at \
+             (no trusted timestamp)"
+                .to_string(),
+        );
+        "created_at"
+    };
 
-        match trusted_key {
+    let bound = |field: &str, value: &Option<String>, errors: &mut Vec<String>| {
+        let value = value.as_ref()?;
+        match chrono::DateTime::parse_from_rfc3339(value) {
+            Ok(t) => Some(t.with_timezone(&chrono::Utc)),
+            Err(e) => {
+                errors.push(format!(
+                    "trusted_builder_keys '{}' {} '{}' is not RFC 3339: {}",
+                    key.key_id, field, value, e
+                ));
+                None
+            }
+        }
+    };
+    let reference_str = reference.to_rfc3339();
+    let errors_before = errors.len();
+    if let Some(from) = bound("valid_from", &key.valid_from, errors) {
+        if reference < from {
+            errors.push(format!(
+                "Builder key '{}' not yet valid at {} ({}); valid_from {}",
+                key.key_id, reference_str, source, from.to_rfc3339()
+            ));
+        }
+    }
+    if let Some(until) = bound("valid_until", &key.valid_until, errors) {
+        if reference > until {
+            errors.push(format!(
+                "Builder key '{}' expired at {}, before {} ({})",
+                key.key_id, until.to_rfc3339(), reference_str, source
+            ));
+        }
+    }
+    if let Some(revoked) = bound("revoked_at", &key.revoked_at, errors) {
+        if trusted_time.is_none() {
+            errors.push(format!(
+                "Builder key '{}' was revoked at {}; a trusted timestamp is required \
+                 to show the bundle was signed before revocation",
+                key.key_id,
+                revoked.to_rfc3339()
+            ));
+        } else if reference >= revoked {
+            errors.push(format!(
+                "Builder key '{}' was revoked at {}, before {} ({})",
+                key.key_id, revoked.to_rfc3339(), reference_str, source
+            ));
+        }
+    }
+    if errors.len() == errors_before {
+        eprintln!(
+            "[vbw] Builder key '{}' valid at {} ({})",
+            key.key_id, reference_str, source
+        );
+    }
+    Ok(())
+}
+
+/// True if `path` is a file (or, with `is_dir`, a directory) a pipeline step
+/// sub-witness may contain: steps/, steps/<name>/, steps/<name>/signatures/,
+/// the STEP_FILES, the step transcript and
+/// steps/<name>/signatures/builder.<alg>.sig.
+fn is_step_path(bundle_dir: &Path, path: &Path, is_dir: bool) -> bool {
+    let Ok(rel) = path.strip_prefix(bundle_dir) else {
+        return false;
+    };
+    let parts: Vec<&str> = rel.iter().filter_map(|c| c.to_str()).collect();
+    if parts.len() != rel.iter().count() || parts.first() != Some(&"steps") {
+        return false;
+    }
+    if parts.len() >= 2 && !is_valid_step_name(parts[1]) {
+        return false;
+    }
+    match (is_dir, parts.len()) {
+        (true, 1) | (true, 2) => true,
+        (true, 3) => parts[2] == "signatures",
+        (false, 3) => {
+            STEP_FILES.contains(&parts[2])
+                || parts[2] == TRANSCRIPT_FILE
+                || parts[2] == LEGACY_TRANSCRIPT_FILE
+        }
+        (false, 4) => {
+            parts[2] == "signatures"
+                && sign::Algorithm::ALL
+                    .iter()
+                    .any(|alg| builder_signature_filename(*alg) == parts[3])
+        }
+        _ => false,
+    }
+}
+
+/// Verify the pipeline steps listed in the manifest.
+///
+/// Each step's canonical step.json must hash to its `step_hash` in the
+/// manifest and carry valid signatures from the key in its own
+/// builder_identity; its component files must match the hashes in
+/// step.json; and it must be built from the manifest's commit. Every
+/// step-output material — in a step, or in the final build's
+/// materials.lock.json — must name an earlier step and match one of that
+/// step's outputs (path and SHA-256). Step directories not listed in the
+/// manifest are rejected.
+fn verify_steps(
+    bundle_dir: &Path,
+    manifest: &Manifest,
+    final_materials: Option<&MaterialsLock>,
+    errors: &mut Vec<String>,
+) -> Result<()> {
+    let refs: &[StepRef] = manifest.steps.as_deref().unwrap_or(&[]);
+    let steps_dir = bundle_dir.join("steps");
+
+    if steps_dir.is_dir() {
+        for entry in fs::read_dir(&steps_dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if !refs.iter().any(|r| r.name == name) {
+                errors.push(format!(
+                    "Unexpected step in bundle: steps/{} (not listed in manifest.steps)",
+                    name
+                ));
+            }
+        }
+    }
+
+    // Outputs of the steps verified so far, by step name
+    let mut step_outputs: Vec<(&str, Outputs)> = Vec::new();
+    for step_ref in refs {
+        let name = step_ref.name.as_str();
+        if !is_valid_step_name(name) {
+            errors.push(format!("Invalid step name in manifest.steps: '{}'", name));
+            continue;
+        }
+        if step_outputs.iter().any(|(n, _)| *n == name) {
+            errors.push(format!("Step '{}' is listed twice in manifest.steps", name));
+            continue;
+        }
+        let dir = steps_dir.join(name);
+        let missing: Vec<&&str> = STEP_FILES.iter().filter(|f| !dir.join(f).exists()).collect();
+        for file in &missing {
+            errors.push(format!("Required file missing: steps/{}/{}", name, file));
+        }
+        let transcript_file = match find_transcript(&dir, &format!("steps/{}/", name)) {
+            Ok(file) if missing.is_empty() => file,
+            Ok(_) => continue,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        let step_json = fs::read_to_string(dir.join("step.json"))?;
+        let step_manifest: StepManifest = match serde_json::from_str(&step_json) {
+            Ok(s) => s,
+            Err(e) => {
+                errors.push(format!("steps/{}/step.json failed to parse: {}", name, e));
+                continue;
+            }
+        };
+        let canonical_bytes = canonical::canonical_manifest_bytes(&step_manifest);
+        let errors_before = errors.len();
+        if hash::sha256_hex(&canonical_bytes) != step_ref.step_hash {
+            errors.push(format!(
+                "Step '{}' hash mismatch: step.json does not match manifest.steps",
+                name
+            ));
+        }
+        if step_manifest.step != name {
+            errors.push(format!(
+                "steps/{}/step.json names step '{}'",
+                name, step_manifest.step
+            ));
+        }
+        if step_manifest.git.commit != manifest.git.commit {
+            errors.push(format!(
+                "Step '{}' was built from commit {}, not the bundle's {}",
+                name, step_manifest.git.commit, manifest.git.commit
+            ));
+        }
+        check_builder_signatures(
+            &dir.join("signatures"),
+            &step_manifest.builder_identity,
+            &canonical_bytes,
+            &format!("Step '{}'", name),
+            errors,
+        );
+        // A step carries no OIDC token to back a binding
+        if step_manifest.builder_identity.oidc.is_some()
+            || step_manifest.builder_identity.issuer.is_some()
+        {
+            errors.push(format!(
+                "Step '{}' claims an OIDC identity, which steps cannot carry",
+                name
+            ));
+        }
+
+        let component_hashes = [
+            ("environment.json", &step_manifest.environment_hash),
+            ("materials.lock.json", &step_manifest.materials_lock_hash),
+            ("outputs.json", &step_manifest.outputs_hash),
+            (transcript_file, &step_manifest.transcript_hash),
+        ];
+        for (file, expected) in component_hashes {
+            let actual = hash::sha256_hex(&fs::read(dir.join(file))?);
+            if actual != *expected {
+                errors.push(format!(
+                    "Step '{}' {} hash mismatch: expected={}, computed={}",
+                    name, file, expected, actual
+                ));
+            }
+        }
+
+        match serde_json::from_str::<MaterialsLock>(&fs::read_to_string(
+            dir.join("materials.lock.json"),
+        )?) {
+            Ok(materials) => {
+                let consumer = format!("Step '{}'", name);
+                check_step_inputs(&consumer, &materials, &step_outputs, errors)
+            }
+            Err(e) => errors.push(format!(
+                "steps/{}/materials.lock.json failed to parse: {}",
+                name, e
+            )),
+        }
+        match serde_json::from_str::<Outputs>(&fs::read_to_string(dir.join("outputs.json"))?) {
+            Ok(outputs) => step_outputs.push((name, outputs)),
+            Err(e) => errors.push(format!("steps/{}/outputs.json failed to parse: {}", name, e)),
+        }
+        if errors.len() == errors_before {
+            eprintln!(
+                "[vbw] Step '{}': OK ({} output(s))",
+                name,
+                step_outputs.last().map_or(0, |(_, o)| o.artifacts.len())
+            );
+        }
+    }
+
+    if let Some(materials) = final_materials {
+        check_step_inputs("Final build", materials, &step_outputs, errors);
+    }
+    Ok(())
+}
+
+/// Check that every step-output material in `materials` matches an output
+/// of one of the (earlier) steps in `step_outputs`.
+fn check_step_inputs(
+    consumer: &str,
+    materials: &MaterialsLock,
+    step_outputs: &[(&str, Outputs)],
+    errors: &mut Vec<String>,
+) {
+    for material in materials.materials.iter().filter(|m| m.kind == STEP_OUTPUT_KIND) {
+        let source = material.source.as_deref().unwrap_or("");
+        let Some((_, outputs)) = step_outputs.iter().find(|(n, _)| *n == source) else {
+            errors.push(format!(
+                "{} input {} names step '{}', which is not an earlier step",
+                consumer, material.name, source
+            ));
+            continue;
+        };
+        if !outputs
+            .artifacts
+            .iter()
+            .any(|a| a.path == material.name && a.sha256 == material.sha256)
+        {
+            errors.push(format!(
+                "{} input {} (sha256 {}) does not match any output of step '{}'",
+                consumer, material.name, material.sha256, source
+            ));
+        }
+    }
+}
+
+/// Verify every builder signature `identity` requires over `signed_bytes`,
+/// reading them from `sig_dir`. `context` prefixes error messages.
+pub(crate) fn check_builder_signatures(
+    sig_dir: &Path,
+    identity: &BuilderIdentity,
+    signed_bytes: &[u8],
+    context: &str,
+    errors: &mut Vec<String>,
+) {
+    for (alg, public_key, filename) in identity.required_signatures() {
+        let signature = match fs::read_to_string(sig_dir.join(&filename)) {
+            Ok(s) => s,
+            Err(_) => {
+                errors.push(format!("{}: missing signatures/{}", context, filename));
+                continue;
+            }
+        };
+        match sign::verify_with(alg, public_key, signed_bytes, signature.trim()) {
+            Ok(true) => {}
+            Ok(false) => errors.push(format!(
+                "{}: builder signature INVALID ({})",
+                context,
+                alg.as_str()
+            )),
+            Err(e) => errors.push(format!(
+                "{}: signature verification error ({}): {}",
+                context,
+                alg.as_str(),
+                e
+            )),
+        }
+    }
+}
+
+/// Check that no symlinks in the bundle escape the bundle directory.
+fn check_symlink_safety(bundle_dir: &Path, errors: &mut Vec<String>) -> Result<()> {
+    let entries = walk_dir(bundle_dir)?;
+    for entry in &entries {
+        // Check if entry is a symlink
+        let metadata = entry.symlink_metadata()?;
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(entry)?;
+            let resolved = if target.is_absolute() {
+                target.clone()
+            } else {
+                entry.parent().unwrap_or(bundle_dir).join(&target)
+            };
+            let resolved_canonical = resolved.canonicalize().unwrap_or_else(|_| resolved.clone());
+            if !resolved_canonical.starts_with(bundle_dir) {
+                errors.push(format!(
+                    "Symlink escapes bundle: {} -> {} (resolves outside {})",
+                    entry.display(),
+                    target.display(),
+                    bundle_dir.display()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// File suffix for structured co-signature statements written by `attest`.
+const COSIG_STATEMENT_SUFFIX: &str = ".cosig.json";
+
+/// File suffix for legacy bare co-signatures (VBW 1.0 `attest`).
+const COSIG_LEGACY_SUFFIX: &str = ".ed25519.sig";
+
+/// True if `name` is an allowed co-signature filename: a non-empty key_id
+/// stem from the sanitized charset followed by a known co-signature suffix.
+fn is_cosignature_filename(name: &str) -> bool {
+    [COSIG_STATEMENT_SUFFIX, COSIG_LEGACY_SUFFIX]
+        .iter()
+        .any(|suffix| name.ends_with(suffix) && name.len() > suffix.len())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Verify co-signatures in the signatures/ directory.
+///
+/// Two formats are accepted:
+///   - `*.cosig.json` — structured statements (key_id, public key, signed_at,
+///     statement type, comment) signed over the canonical manifest bytes plus
+///     the statement metadata. See `verify_cosignature_statement()`.
+///   - `*.ed25519.sig` (except `builder.ed25519.sig`) — legacy bare
+///     signatures where the key_id is only the filename stem.
+///
+/// For both, the signer must match a key_id in the policy's
+/// trusted_cosigner_keys and the signature must verify against that key.
+///
+/// If `require_maintainer_cosign_for_release` is true in the policy,
+/// at least one valid co-signature must be present. Each entry in
+/// `required_statements` must be covered by at least one valid statement.
+fn verify_cosignatures(
+    bundle_dir: &Path,
+    canonical_bytes: &[u8],
+    policy: Option<&Policy>,
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let sig_dir = bundle_dir.join("signatures");
+    if !sig_dir.is_dir() {
+        return;
+    }
+
+    // Collect trusted keys from policy
+    let trusted_keys: Vec<&TrustedCosignerKey> = policy
+        .and_then(|p| p.requirements.signing.as_ref())
+        .and_then(|s| s.trusted_cosigner_keys.as_ref())
+        .map(|keys| keys.iter().collect())
+        .unwrap_or_default();
+
+    let require_cosign = policy
+        .and_then(|p| p.requirements.signing.as_ref())
+        .and_then(|s| s.require_maintainer_cosign_for_release)
+        .unwrap_or(false);
+
+    let required_statements: Vec<&String> = policy
+        .and_then(|p| p.requirements.signing.as_ref())
+        .and_then(|s| s.required_statements.as_ref())
+        .map(|st| st.iter().collect())
+        .unwrap_or_default();
+
+    // Find all co-signature files (sorted for stable output)
+    let mut cosig_files: Vec<_> = match fs::read_dir(&sig_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| {
+                        n.ends_with(COSIG_STATEMENT_SUFFIX)
+                            || (n.ends_with(COSIG_LEGACY_SUFFIX) && n != "builder.ed25519.sig")
+                    })
+                    .unwrap_or(false)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    cosig_files.sort();
+
+    let mut valid_cosig_count = 0;
+    let mut covered_statements: BTreeSet<String> = BTreeSet::new();
+
+    for cosig_path in &cosig_files {
+        let filename = match cosig_path.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        if filename.ends_with(COSIG_STATEMENT_SUFFIX) {
+            if let Some(statement) = verify_cosignature_statement(
+                cosig_path,
+                filename,
+                canonical_bytes,
+                &trusted_keys,
+                errors,
+                warnings,
+            ) {
+                valid_cosig_count += 1;
+                covered_statements.insert(statement);
+            }
+            continue;
+        }
+
+        // Legacy bare signature: extract key_id by stripping the ".ed25519.sig" suffix
+        let key_id = &filename[..filename.len() - COSIG_LEGACY_SUFFIX.len()];
+
+        // Look up trusted key by key_id
+        let trusted_key = trusted_keys.iter().find(|k| {
+            // Match against sanitized key_id (same sanitization as cmd_attest)
+            let sanitized: String = k
+                .key_id
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            sanitized == key_id
+        });
+
+        match trusted_key {
+            Some(tk) if tk.algorithm() != sign::Algorithm::Ed25519 => {
+                errors.push(format!(
+                    "Co-signature '{}' is a legacy Ed25519 signature, but trusted key '{}' \
+                     is {}",
+                    filename,
+                    tk.key_id,
+                    tk.algorithm().as_str()
+                ));
+            }
             Some(tk) => {
                 // Read and verify the signature
                 match fs::read_to_string(cosig_path) {
                     Ok(sig_data) => {
                         let sig = sig_data.trim();
-                        match sign::verify(&tk.public_key_ed25519, canonical_bytes, sig) {
+                        match sign::verify(tk.public_key_b64(), canonical_bytes, sig) {
                             Ok(true) => {
                                 eprintln!(
                                     "[vbw] Co-signature '{}' (key_id: {}): OK",
@@ -515,21 +1459,7 @@ fn verify_cosignatures(
                     }
                 }
             }
-            None => {
-                if trusted_keys.is_empty() {
-                    // No trusted keys configured — co-sig present but unverifiable
-                    warnings.push(format!(
-                        "Co-signature '{}' present but no trusted_cosigner_keys in policy \
-                         (cannot verify)",
-                        filename
-                    ));
-                } else {
-                    warnings.push(format!(
-                        "Co-signature '{}' has no matching key_id in trusted_cosigner_keys",
-                        filename
-                    ));
-                }
-            }
+            None => warn_untrusted_cosigner(filename, trusted_keys.is_empty(), warnings),
         }
     }
 
@@ -542,6 +1472,16 @@ fn verify_cosignatures(
         );
     }
 
+    // Enforce required statement types
+    for required in required_statements {
+        if !covered_statements.contains(required) {
+            errors.push(format!(
+                "Policy requires a '{}' co-signature statement, but no valid one was found",
+                required
+            ));
+        }
+    }
+
     if !cosig_files.is_empty() {
         eprintln!(
             "[vbw] Co-signatures: {} found, {} verified",
@@ -551,33 +1491,158 @@ fn verify_cosignatures(
     }
 }
 
-/// Recursively walk a directory and return all entries (files and dirs).
+/// Verify a single structured co-signature statement (`*.cosig.json`).
 ///
-/// Protects against symlink cycle DoS attacks by:
-///   1. Limiting recursion depth to MAX_WALK_DEPTH
-///   2. Tracking visited directories by canonical path to detect cycles
-fn walk_dir(dir: &Path) -> Result<Vec<PathBuf>> {
-    let mut visited = HashSet::new();
-    walk_dir_inner(dir, &mut visited, 0)
-}
+/// Checks, in order: the document parses, its key_id matches a trusted
+/// cosigner key, its embedded public key equals the trusted key, its
+/// manifest_hash matches this bundle, and the signature verifies over
+/// `cosignature_signing_bytes()` with the trusted key.
+///
+/// Returns the statement type if the co-signature is valid.
+fn verify_cosignature_statement(
+    path: &Path,
+    filename: &str,
+    canonical_bytes: &[u8],
+    trusted_keys: &[&TrustedCosignerKey],
+    errors: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    let data = match fs::read_to_string(path) {
+        Ok(d) => d,
+        Err(e) => {
+            errors.push(format!(
+                "Cannot read co-signature file '{}': {}",
+                filename, e
+            ));
+            return None;
+        }
+    };
+    let cosig: Cosignature = match serde_json::from_str(&data) {
+        Ok(c) => c,
+        Err(e) => {
+            errors.push(format!(
+                "Co-signature '{}' is not a valid statement document: {}",
+                filename, e
+            ));
+            return None;
+        }
+    };
 
-fn walk_dir_inner(
-    dir: &Path,
-    visited: &mut HashSet<PathBuf>,
-    depth: usize,
-) -> Result<Vec<PathBuf>> {
-    if depth > MAX_WALK_DEPTH {
-        anyhow::bail!(
-            "Directory traversal exceeded maximum depth ({}) at {} — possible symlink cycle",
-            MAX_WALK_DEPTH,
-            dir.display()
-        );
-    }
+    eprintln!(
+        "[vbw] Co-signature statement '{}' by {} at {}{}",
+        cosig.statement,
+        cosig.key_id,
+        cosig.signed_at,
+        cosig
+            .comment
+            .as_ref()
+            .map(|c| format!(" — \"{}\"", c))
+            .unwrap_or_default()
+    );
 
-    // Track visited directories by canonical path to detect symlink cycles
-    if let Ok(canonical) = dir.canonicalize() {
-        if !visited.insert(canonical) {
-            anyhow::bail!(
+    let trusted_key = match trusted_keys.iter().find(|k| k.key_id == cosig.key_id) {
+        Some(tk) => tk,
+        None => {
+            warn_untrusted_cosigner(filename, trusted_keys.is_empty(), warnings);
+            return None;
+        }
+    };
+
+    if cosig.algorithm() != trusted_key.algorithm()
+        || cosig.public_key_b64() != trusted_key.public_key_b64()
+    {
+        errors.push(format!(
+            "Co-signature '{}' public key does not match trusted key for key_id '{}'",
+            filename, cosig.key_id
+        ));
+        return None;
+    }
+
+    let manifest_hash = hash::sha256_hex(canonical_bytes);
+    if cosig.manifest_hash != manifest_hash {
+        errors.push(format!(
+            "Co-signature '{}' covers a different manifest: statement={}, bundle={}",
+            filename, cosig.manifest_hash, manifest_hash
+        ));
+        return None;
+    }
+
+    let payload = canonical::cosignature_signing_bytes(canonical_bytes, &cosig);
+    match sign::verify_with(
+        trusted_key.algorithm(),
+        trusted_key.public_key_b64(),
+        &payload,
+        &cosig.signature,
+    ) {
+        Ok(true) => {
+            eprintln!(
+                "[vbw] Co-signature '{}' (key_id: {}, statement: {}): OK",
+                filename, cosig.key_id, cosig.statement
+            );
+            Some(cosig.statement)
+        }
+        Ok(false) => {
+            errors.push(format!(
+                "Co-signature '{}' INVALID for key_id '{}' \
+                 (signature does not match canonical manifest bytes + statement)",
+                filename, cosig.key_id
+            ));
+            None
+        }
+        Err(e) => {
+            errors.push(format!(
+                "Co-signature '{}' verification error for key_id '{}': {}",
+                filename, cosig.key_id, e
+            ));
+            None
+        }
+    }
+}
+
+/// Record a warning for a co-signature whose signer is not in the policy.
+fn warn_untrusted_cosigner(filename: &str, no_trusted_keys: bool, warnings: &mut Vec<String>) {
+    if no_trusted_keys {
+        // No trusted keys configured — co-sig present but unverifiable
+        warnings.push(format!(
+            "Co-signature '{}' present but no trusted_cosigner_keys in policy \
+             (cannot verify)",
+            filename
+        ));
+    } else {
+        warnings.push(format!(
+            "Co-signature '{}' has no matching key_id in trusted_cosigner_keys",
+            filename
+        ));
+    }
+}
+
+/// Recursively walk a directory and return all entries (files and dirs).
+///
+/// Protects against symlink cycle DoS attacks by:
+///   1. Limiting recursion depth to MAX_WALK_DEPTH
+///   2. Tracking visited directories by canonical path to detect cycles
+fn walk_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut visited = HashSet::new();
+    walk_dir_inner(dir, &mut visited, 0)
+}
+
+fn walk_dir_inner(
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    depth: usize,
+) -> Result<Vec<PathBuf>> {
+    if depth > MAX_WALK_DEPTH {
+        anyhow::bail!(
+            "Directory traversal exceeded maximum depth ({}) at {} — possible symlink cycle",
+            MAX_WALK_DEPTH,
+            dir.display()
+        );
+    }
+
+    // Track visited directories by canonical path to detect symlink cycles
+    if let Ok(canonical) = dir.canonicalize() {
+        if !visited.insert(canonical) {
+            anyhow::bail!(
                 "Directory cycle detected at {} (already visited via symlink)",
                 dir.display()
             );
@@ -596,6 +1661,72 @@ fn walk_dir_inner(
     Ok(results)
 }
 
+/// Show the traced network access of the build.
+fn print_network_access(access: &NetworkAccessLog) {
+    eprintln!(
+        "[vbw] Network access (traced): {} destination(s), {} DNS name(s)",
+        access.connections.len(),
+        access.dns_queries.len()
+    );
+    for query in &access.dns_queries {
+        eprintln!("[vbw]   dns     {} (x{})", query.name, query.count);
+    }
+    for connection in &access.connections {
+        let address = if connection.address.contains(':') {
+            format!("[{}]:{}", connection.address, connection.port)
+        } else {
+            format!("{}:{}", connection.address, connection.port)
+        };
+        eprintln!("[vbw]   connect {} (x{})", address, connection.count);
+    }
+}
+
+/// Show the CI run that produced the build.
+fn print_ci(ci: &CiContext) {
+    let mut details = Vec::new();
+    for (label, value) in [
+        ("repository", &ci.repository),
+        ("workflow", &ci.workflow),
+        ("job", &ci.job),
+        ("event", &ci.event),
+        ("ref", &ci.git_ref),
+        ("actor", &ci.actor),
+    ] {
+        if let Some(value) = value {
+            details.push(format!("{} {}", label, value));
+        }
+    }
+    eprintln!(
+        "[vbw] CI: {} run {} ({})",
+        ci.provider,
+        ci.run_id.as_deref().unwrap_or("unknown"),
+        details.join(", ")
+    );
+}
+
+/// Show how many files the build read, by class.
+fn print_inputs(inputs: &InputsLog) {
+    let count = |class: InputClass| inputs.files.iter().filter(|f| f.class == class).count();
+    eprintln!(
+        "[vbw] Files read (traced): {} tracked, {} untracked, {} toolchain, {} outside the repository",
+        count(InputClass::TrackedInGit),
+        count(InputClass::UntrackedInRepo),
+        count(InputClass::Toolchain),
+        count(InputClass::OutsideRepo)
+    );
+    for file in inputs
+        .files
+        .iter()
+        .filter(|f| f.class == InputClass::UntrackedInRepo)
+    {
+        eprintln!(
+            "[vbw]   untracked {} ({})",
+            file.path,
+            file.sha256.as_deref().unwrap_or("not hashed")
+        );
+    }
+}
+
 fn verify_and_parse_component<F>(
     bundle_dir: &Path,
     filename: &str,
@@ -647,6 +1778,19 @@ fn check_policy_compliance(
                 env.reproducibility.mode, policy.requirements.reproducibility.mode
             ));
         }
+        let clean_required = policy
+            .requirements
+            .environment
+            .as_ref()
+            .and_then(|e| e.clean)
+            .unwrap_or(false);
+        if clean_required && env.build_env.is_none() {
+            warnings.push(
+                "Policy requires a clean build environment but environment.json records none \
+                 (the build inherited the builder's environment)"
+                    .to_string(),
+            );
+        }
     }
 
     if policy.requirements.materials.require_lockfile_hashes {
@@ -658,7 +1802,7 @@ fn check_policy_compliance(
     }
 }
 
-fn emit_verdict(errors: Vec<String>, warnings: Vec<String>) -> Result<Verdict> {
+pub(crate) fn emit_verdict(errors: Vec<String>, warnings: Vec<String>) -> Result<Verdict> {
     if !errors.is_empty() {
         eprintln!();
         eprintln!("UNVERIFIED — {} error(s):", errors.len());
@@ -707,9 +1851,17 @@ mod tests {
                     require_lockfile_hashes: false,
                     require_vendor_archive_and_tree: Some(false),
                 },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
                 signing: Some(SigningRequirement {
                     require_maintainer_cosign_for_release: Some(false),
                     trusted_cosigner_keys: None,
+                    required_statements: None,
+                    timestamp: None,
+                    trusted_builder_keys: None,
+                    require_verified_oidc: None,
                 }),
             },
         };
@@ -724,13 +1876,17 @@ mod tests {
                 arch: None,
             },
             container: None,
+            ci: None,
             tools: vec![ToolInfo {
                 name: "test".to_string(),
                 version: "1.0".to_string(),
                 path: None,
+                sha256: None,
                 invocation: None,
+                details: None,
             }],
             env: None,
+            build_env: None,
             locale: None,
             timezone: None,
             reproducibility: Reproducibility {
@@ -775,18 +1931,33 @@ mod tests {
             outputs_hash: out_hash,
             builder_identity: BuilderIdentity {
                 key_id: "test@verify".to_string(),
+                key_type: None,
                 public_key_ed25519: pk,
+                public_key: None,
                 issuer: None,
+                oidc: None,
+                hybrid: None,
+                ssh_fingerprint: None,
             },
             policy_ref: PolicyRef {
                 path: "vbw/policy.json".to_string(),
                 hash_sha256: policy_hash,
             },
+            sequence: None,
+            previous_manifest_hash: None,
+            steps: None,
+            network_log_hash: None,
+            network_access_hash: None,
+            inputs_hash: None,
+            transcript_hash: None,
+            build_result: None,
             enforcement: Some(Enforcement {
                 mode_requested: ReproducibilityMode::C_WITNESSED_ND,
                 mode_enforced: true,
                 network_blocked: false,
                 source_date_epoch_set: false,
+                network_violations: None,
+                untracked_inputs: None,
                 notes: None,
             }),
             notes: None,
@@ -822,7 +1993,7 @@ mod tests {
         fs::create_dir(&bundle).unwrap();
         create_test_bundle(&bundle);
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         assert_eq!(verdict, Verdict::Verified);
     }
 
@@ -838,7 +2009,7 @@ mod tests {
         manifest_json = manifest_json.replace("test", "tampered");
         fs::write(bundle.join("manifest.json"), &manifest_json).unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -868,7 +2039,7 @@ mod tests {
         let bad_sig = sign::sign(&other_sk, &canonical_bytes).unwrap();
         fs::write(bundle.join("signatures/builder.ed25519.sig"), &bad_sig).unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -895,7 +2066,7 @@ mod tests {
         )
         .unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -920,7 +2091,7 @@ mod tests {
         // Add an unexpected file
         fs::write(bundle.join("malicious.txt"), "pwned").unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -943,7 +2114,7 @@ mod tests {
         // Remove a required file
         fs::remove_file(bundle.join("transcript.txt")).unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -971,7 +2142,7 @@ mod tests {
             std::os::unix::fs::symlink("/etc/passwd", &symlink_path).unwrap();
         }
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -1003,7 +2174,7 @@ mod tests {
         )
         .unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         // Should produce a warning (no trusted keys to verify against)
         // but still verify since require_maintainer_cosign_for_release is false
         match verdict {
@@ -1063,12 +2234,22 @@ mod tests {
                     require_lockfile_hashes: false,
                     require_vendor_archive_and_tree: Some(false),
                 },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
                 signing: Some(SigningRequirement {
                     require_maintainer_cosign_for_release: Some(true),
                     trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
                         key_id: "maintainer_org".to_string(),
+                        key_type: None,
                         public_key_ed25519: cosigner_pk,
+                        public_key: None,
                     }]),
+                    required_statements: None,
+                    timestamp: None,
+                    trusted_builder_keys: None,
+                    require_verified_oidc: None,
                 }),
             },
         };
@@ -1108,7 +2289,7 @@ mod tests {
         )
         .unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         assert!(
             matches!(verdict, Verdict::Verified),
             "Expected Verified with valid cosignature, got {:?}",
@@ -1153,12 +2334,22 @@ mod tests {
                     require_lockfile_hashes: false,
                     require_vendor_archive_and_tree: Some(false),
                 },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
                 signing: Some(SigningRequirement {
                     require_maintainer_cosign_for_release: Some(false),
                     trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
                         key_id: "maintainer_org".to_string(),
+                        key_type: None,
                         public_key_ed25519: cosigner_pk,
+                        public_key: None,
                     }]),
+                    required_statements: None,
+                    timestamp: None,
+                    trusted_builder_keys: None,
+                    require_verified_oidc: None,
                 }),
             },
         };
@@ -1186,7 +2377,7 @@ mod tests {
         fs::write(bundle.join("signatures/builder.ed25519.sig"), &builder_sig).unwrap();
         fs::write(bundle.join("hashes/manifest.sha256"), &manifest_hash).unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -1223,12 +2414,22 @@ mod tests {
                     require_lockfile_hashes: false,
                     require_vendor_archive_and_tree: Some(false),
                 },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
                 signing: Some(SigningRequirement {
                     require_maintainer_cosign_for_release: Some(true),
                     trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
                         key_id: "maintainer_org".to_string(),
+                        key_type: None,
                         public_key_ed25519: cosigner_pk,
+                        public_key: None,
                     }]),
+                    required_statements: None,
+                    timestamp: None,
+                    trusted_builder_keys: None,
+                    require_verified_oidc: None,
                 }),
             },
         };
@@ -1256,7 +2457,7 @@ mod tests {
         fs::write(bundle.join("signatures/builder.ed25519.sig"), &builder_sig).unwrap();
         fs::write(bundle.join("hashes/manifest.sha256"), &manifest_hash).unwrap();
 
-        let verdict = run_verify(&bundle).unwrap();
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
                 assert!(
@@ -1269,14 +2470,1317 @@ mod tests {
         }
     }
 
+    /// Helper: replace the bundle's policy with one using `signing`, then
+    /// update the manifest's policy hash and re-sign it with a fresh builder key.
+    fn rewrite_policy_and_resign(dir: &Path, signing: SigningRequirement) -> Manifest {
+        let (builder_sk, builder_pk) = sign::keygen();
+        rewrite_policy_and_resign_with(dir, signing, &builder_sk, &builder_pk, None)
+    }
+
+    /// Like `rewrite_policy_and_resign`, with a given builder key and
+    /// optionally a new created_at.
+    fn rewrite_policy_and_resign_with(
+        dir: &Path,
+        signing: SigningRequirement,
+        builder_sk: &str,
+        builder_pk: &str,
+        created_at: Option<&str>,
+    ) -> Manifest {
+        let policy = Policy {
+            policy_version: "1.0".to_string(),
+            requirements: PolicyRequirements {
+                network: NetworkRequirement {
+                    allowed: true,
+                    allowlist: Some(vec![]),
+                },
+                reproducibility: ReproducibilityRequirement {
+                    mode: ReproducibilityMode::C_WITNESSED_ND,
+                    require_source_date_epoch: Some(false),
+                },
+                materials: MaterialsRequirement {
+                    require_lockfile_hashes: false,
+                    require_vendor_archive_and_tree: Some(false),
+                },
+                environment: None,
+                toolchain: None,
+                container: None,
+                ci: None,
+                signing: Some(signing),
+            },
+        };
+        let policy_json = serde_json::to_string_pretty(&policy).unwrap();
+        fs::write(dir.join("policy.json"), &policy_json).unwrap();
+
+        let manifest_json = fs::read_to_string(dir.join("manifest.json")).unwrap();
+        let mut manifest: Manifest = serde_json::from_str(&manifest_json).unwrap();
+        manifest.policy_ref.hash_sha256 = hash::sha256_hex(policy_json.as_bytes());
+        manifest.builder_identity.public_key_ed25519 = builder_pk.to_string();
+        if let Some(created_at) = created_at {
+            manifest.created_at = created_at.to_string();
+        }
+
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("signatures/builder.ed25519.sig"),
+            sign::sign(builder_sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+        manifest
+    }
+
+    /// Helper: build a signed co-signature statement over `manifest`.
+    fn signed_statement(manifest: &Manifest, sk: &str, pk: &str, statement: &str) -> Cosignature {
+        let canonical_bytes = canonical::canonical_manifest_bytes(manifest);
+        let mut cosig = Cosignature {
+            cosignature_version: "1.0".to_string(),
+            key_id: "maintainer@org".to_string(),
+            key_type: None,
+            public_key_ed25519: pk.to_string(),
+            public_key: None,
+            signed_at: "2026-01-02T00:00:00Z".to_string(),
+            statement: statement.to_string(),
+            comment: Some("looks good".to_string()),
+            manifest_hash: hash::sha256_hex(&canonical_bytes),
+            signature: String::new(),
+        };
+        let payload = canonical::cosignature_signing_bytes(&canonical_bytes, &cosig);
+        cosig.signature = sign::sign(sk, &payload).unwrap();
+        cosig
+    }
+
     #[test]
-    fn verify_nonexistent_bundle_dir() {
-        let verdict = run_verify(Path::new("/nonexistent/path/vbw")).unwrap();
+    fn verify_cosignature_statement_satisfies_required_statement() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        let (cosigner_sk, cosigner_pk) = sign::keygen();
+        let manifest = rewrite_policy_and_resign(
+            &bundle,
+            SigningRequirement {
+                require_maintainer_cosign_for_release: Some(true),
+                trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
+                    key_id: "maintainer@org".to_string(),
+                    key_type: None,
+                    public_key_ed25519: cosigner_pk.clone(),
+                    public_key: None,
+                }]),
+                required_statements: Some(vec!["approved-for-release".to_string()]),
+                timestamp: None,
+                trusted_builder_keys: None,
+                require_verified_oidc: None,
+            },
+        );
+
+        let cosig = signed_statement(&manifest, &cosigner_sk, &cosigner_pk, "approved-for-release");
+        fs::write(
+            bundle.join("signatures/maintainer_org.cosig.json"),
+            serde_json::to_string_pretty(&cosig).unwrap(),
+        )
+        .unwrap();
+
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
+        assert_eq!(verdict, Verdict::Verified);
+    }
+
+    #[test]
+    fn verify_cosignature_statement_tampered_metadata_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        let (cosigner_sk, cosigner_pk) = sign::keygen();
+        let manifest = rewrite_policy_and_resign(
+            &bundle,
+            SigningRequirement {
+                require_maintainer_cosign_for_release: Some(false),
+                trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
+                    key_id: "maintainer@org".to_string(),
+                    key_type: None,
+                    public_key_ed25519: cosigner_pk.clone(),
+                    public_key: None,
+                }]),
+                required_statements: None,
+                timestamp: None,
+                trusted_builder_keys: None,
+                require_verified_oidc: None,
+            },
+        );
+
+        // Signed as "reviewed", then upgraded to "deployed-to-prod" after the fact
+        let mut cosig = signed_statement(&manifest, &cosigner_sk, &cosigner_pk, "reviewed");
+        cosig.statement = "deployed-to-prod".to_string();
+        fs::write(
+            bundle.join("signatures/maintainer_org.cosig.json"),
+            serde_json::to_string_pretty(&cosig).unwrap(),
+        )
+        .unwrap();
+
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
         match verdict {
             Verdict::Unverified(errors) => {
-                assert!(errors.iter().any(|e| e.contains("does not exist")));
+                assert!(
+                    errors.iter().any(|e| e.contains("INVALID")),
+                    "Expected invalid statement signature error, got: {:?}",
+                    errors
+                );
             }
-            _ => panic!("Expected Unverified for nonexistent dir"),
+            _ => panic!("Expected Unverified for tampered statement, got {:?}", verdict),
+        }
+    }
+
+    #[test]
+    fn verify_required_statement_missing_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        let (cosigner_sk, cosigner_pk) = sign::keygen();
+        let manifest = rewrite_policy_and_resign(
+            &bundle,
+            SigningRequirement {
+                require_maintainer_cosign_for_release: Some(true),
+                trusted_cosigner_keys: Some(vec![TrustedCosignerKey {
+                    key_id: "maintainer@org".to_string(),
+                    key_type: None,
+                    public_key_ed25519: cosigner_pk.clone(),
+                    public_key: None,
+                }]),
+                required_statements: Some(vec!["deployed-to-prod".to_string()]),
+                timestamp: None,
+                trusted_builder_keys: None,
+                require_verified_oidc: None,
+            },
+        );
+
+        // A valid "reviewed" statement does not satisfy "deployed-to-prod"
+        let cosig = signed_statement(&manifest, &cosigner_sk, &cosigner_pk, "reviewed");
+        fs::write(
+            bundle.join("signatures/maintainer_org.cosig.json"),
+            serde_json::to_string_pretty(&cosig).unwrap(),
+        )
+        .unwrap();
+
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
+        match verdict {
+            Verdict::Unverified(errors) => {
+                assert!(
+                    errors
+                        .iter()
+                        .any(|e| e.contains("requires a 'deployed-to-prod' co-signature")),
+                    "Expected required statement error, got: {:?}",
+                    errors
+                );
+            }
+            _ => panic!("Expected Unverified when statement missing, got {:?}", verdict),
+        }
+    }
+
+    /// Helper: re-sign the bundle's manifest with a fresh key of `algorithm`,
+    /// writing the builder signature under its algorithm-tagged filename.
+    fn resign_with_algorithm(dir: &Path, algorithm: sign::Algorithm) {
+        let manifest_json = fs::read_to_string(dir.join("manifest.json")).unwrap();
+        let mut manifest: Manifest = serde_json::from_str(&manifest_json).unwrap();
+
+        let (sk, pk) = sign::keygen_with(algorithm);
+        let (public_key_ed25519, public_key) = public_key_fields(algorithm, pk);
+        manifest.builder_identity.key_type = Some(algorithm);
+        manifest.builder_identity.public_key_ed25519 = public_key_ed25519;
+        manifest.builder_identity.public_key = public_key;
+
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::remove_file(dir.join("signatures/builder.ed25519.sig")).unwrap();
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("signatures")
+                .join(manifest.builder_identity.signature_filename()),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_ecdsa_p256_builder_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        resign_with_algorithm(&bundle, sign::Algorithm::EcdsaP256);
+
+        assert!(bundle.join("signatures/builder.ecdsa-p256.sig").exists());
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
+        assert_eq!(verdict, Verdict::Verified);
+    }
+
+    #[test]
+    fn verify_rejects_builder_signature_for_undeclared_algorithm() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        resign_with_algorithm(&bundle, sign::Algorithm::EcdsaP256);
+
+        // A leftover Ed25519 builder signature is not what the manifest declares
+        fs::write(bundle.join("signatures/builder.ed25519.sig"), "stale").unwrap();
+
+        let verdict = run_verify(&bundle, &VerifyOptions::default()).unwrap();
+        match verdict {
+            Verdict::Unverified(errors) => {
+                assert!(
+                    errors
+                        .iter()
+                        .any(|e| e.contains("Unexpected file") && e.contains("builder.ed25519.sig")),
+                    "Expected unexpected builder signature error, got: {:?}",
+                    errors
+                );
+            }
+            _ => panic!("Expected Unverified, got {:?}", verdict),
         }
     }
+
+    /// Re-sign the test bundle with a fresh hybrid Ed25519 + ML-DSA-65 key.
+    fn resign_hybrid(dir: &Path) {
+        let manifest_json = fs::read_to_string(dir.join("manifest.json")).unwrap();
+        let mut manifest: Manifest = serde_json::from_str(&manifest_json).unwrap();
+
+        let (sk, pk, pq_pk) = sign::keygen_hybrid(sign::Algorithm::Ed25519);
+        manifest.builder_identity.key_type = Some(sign::Algorithm::Ed25519);
+        manifest.builder_identity.public_key_ed25519 = pk;
+        manifest.builder_identity.hybrid = Some(HybridKey {
+            key_type: sign::Algorithm::MlDsa65,
+            public_key: pq_pk,
+        });
+
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        for signer in sign::keyset_from_secret(&sk).unwrap().signers() {
+            fs::write(
+                dir.join("signatures")
+                    .join(builder_signature_filename(signer.algorithm())),
+                signer.sign(&canonical_bytes).unwrap(),
+            )
+            .unwrap();
+        }
+        fs::write(
+            dir.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_hybrid_bundle_requires_both_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        resign_hybrid(&bundle);
+
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        // Dropping the post-quantum signature must fail closed
+        let pq_sig = bundle.join("signatures/builder.ml-dsa-65.sig");
+        let saved = fs::read_to_string(&pq_sig).unwrap();
+        fs::remove_file(&pq_sig).unwrap();
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains("builder.ml-dsa-65.sig")),
+                "Expected missing ML-DSA signature error, got: {:?}",
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+
+        // A valid Ed25519 signature does not excuse a bad ML-DSA one
+        let (other_sk, _) = sign::keygen_with(sign::Algorithm::MlDsa65);
+        fs::write(&pq_sig, sign::sign(&other_sk, b"not the manifest").unwrap()).unwrap();
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains("INVALID") && e.contains("ml-dsa-65")),
+                "Expected invalid ML-DSA signature error, got: {:?}",
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+
+        fs::write(&pq_sig, saved).unwrap();
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+    }
+
+    #[test]
+    fn verify_rejects_stripped_hybrid_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        resign_hybrid(&bundle);
+
+        // Downgrade attempt: remove the hybrid key and its signature. The
+        // classical signature covered the hybrid key, so it no longer verifies.
+        let manifest_json = fs::read_to_string(bundle.join("manifest.json")).unwrap();
+        let mut manifest: Manifest = serde_json::from_str(&manifest_json).unwrap();
+        manifest.builder_identity.hybrid = None;
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::write(
+            bundle.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+        fs::remove_file(bundle.join("signatures/builder.ml-dsa-65.sig")).unwrap();
+
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains("Builder signature INVALID")),
+                "Expected invalid builder signature, got: {:?}",
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn verify_checks_ssh_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        let (sk, pk) = sign::keygen();
+
+        let set_fingerprint = |fingerprint: String| {
+            let manifest_json = fs::read_to_string(bundle.join("manifest.json")).unwrap();
+            let mut manifest: Manifest = serde_json::from_str(&manifest_json).unwrap();
+            manifest.builder_identity.public_key_ed25519 = pk.clone();
+            manifest.builder_identity.ssh_fingerprint = Some(fingerprint);
+            let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+            fs::write(
+                bundle.join("manifest.json"),
+                serde_json::to_string_pretty(&manifest).unwrap(),
+            )
+            .unwrap();
+            fs::write(
+                bundle.join("signatures/builder.ed25519.sig"),
+                sign::sign(&sk, &canonical_bytes).unwrap(),
+            )
+            .unwrap();
+            fs::write(
+                bundle.join("hashes/manifest.sha256"),
+                hash::sha256_hex(&canonical_bytes),
+            )
+            .unwrap();
+        };
+
+        set_fingerprint(sign::ed25519_fingerprint(&pk).unwrap());
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        set_fingerprint("SHA256:xz+TGVw+NzEN6OSO0Y+fbVcdNa+Pv1uY+7AxDGVFM0M".to_string());
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains("ssh_fingerprint")),
+                "Expected fingerprint mismatch, got: {:?}",
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn verify_nonexistent_bundle_dir() {
+        let verdict = run_verify(Path::new("/nonexistent/path/vbw"), &VerifyOptions::default()).unwrap();
+        match verdict {
+            Verdict::Unverified(errors) => {
+                assert!(errors.iter().any(|e| e.contains("does not exist")));
+            }
+            _ => panic!("Expected Unverified for nonexistent dir"),
+        }
+    }
+
+    /// Bundle signed "now" by a known key, with a timestamp policy trusting
+    /// `tsa` and `trusted_builder_keys` built from the key by `keys`.
+    fn timestamped_bundle(
+        dir: &Path,
+        tsa: &crate::tsa::stand_in::StandInTsa,
+        timestamp: TimestampRequirement,
+        keys: impl Fn(&str) -> Vec<TrustedBuilderKey>,
+    ) {
+        let (sk, pk) = sign::keygen();
+        let signing = SigningRequirement {
+            require_maintainer_cosign_for_release: Some(false),
+            trusted_cosigner_keys: None,
+            required_statements: None,
+            timestamp: Some(timestamp),
+            trusted_builder_keys: Some(keys(&pk)),
+            require_verified_oidc: None,
+        };
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        rewrite_policy_and_resign_with(dir, signing, &sk, &pk, Some(&now));
+
+        let signature = fs::read_to_string(dir.join("signatures/builder.ed25519.sig")).unwrap();
+        let token = tsa.issue(
+            crate::tsa::stand_in::imprint_of(&sign::decode_signature(&signature).unwrap()),
+            None,
+            std::time::SystemTime::now(),
+        );
+        fs::write(dir.join("signatures/builder.ed25519.tst"), token).unwrap();
+    }
+
+    fn builder_key(pk: &str, revoked_at: Option<String>) -> TrustedBuilderKey {
+        let day = chrono::Duration::days(1);
+        TrustedBuilderKey {
+            key_id: "ci".to_string(),
+            key_type: None,
+            public_key_ed25519: pk.to_string(),
+            public_key: None,
+            valid_from: Some((chrono::Utc::now() - day).to_rfc3339()),
+            valid_until: Some((chrono::Utc::now() + day).to_rfc3339()),
+            revoked_at,
+        }
+    }
+
+    fn assert_unverified_with(bundle: &Path, needle: &str) {
+        match run_verify(bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::Unverified(errors) => assert!(
+                errors.iter().any(|e| e.contains(needle)),
+                "Expected error containing '{}', got: {:?}",
+                needle,
+                errors
+            ),
+            v => panic!("Expected Unverified, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn verify_timestamp_sets_key_validity_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        let tsa = crate::tsa::stand_in::StandInTsa::new();
+        let pinned = TimestampRequirement {
+            tsa_url: None,
+            required: Some(true),
+            trusted_tsa_cert_sha256: Some(vec![tsa.cert_sha256()]),
+        };
+
+        // Valid key, trusted token
+        timestamped_bundle(&bundle, &tsa, pinned.clone(), |pk| vec![builder_key(pk, None)]);
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        // Revoked after the timestamp: still valid
+        let later = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        timestamped_bundle(&bundle, &tsa, pinned.clone(), |pk| {
+            vec![builder_key(pk, Some(later.clone()))]
+        });
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        // Revoked before the timestamp
+        let earlier = (chrono::Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        timestamped_bundle(&bundle, &tsa, pinned.clone(), |pk| {
+            vec![builder_key(pk, Some(earlier.clone()))]
+        });
+        assert_unverified_with(&bundle, "was revoked");
+
+        // Key not listed at all
+        timestamped_bundle(&bundle, &tsa, pinned, |_| {
+            vec![builder_key(&sign::keygen().1, None)]
+        });
+        assert_unverified_with(&bundle, "not in policy trusted_builder_keys");
+    }
+
+    #[test]
+    fn verify_rejects_untrusted_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        let tsa = crate::tsa::stand_in::StandInTsa::new();
+        let pinned_to = |pin: String| TimestampRequirement {
+            tsa_url: None,
+            required: Some(true),
+            trusted_tsa_cert_sha256: Some(vec![pin]),
+        };
+
+        // Token from a TSA that is not pinned
+        let other = crate::tsa::stand_in::StandInTsa::new();
+        timestamped_bundle(&bundle, &tsa, pinned_to(other.cert_sha256()), |pk| {
+            vec![builder_key(pk, None)]
+        });
+        assert_unverified_with(&bundle, "untrusted TSA");
+
+        // Token over a different signature
+        timestamped_bundle(&bundle, &tsa, pinned_to(tsa.cert_sha256()), |pk| {
+            vec![builder_key(pk, None)]
+        });
+        let stale = tsa.issue(
+            crate::tsa::stand_in::imprint_of(b"some other signature"),
+            None,
+            std::time::SystemTime::now(),
+        );
+        fs::write(bundle.join("signatures/builder.ed25519.tst"), stale).unwrap();
+        assert_unverified_with(&bundle, "Timestamp token INVALID");
+
+        // Required but missing
+        fs::remove_file(bundle.join("signatures/builder.ed25519.tst")).unwrap();
+        assert_unverified_with(&bundle, "requires a trusted timestamp");
+
+        // A revoked key cannot rely on self-reported created_at
+        let later = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        let unpinned = TimestampRequirement::default();
+        timestamped_bundle(&bundle, &tsa, unpinned, |pk| {
+            vec![builder_key(pk, Some(later.clone()))]
+        });
+        assert_unverified_with(&bundle, "trusted timestamp is required");
+    }
+
+    /// Write a signed step sub-witness to steps/<name>/ and return its ref.
+    fn write_test_step(
+        bundle: &Path,
+        name: &str,
+        inputs: Vec<MaterialEntry>,
+        outputs: Vec<Artifact>,
+    ) -> StepRef {
+        let dir = bundle.join("steps").join(name);
+        fs::create_dir_all(dir.join("signatures")).unwrap();
+        let materials = serde_json::to_string_pretty(&MaterialsLock {
+            lockfiles: vec![],
+            materials: inputs,
+        })
+        .unwrap();
+        let outputs = serde_json::to_string_pretty(&Outputs { artifacts: outputs }).unwrap();
+        let environment = fs::read_to_string(bundle.join("environment.json")).unwrap();
+        let transcript = format!("[step {}]\n", name);
+        for (file, contents) in [
+            ("environment.json", &environment),
+            ("materials.lock.json", &materials),
+            ("outputs.json", &outputs),
+            ("transcript.txt", &transcript),
+        ] {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(bundle.join("manifest.json")).unwrap())
+                .unwrap();
+        let (sk, pk) = sign::keygen();
+        let mut identity = manifest.builder_identity.clone();
+        identity.public_key_ed25519 = pk;
+        let step = StepManifest {
+            vbw_version: "1.0".to_string(),
+            step: name.to_string(),
+            build_id: format!("step-{}", name),
+            created_at: manifest.created_at.clone(),
+            git: manifest.git.clone(),
+            source_commit_tree_hash: manifest.source_commit_tree_hash.clone(),
+            command: vec!["make".to_string(), name.to_string()],
+            environment_hash: hash::sha256_hex(environment.as_bytes()),
+            materials_lock_hash: hash::sha256_hex(materials.as_bytes()),
+            outputs_hash: hash::sha256_hex(outputs.as_bytes()),
+            transcript_hash: hash::sha256_hex(transcript.as_bytes()),
+            policy_hash: manifest.policy_ref.hash_sha256.clone(),
+            builder_identity: identity,
+            build_result: None,
+            enforcement: None,
+        };
+        let canonical_bytes = canonical::canonical_manifest_bytes(&step);
+        fs::write(dir.join("step.json"), serde_json::to_string_pretty(&step).unwrap()).unwrap();
+        fs::write(
+            dir.join("signatures/builder.ed25519.sig"),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        StepRef {
+            name: name.to_string(),
+            step_hash: hash::sha256_hex(&canonical_bytes),
+        }
+    }
+
+    /// Set manifest.steps and re-sign the manifest with a fresh key.
+    fn set_steps_and_resign(bundle: &Path, steps: Vec<StepRef>) {
+        let mut manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(bundle.join("manifest.json")).unwrap())
+                .unwrap();
+        manifest.steps = Some(steps);
+        let (sk, pk) = sign::keygen();
+        manifest.builder_identity.public_key_ed25519 = pk;
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::write(
+            bundle.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("signatures/builder.ed25519.sig"),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+    }
+
+    fn step_artifact(path: &str, sha256: &str) -> Artifact {
+        Artifact {
+            path: path.to_string(),
+            sha256: sha256.to_string(),
+            size_bytes: 1,
+            mime: None,
+            build_id: None,
+            notes: None,
+        }
+    }
+
+    fn step_input(step: &str, path: &str, sha256: &str) -> MaterialEntry {
+        MaterialEntry {
+            name: path.to_string(),
+            kind: STEP_OUTPUT_KIND.to_string(),
+            source: Some(step.to_string()),
+            sha256: sha256.to_string(),
+            upstream_manifest_hash: None,
+            upstream_path: None,
+            archive_sha256: None,
+            extracted_tree_hash: None,
+        }
+    }
+
+    #[test]
+    fn verify_pipeline_steps_link_inputs_to_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        let (gen_hash, obj_hash) = ("1".repeat(64), "2".repeat(64));
+
+        let codegen = write_test_step(
+            &bundle,
+            "codegen",
+            vec![],
+            vec![step_artifact("gen/api.rs", &gen_hash)],
+        );
+        let compile = write_test_step(
+            &bundle,
+            "compile",
+            vec![step_input("codegen", "gen/api.rs", &gen_hash)],
+            vec![step_artifact("target/app", &obj_hash)],
+        );
+        set_steps_and_resign(&bundle, vec![codegen.clone(), compile.clone()]);
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        // Steps out of order: compile's input is not from an earlier step
+        set_steps_and_resign(&bundle, vec![compile.clone(), codegen.clone()]);
+        assert_unverified_with(&bundle, "which is not an earlier step");
+
+        // A tampered step transcript breaks the step's component hash
+        set_steps_and_resign(&bundle, vec![codegen.clone(), compile.clone()]);
+        fs::write(bundle.join("steps/codegen/transcript.txt"), "edited").unwrap();
+        assert_unverified_with(&bundle, "Step 'codegen' transcript.txt hash mismatch");
+    }
+
+    #[test]
+    fn verify_pipeline_rejects_mismatched_inputs_and_unlisted_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        // compile (validly signed) claims an input codegen never produced
+        let codegen = write_test_step(
+            &bundle,
+            "codegen",
+            vec![],
+            vec![step_artifact("gen/api.rs", &"1".repeat(64))],
+        );
+        let compile = write_test_step(
+            &bundle,
+            "compile",
+            vec![step_input("codegen", "gen/api.rs", &"9".repeat(64))],
+            vec![],
+        );
+        set_steps_and_resign(&bundle, vec![codegen.clone(), compile]);
+        assert_unverified_with(&bundle, "does not match any output of step 'codegen'");
+
+        // A step directory the manifest does not list
+        fs::remove_dir_all(bundle.join("steps/compile")).unwrap();
+        set_steps_and_resign(&bundle, vec![]);
+        assert_unverified_with(&bundle, "Unexpected step in bundle: steps/codegen");
+
+        // A stray file inside a step directory
+        set_steps_and_resign(&bundle, vec![codegen]);
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+        fs::write(bundle.join("steps/codegen/notes.txt"), "x").unwrap();
+        assert_unverified_with(&bundle, "Unexpected file in bundle: steps/codegen/notes.txt");
+        fs::remove_file(bundle.join("steps/codegen/notes.txt")).unwrap();
+
+        // A (validly signed) step claiming an OIDC identity it has no token for
+        let step_dir = bundle.join("steps/codegen");
+        let mut step: StepManifest =
+            serde_json::from_str(&fs::read_to_string(step_dir.join("step.json")).unwrap()).unwrap();
+        let (sk, pk) = sign::keygen();
+        step.builder_identity.public_key_ed25519 = pk;
+        step.builder_identity.issuer = Some("https://token.actions.githubusercontent.com".into());
+        let canonical_bytes = canonical::canonical_manifest_bytes(&step);
+        fs::write(
+            step_dir.join("step.json"),
+            serde_json::to_string_pretty(&step).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            step_dir.join("signatures/builder.ed25519.sig"),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        set_steps_and_resign(
+            &bundle,
+            vec![StepRef {
+                name: "codegen".to_string(),
+                step_hash: hash::sha256_hex(&canonical_bytes),
+            }],
+        );
+        assert_unverified_with(&bundle, "Step 'codegen' claims an OIDC identity");
+    }
+
+    /// Replace materials.lock.json or outputs.json, update its hash in the
+    /// manifest and re-sign. Returns the new canonical manifest hash.
+    fn set_component_and_resign(bundle: &Path, file: &str, contents: &str) -> String {
+        let mut manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(bundle.join("manifest.json")).unwrap())
+                .unwrap();
+        let component_hash = hash::sha256_hex(contents.as_bytes());
+        match file {
+            "materials.lock.json" => manifest.materials_lock_hash = component_hash,
+            "outputs.json" => manifest.outputs_hash = component_hash,
+            _ => panic!("unsupported component {}", file),
+        }
+        fs::write(bundle.join(file), contents).unwrap();
+        let (sk, pk) = sign::keygen();
+        manifest.builder_identity.public_key_ed25519 = pk;
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        let manifest_hash = hash::sha256_hex(&canonical_bytes);
+        fs::write(
+            bundle.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("signatures/builder.ed25519.sig"),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(bundle.join("hashes/manifest.sha256"), &manifest_hash).unwrap();
+        manifest_hash
+    }
+
+    #[test]
+    fn verify_checks_vbw_artifact_materials_against_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let (upstream, downstream) = (dir.path().join("lib"), dir.path().join("app"));
+        fs::create_dir(&upstream).unwrap();
+        fs::create_dir(&downstream).unwrap();
+        create_test_bundle(&upstream);
+        create_test_bundle(&downstream);
+
+        let lib_hash = "3".repeat(64);
+        let outputs = Outputs {
+            artifacts: vec![step_artifact("dist/libfoo.a", &lib_hash)],
+        };
+        let upstream_hash = set_component_and_resign(
+            &upstream,
+            "outputs.json",
+            &serde_json::to_string_pretty(&outputs).unwrap(),
+        );
+        let consume = |sha256: &str| {
+            let materials = MaterialsLock {
+                lockfiles: vec![],
+                materials: vec![MaterialEntry {
+                    name: "vendor/libfoo.a".to_string(),
+                    kind: VBW_ARTIFACT_KIND.to_string(),
+                    source: Some("test".to_string()),
+                    sha256: sha256.to_string(),
+                    upstream_manifest_hash: Some(upstream_hash.clone()),
+                    upstream_path: Some("dist/libfoo.a".to_string()),
+                    archive_sha256: None,
+                    extracted_tree_hash: None,
+                }],
+            };
+            set_component_and_resign(
+                &downstream,
+                "materials.lock.json",
+                &serde_json::to_string_pretty(&materials).unwrap(),
+            );
+        };
+        consume(&lib_hash);
+        let upstreams = [upstream.clone()];
+        let with_upstreams = VerifyOptions {
+            upstream_dirs: &upstreams,
+            ..Default::default()
+        };
+        assert_eq!(run_verify(&downstream, &with_upstreams).unwrap(), Verdict::Verified);
+
+        // Without the upstream bundle the provenance is unchecked
+        match run_verify(&downstream, &VerifyOptions::default()).unwrap() {
+            Verdict::VerifiedWithVariance(w) => {
+                assert!(w.iter().any(|w| w.contains("was not supplied with --upstream")))
+            }
+            other => panic!("expected variance, got {:?}", other),
+        }
+
+        // The consumed file is not what upstream built
+        consume(&"4".repeat(64));
+        match run_verify(&downstream, &with_upstreams).unwrap() {
+            Verdict::Unverified(e) => assert!(
+                e.iter().any(|e| e.contains("does not match artifact dist/libfoo.a")),
+                "{:?}",
+                e
+            ),
+            other => panic!("expected UNVERIFIED, got {:?}", other),
+        }
+
+        // A tampered upstream outputs.json fails the upstream bundle's checks
+        consume(&lib_hash);
+        fs::write(upstream.join("outputs.json"), "{\"artifacts\": []}").unwrap();
+        match run_verify(&downstream, &with_upstreams).unwrap() {
+            Verdict::Unverified(e) => {
+                assert!(e.iter().any(|e| e.contains("outputs.json hash mismatch")), "{:?}", e)
+            }
+            other => panic!("expected UNVERIFIED, got {:?}", other),
+        }
+    }
+
+    /// Apply `update` to the manifest and re-sign it with a fresh key.
+    fn update_manifest_and_resign(bundle: &Path, update: impl FnOnce(&mut Manifest)) {
+        update_manifest_and_resign_with(bundle, sign::keygen(), update);
+    }
+
+    /// Apply `update` to the manifest and re-sign it with the Ed25519 key
+    /// pair `(sk, pk)`.
+    fn update_manifest_and_resign_with(
+        bundle: &Path,
+        (sk, pk): (String, String),
+        update: impl FnOnce(&mut Manifest),
+    ) {
+        let mut manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(bundle.join("manifest.json")).unwrap())
+                .unwrap();
+        update(&mut manifest);
+        manifest.builder_identity.public_key_ed25519 = pk;
+        let canonical_bytes = canonical::canonical_manifest_bytes(&manifest);
+        fs::write(
+            bundle.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("signatures/builder.ed25519.sig"),
+            sign::sign(&sk, &canonical_bytes).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            bundle.join("hashes/manifest.sha256"),
+            hash::sha256_hex(&canonical_bytes),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_checks_network_log_and_reports_violations() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        // A stray network.json the manifest does not reference
+        fs::write(bundle.join(NETWORK_LOG_FILE), "{}").unwrap();
+        assert_unverified_with(&bundle, "manifest has no network_log_hash");
+
+        let log = serde_json::to_string_pretty(&NetworkLog {
+            proxy: "127.0.0.1:40000".to_string(),
+            allowlist: vec!["registry.npmjs.org".to_string()],
+            connections: vec![NetworkConnection {
+                time: "2026-01-01T00:00:00.000Z".to_string(),
+                method: "CONNECT".to_string(),
+                host: "evil.test".to_string(),
+                port: 443,
+                allowed: false,
+            }],
+        })
+        .unwrap();
+        fs::write(bundle.join(NETWORK_LOG_FILE), &log).unwrap();
+        update_manifest_and_resign(&bundle, |m| {
+            m.network_log_hash = Some(hash::sha256_hex(log.as_bytes()));
+            m.enforcement.as_mut().unwrap().network_violations =
+                Some(vec!["evil.test:443".to_string()]);
+        });
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::VerifiedWithVariance(w) => assert!(
+                w.iter().any(|w| w.contains("outside the network allowlist")
+                    && w.contains("evil.test:443")),
+                "{:?}",
+                w
+            ),
+            other => panic!("expected variance, got {:?}", other),
+        }
+
+        // Editing the log (hiding the attempt) breaks its hash
+        fs::write(bundle.join(NETWORK_LOG_FILE), log.replace("evil.test", "ok.test")).unwrap();
+        assert_unverified_with(&bundle, "network.json hash mismatch");
+    }
+
+    #[test]
+    fn verify_checks_transcript_and_build_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        let mut result = BuildResult {
+            success: true,
+            exit_code: Some(0),
+            signal: None,
+            wall_time_ms: 1500,
+            user_cpu_ms: Some(900),
+            system_cpu_ms: Some(100),
+            max_rss_kib: Some(2048),
+        };
+        update_manifest_and_resign(&bundle, |m| {
+            m.transcript_hash = Some(hash::sha256_hex(b"test transcript\n"));
+            m.build_result = Some(result.clone());
+        });
+        assert_eq!(
+            run_verify(&bundle, &VerifyOptions::default()).unwrap(),
+            Verdict::Verified
+        );
+
+        // A signed transcript cannot be edited
+        fs::write(bundle.join("transcript.txt"), "all good\n").unwrap();
+        assert_unverified_with(&bundle, "transcript.txt hash mismatch");
+        fs::write(bundle.join("transcript.txt"), "test transcript\n").unwrap();
+
+        // A failed build's bundle is authentic but not VERIFIED
+        result.success = false;
+        result.exit_code = Some(2);
+        update_manifest_and_resign(&bundle, |m| m.build_result = Some(result));
+        assert_unverified_with(&bundle, "Build command failed (exit code 2)");
+    }
+
+    #[test]
+    fn verify_checks_jsonl_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        // Both transcript names at once
+        let transcript = concat!(
+            r#"{"time":"2026-01-01T00:00:00.000Z","stream":"stdout","text":"50%\r100%\n"}"#,
+            "\n",
+            r#"{"time":"2026-01-01T00:00:00.001Z","stream":"stderr","base64":"/w=="}"#,
+            "\n",
+        );
+        fs::write(bundle.join(TRANSCRIPT_FILE), transcript).unwrap();
+        assert_unverified_with(&bundle, "transcript.txt (bundle also has transcript.jsonl)");
+
+        // transcript.jsonl must be signed
+        fs::remove_file(bundle.join(LEGACY_TRANSCRIPT_FILE)).unwrap();
+        assert_unverified_with(&bundle, "manifest has no transcript_hash");
+
+        update_manifest_and_resign(&bundle, |m| {
+            m.transcript_hash = Some(hash::sha256_hex(transcript.as_bytes()));
+        });
+        assert_eq!(
+            run_verify(&bundle, &VerifyOptions::default()).unwrap(),
+            Verdict::Verified
+        );
+
+        fs::write(bundle.join(TRANSCRIPT_FILE), transcript.replace("/w==", "AA==")).unwrap();
+        assert_unverified_with(&bundle, "transcript.jsonl hash mismatch");
+
+        fs::remove_file(bundle.join(TRANSCRIPT_FILE)).unwrap();
+        assert_unverified_with(&bundle, "Required file missing: transcript.jsonl");
+    }
+
+    #[test]
+    fn verify_warns_when_clean_environment_is_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        /// Rewrite a JSON component and return its new hash.
+        fn rewrite<T: serde::Serialize + serde::de::DeserializeOwned>(
+            path: &Path,
+            update: impl FnOnce(&mut T),
+        ) -> String {
+            let mut value: T = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            update(&mut value);
+            let json = serde_json::to_string_pretty(&value).unwrap();
+            fs::write(path, &json).unwrap();
+            hash::sha256_hex(json.as_bytes())
+        }
+
+        let policy_hash = rewrite(&bundle.join("policy.json"), |p: &mut Policy| {
+            p.requirements.environment = Some(EnvironmentRequirement {
+                clean: Some(true),
+                ..Default::default()
+            });
+        });
+        update_manifest_and_resign(&bundle, |m| m.policy_ref.hash_sha256 = policy_hash);
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::VerifiedWithVariance(w) => assert!(
+                w.iter().any(|w| w.contains("clean build environment")),
+                "{:?}",
+                w
+            ),
+            other => panic!("expected variance, got {:?}", other),
+        }
+
+        let env_hash = rewrite(&bundle.join("environment.json"), |e: &mut Environment| {
+            e.build_env = Some(std::collections::BTreeMap::from([(
+                "PATH".to_string(),
+                "/usr/bin:/bin".to_string(),
+            )]));
+        });
+        update_manifest_and_resign(&bundle, |m| m.environment_hash = env_hash);
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+    }
+
+    #[test]
+    fn verify_checks_traced_network_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        fs::write(bundle.join(NETWORK_ACCESS_FILE), "{}").unwrap();
+        assert_unverified_with(&bundle, "manifest has no network_access_hash");
+
+        let access = serde_json::to_string_pretty(&NetworkAccessLog {
+            connections: vec![TracedConnection {
+                address: "104.16.0.35".to_string(),
+                port: 443,
+                count: 2,
+            }],
+            dns_queries: vec![DnsQuery {
+                name: "registry.npmjs.org".to_string(),
+                count: 2,
+            }],
+        })
+        .unwrap();
+        fs::write(bundle.join(NETWORK_ACCESS_FILE), &access).unwrap();
+        update_manifest_and_resign(&bundle, |m| {
+            m.network_access_hash = Some(hash::sha256_hex(access.as_bytes()));
+        });
+        assert_eq!(run_verify(&bundle, &VerifyOptions::default()).unwrap(), Verdict::Verified);
+
+        fs::write(
+            bundle.join(NETWORK_ACCESS_FILE),
+            access.replace("registry.npmjs.org", "example.org"),
+        )
+        .unwrap();
+        assert_unverified_with(&bundle, "network-access.json hash mismatch");
+    }
+
+    #[test]
+    fn verify_checks_traced_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+
+        fs::write(bundle.join(INPUTS_FILE), "{}").unwrap();
+        assert_unverified_with(&bundle, "manifest has no inputs_hash");
+
+        let inputs = serde_json::to_string_pretty(&InputsLog {
+            files: vec![
+                InputFile {
+                    path: "config.local.json".to_string(),
+                    class: InputClass::UntrackedInRepo,
+                    count: 1,
+                    sha256: Some(hash::sha256_hex(b"{}")),
+                },
+                InputFile {
+                    path: "src/main.rs".to_string(),
+                    class: InputClass::TrackedInGit,
+                    count: 1,
+                    sha256: None,
+                },
+            ],
+        })
+        .unwrap();
+        fs::write(bundle.join(INPUTS_FILE), &inputs).unwrap();
+        update_manifest_and_resign(&bundle, |m| {
+            m.inputs_hash = Some(hash::sha256_hex(inputs.as_bytes()));
+            m.enforcement.as_mut().unwrap().untracked_inputs =
+                Some(vec!["config.local.json".to_string()]);
+        });
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::VerifiedWithVariance(w) => assert!(
+                w.iter()
+                    .any(|w| w.contains("provenance gap") && w.contains("config.local.json")),
+                "{:?}",
+                w
+            ),
+            other => panic!("expected variance, got {:?}", other),
+        }
+
+        fs::write(
+            bundle.join(INPUTS_FILE),
+            inputs.replace("src/main.rs", "src/lib.rs"),
+        )
+        .unwrap();
+        assert_unverified_with(&bundle, "inputs.json hash mismatch");
+    }
+
+    #[test]
+    fn verify_checks_oidc_binding() {
+        use crate::oidc::stand_in::{github_claims, StandInIssuer};
+
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("vbw");
+        fs::create_dir(&bundle).unwrap();
+        create_test_bundle(&bundle);
+        let issuer = StandInIssuer::es256();
+        let jwks = dir.path().join("jwks.json");
+        fs::write(&jwks, issuer.jwks()).unwrap();
+        let with_jwks = VerifyOptions {
+            oidc_jwks: Some(&jwks),
+            ..Default::default()
+        };
+
+        // Re-sign the bundle with a fresh key bound by a token minted at
+        // created_at. `claims` may alter the token; `audience_key` is the
+        // key the audience names (the builder key when None).
+        let bind = |claims: &dyn Fn(&mut serde_json::Value), audience_key: Option<&str>| {
+            let (sk, pk) = sign::keygen();
+            let audience = crate::oidc::key_audience(audience_key.unwrap_or(&pk)).unwrap();
+            let created_at = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+                .unwrap()
+                .timestamp();
+            let mut token_claims = github_claims(&audience, "aabbccddee");
+            token_claims["iat"] = created_at.into();
+            token_claims["nbf"] = created_at.into();
+            token_claims["exp"] = (created_at + 300).into();
+            claims(&mut token_claims);
+            let token = issuer.mint(&token_claims);
+            fs::write(bundle.join("signatures").join(OIDC_TOKEN_FILE), &token).unwrap();
+            let decoded = crate::oidc::decode(&token).unwrap();
+            update_manifest_and_resign_with(&bundle, (sk, pk), |m| {
+                m.builder_identity.issuer = Some(decoded.claims.iss.clone());
+                m.builder_identity.oidc =
+                    Some(OidcBinding::from_token(&token, &decoded.claims, &audience));
+            });
+        };
+
+        // Record the CI repository the token names
+        let update_json = |file: &str, update: &dyn Fn(&mut serde_json::Value)| {
+            let path = bundle.join(file);
+            let mut value: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            update(&mut value);
+            let json = serde_json::to_string_pretty(&value).unwrap();
+            fs::write(&path, &json).unwrap();
+            hash::sha256_hex(json.as_bytes())
+        };
+        let environment_hash = update_json("environment.json", &|e| {
+            e["ci"] = serde_json::json!({
+                "provider": "github-actions",
+                "repository": "acme/widget",
+            })
+        });
+        update_manifest_and_resign(&bundle, |m| m.environment_hash = environment_hash);
+
+        bind(&|_| {}, None);
+        assert_eq!(run_verify(&bundle, &with_jwks).unwrap(), Verdict::Verified);
+        // Without the issuer's keys the signature is unchecked
+        match run_verify(&bundle, &VerifyOptions::default()).unwrap() {
+            Verdict::VerifiedWithVariance(w) => {
+                assert!(w.iter().any(|w| w.contains("pass --oidc-jwks")), "{:?}", w)
+            }
+            other => panic!("expected variance, got {:?}", other),
+        }
+
+        // Claims that contradict the manifest's git state
+        bind(&|c| c["sha"] = "ffffffffff".into(), None);
+        assert_unverified_with(&bundle, "OIDC token is for commit ffffffffff");
+        bind(&|c| c["ref"] = "refs/heads/dev".into(), None);
+        assert_unverified_with(&bundle, "does not match the manifest's git branch/tag");
+        bind(&|c| c["repository"] = "evil/fork".into(), None);
+        assert_unverified_with(&bundle, "OIDC token is for repository evil/fork");
+
+        // A token minted for another key
+        let (_, other_pk) = sign::keygen();
+        bind(&|_| {}, Some(&other_pk));
+        assert_unverified_with(&bundle, "audience is not bound to the builder key");
+
+        // A token swapped after signing
+        bind(&|_| {}, None);
+        let token_path = bundle.join("signatures").join(OIDC_TOKEN_FILE);
+        let other = issuer.mint(&github_claims("vbw:sha256:00", "aabbccddee"));
+        fs::write(&token_path, other).unwrap();
+        assert_unverified_with(&bundle, "builder_identity.oidc does not match the claims");
+
+        // A token signed by someone else
+        bind(&|_| {}, None);
+        fs::write(&jwks, StandInIssuer::es256().jwks()).unwrap();
+        match run_verify(&bundle, &with_jwks).unwrap() {
+            Verdict::Unverified(e) => {
+                assert!(e.iter().any(|e| e.contains("OIDC token signature")), "{:?}", e)
+            }
+            other => panic!("expected UNVERIFIED, got {:?}", other),
+        }
+
+        // A token file without a binding in the manifest
+        update_manifest_and_resign(&bundle, |m| {
+            m.builder_identity.issuer = None;
+            m.builder_identity.oidc = None;
+        });
+        assert_unverified_with(&bundle, "manifest has no builder_identity.oidc");
+
+        // A policy requiring a verified identity turns an unchecked token,
+        // or none at all, into a failure
+        fs::write(&jwks, issuer.jwks()).unwrap();
+        let policy_hash = update_json("policy.json", &|p| {
+            p["requirements"]["signing"]["require_verified_oidc"] = true.into()
+        });
+        update_manifest_and_resign(&bundle, |m| m.policy_ref.hash_sha256 = policy_hash);
+        bind(&|_| {}, None);
+        assert_eq!(run_verify(&bundle, &with_jwks).unwrap(), Verdict::Verified);
+        assert_unverified_with(&bundle, "Policy requires a verified OIDC identity");
+        fs::remove_file(&token_path).unwrap();
+        update_manifest_and_resign(&bundle, |m| {
+            m.builder_identity.issuer = None;
+            m.builder_identity.oidc = None;
+        });
+        assert_unverified_with(&bundle, "the builder key has none");
+    }
 }